@@ -0,0 +1,73 @@
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use tokio::runtime::Runtime;
+use valet::prelude::*;
+
+const RECORDS: usize = 5000;
+
+/// A fresh database with one registered user and an empty lot, plus the
+/// records to import into it.
+async fn setup(db: Database) -> (Database, Lot, Vec<Record>) {
+    let user = User::new("bench", "password".try_into().unwrap())
+        .expect("failed to make user")
+        .register(&db)
+        .await
+        .expect("failed to register user");
+    let mut lot = Lot::new(DEFAULT_LOT);
+    lot.save(&db, &user).await.expect("failed to save lot");
+    let records = (0..RECORDS)
+        .map(|i| {
+            Record::new(
+                &lot,
+                format!("user{i}@example.com").parse::<Label>().unwrap(),
+                Data::new("correct horse battery staple".try_into().unwrap())
+                    .add_extra("notes".into(), format!("imported row {i}")),
+            )
+        })
+        .collect();
+    (db, lot, records)
+}
+
+fn import(c: &mut Criterion) {
+    let rt = Runtime::new().expect("failed to start runtime");
+    let fresh = || {
+        rt.block_on(async {
+            let db = Database::new("sqlite://:memory:")
+                .await
+                .expect("failed to create database");
+            setup(db).await
+        })
+    };
+
+    let mut group = c.benchmark_group("import");
+    group.sample_size(10);
+    group.bench_function("Record::save loop", |b| {
+        b.iter_batched(
+            fresh,
+            |(db, mut lot, records)| {
+                rt.block_on(async {
+                    for record in &records {
+                        record.save(&db, &mut lot).await.expect("failed to save");
+                    }
+                })
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("Record::save_many", |b| {
+        b.iter_batched(
+            fresh,
+            |(db, mut lot, records)| {
+                rt.block_on(async {
+                    Record::save_many(&db, &mut lot, &records, |_| {})
+                        .await
+                        .expect("failed to save")
+                })
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(all, import);
+criterion_main!(all);
@@ -0,0 +1,51 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use tokio::runtime::Runtime;
+use valet::prelude::*;
+
+/// Loading a lot and revealing a large record, the paths that unpack
+/// ciphertext fetched from the database.
+fn load(c: &mut Criterion) {
+    let rt = Runtime::new().expect("failed to start runtime");
+    let (db, user, uuid) = rt.block_on(async {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("bench", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new(DEFAULT_LOT);
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let note: String = (0..4096).map(|i| format!("{i:08x}")).collect();
+        let uuid = Record::new(
+            &lot,
+            "notes".parse::<Label>().unwrap(),
+            Data::new("secret".try_into().unwrap()).add_extra("notes".into(), note),
+        )
+        .save(&db, &mut lot)
+        .await
+        .expect("failed to save record");
+        (db, user, uuid)
+    });
+
+    c.bench_function("Lot::load", |b| {
+        b.iter(|| {
+            rt.block_on(Lot::load(&db, DEFAULT_LOT, &user))
+                .expect("failed to load lot")
+        })
+    });
+    let lot = rt
+        .block_on(Lot::load(&db, DEFAULT_LOT, &user))
+        .expect("failed to load lot")
+        .expect("no lot");
+    c.bench_function("Record::show large", |b| {
+        b.iter(|| {
+            rt.block_on(Record::show(&db, &lot, &uuid))
+                .expect("failed to show record")
+        })
+    });
+}
+
+criterion_group!(all, load);
+criterion_main!(all);
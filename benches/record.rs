@@ -1,4 +1,6 @@
-use criterion::{Criterion, criterion_group, criterion_main};
+use criterion::{BenchmarkGroup, BenchmarkId, Criterion, criterion_group, criterion_main};
+use criterion::{Throughput, measurement::WallTime};
+use std::collections::{BTreeMap, HashMap};
 use valet::{
     encrypt::{Key, Stash},
     prelude::*,
@@ -37,5 +39,94 @@ fn small_data(c: &mut Criterion) {
     });
 }
 
-criterion_group!(all, small_data);
+/// Representative records, from a bare login up to a long note and a
+/// label carrying many searchable fields.
+fn sized_records() -> Vec<(&'static str, Label, Data)> {
+    let login = (
+        "login",
+        "nix@example.com".parse::<Label>().unwrap(),
+        Data::new("correct horse battery staple".try_into().unwrap()),
+    );
+
+    let note: String = (0..200)
+        .map(|i| format!("line {i}: the quick brown fox jumps over the lazy dog\n"))
+        .collect();
+    let large_note = (
+        "large_note",
+        "notes".parse::<Label>().unwrap(),
+        Data::new("secret".try_into().unwrap()).add_extra("notes".into(), note),
+    );
+
+    let label_extra: BTreeMap<String, String> = (0..32)
+        .map(|i| {
+            (
+                format!("field{i}"),
+                format!("https://{i}.example.com/login"),
+            )
+        })
+        .collect();
+    let data_extra: HashMap<String, String> = (0..32)
+        .map(|i| (format!("recovery{i}"), format!("{i:04}-abcd-efgh-{i:04}")))
+        .collect();
+    let many_fields = (
+        "many_fields",
+        "example.com"
+            .parse::<Label>()
+            .unwrap()
+            .with_extra(label_extra)
+            .unwrap(),
+        Data::new("secret".try_into().unwrap()).with_extra(data_extra),
+    );
+
+    vec![login, large_note, many_fields]
+}
+
+/// The full stash pipeline for one value: each stage is benched on its
+/// own so a regression can be pinned to encoding, compression or
+/// encryption.
+fn bench_stash<T: Stash<Lot>>(group: &mut BenchmarkGroup<'_, WallTime>, size: &str, value: &T) {
+    let key = Key::<Lot>::generate();
+    let encoded = value.encode();
+    let compressed = value.compress().expect("failed to compress");
+    let encrypted = value.encrypt(&key).expect("failed to encrypt");
+    group.throughput(Throughput::Bytes(encoded.len() as u64));
+
+    group.bench_with_input(BenchmarkId::new("encode", size), value, |b, v| {
+        b.iter(|| v.encode())
+    });
+    group.bench_with_input(BenchmarkId::new("decode", size), &encoded, |b, buf| {
+        b.iter(|| T::decode(buf).expect("failed to decode"))
+    });
+    group.bench_with_input(BenchmarkId::new("compress", size), value, |b, v| {
+        b.iter(|| v.compress().expect("failed to compress"))
+    });
+    group.bench_with_input(
+        BenchmarkId::new("decompress", size),
+        &compressed,
+        |b, buf| b.iter(|| T::decompress(buf).expect("failed to decompress")),
+    );
+    group.bench_with_input(BenchmarkId::new("encrypt", size), value, |b, v| {
+        b.iter(|| v.encrypt(&key).expect("failed to encrypt"))
+    });
+    group.bench_with_input(BenchmarkId::new("decrypt", size), &encrypted, |b, buf| {
+        b.iter(|| T::decrypt(buf, &key).expect("failed to decrypt"))
+    });
+}
+
+fn sized(c: &mut Criterion) {
+    let records = sized_records();
+    let mut data_group = c.benchmark_group("Data");
+    for (size, _, data) in &records {
+        bench_stash(&mut data_group, size, data);
+    }
+    data_group.finish();
+
+    let mut label_group = c.benchmark_group("Label");
+    for (size, label, _) in &records {
+        bench_stash(&mut label_group, size, label);
+    }
+    label_group.finish();
+}
+
+criterion_group!(all, small_data, sized);
 criterion_main!(all);
@@ -0,0 +1 @@
+ALTER TABLE lots ADD COLUMN parent TEXT REFERENCES lots (uuid) ON DELETE CASCADE;
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS meta (
+    key    TEXT  PRIMARY KEY NOT NULL,
+    value  TEXT  NOT NULL
+);
+
+INSERT OR IGNORE INTO meta (key, value) VALUES ('format_version', '1');
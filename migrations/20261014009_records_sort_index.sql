@@ -0,0 +1,3 @@
+-- Optional user-chosen position of a record within its lot. Not secret:
+-- it only orders records the index already lists by label.
+ALTER TABLE records ADD COLUMN sort_index INTEGER;
@@ -0,0 +1,4 @@
+-- Optional TOTP second factor: the shared secret, encrypted under the
+-- user key. Both NULL when the user hasn't enrolled.
+ALTER TABLE users ADD COLUMN totp_data BLOB;
+ALTER TABLE users ADD COLUMN totp_nonce BLOB;
@@ -0,0 +1,3 @@
+-- Bumped each time a user's password (and so their key) changes, so
+-- sessions holding the old key can tell they are stale.
+ALTER TABLE users ADD COLUMN key_version INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+-- Argon2 cost each user's key is derived with. Existing users keep the
+-- argon2 crate defaults they were registered under.
+ALTER TABLE users ADD COLUMN kdf_memory INTEGER NOT NULL DEFAULT 19456;
+ALTER TABLE users ADD COLUMN kdf_iterations INTEGER NOT NULL DEFAULT 2;
+ALTER TABLE users ADD COLUMN kdf_parallelism INTEGER NOT NULL DEFAULT 1;
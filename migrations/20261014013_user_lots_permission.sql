@@ -0,0 +1,3 @@
+-- What the grantee may do with the lot, 'read' or 'write'. Existing
+-- grants were all made before read-only sharing, so they can write.
+ALTER TABLE user_lots ADD COLUMN permission TEXT NOT NULL DEFAULT 'write';
@@ -0,0 +1,4 @@
+-- When each lot was first and last saved, in Unix seconds. Not secret,
+-- like `sort_index`. NULL for lots saved before these columns existed.
+ALTER TABLE lots ADD COLUMN created_at INTEGER;
+ALTER TABLE lots ADD COLUMN updated_at INTEGER;
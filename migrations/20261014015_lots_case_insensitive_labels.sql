@@ -0,0 +1,3 @@
+-- Whether record names in a lot match ignoring case, see
+-- `Lot::set_case_insensitive_labels`. Off for existing lots.
+ALTER TABLE lots ADD COLUMN case_insensitive_labels INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,8 @@
+-- A user's key wrapped under their recovery phrase, see
+-- `User::generate_recovery`. At most one per user.
+CREATE TABLE IF NOT EXISTS user_recovery (
+    username  TEXT  PRIMARY KEY NOT NULL,
+    data      BLOB  NOT NULL,
+    nonce     BLOB  NOT NULL,
+    FOREIGN KEY (username) REFERENCES users (username) ON DELETE CASCADE
+);
@@ -0,0 +1,22 @@
+-- One row per change, see `Database::audit_log`. Only uuids and the
+-- kind of change are kept, never a name or value. Rows outlive the
+-- lots and records they name, and can't be changed once written.
+CREATE TABLE IF NOT EXISTS audit_log (
+    id      INTEGER  PRIMARY KEY AUTOINCREMENT,
+    at      INTEGER  NOT NULL,
+    op      TEXT     NOT NULL,
+    lot     TEXT     NOT NULL,
+    record  TEXT
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_at ON audit_log (at);
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
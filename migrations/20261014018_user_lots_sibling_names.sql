@@ -0,0 +1,45 @@
+-- Lot names are unique among a user's sibling lots rather than across
+-- all of them, so `work::aws` and `home::aws` can both exist. The parent
+-- lives on `lots`, so triggers enforce this in place of the old
+-- `UNIQUE (username, name)`, which SQLite can only drop by rebuilding
+-- the table.
+CREATE TABLE user_lots_new (
+    username    TEXT  NOT NULL,
+    lot         TEXT  NOT NULL,
+    name        TEXT  NOT NULL,
+    data        BLOB  NOT NULL,
+    nonce       BLOB  NOT NULL,
+    permission  TEXT  NOT NULL DEFAULT 'write',
+    PRIMARY KEY (username, lot),
+    FOREIGN KEY (username) REFERENCES users (username) ON DELETE CASCADE,
+    FOREIGN KEY (lot) REFERENCES lots (uuid) ON DELETE CASCADE
+);
+
+INSERT INTO user_lots_new (username, lot, name, data, nonce, permission)
+    SELECT username, lot, name, data, nonce, permission FROM user_lots;
+
+DROP TABLE user_lots;
+
+ALTER TABLE user_lots_new RENAME TO user_lots;
+
+CREATE INDEX IF NOT EXISTS user_lots_name ON user_lots (username, name);
+
+CREATE TRIGGER IF NOT EXISTS user_lots_sibling_name_insert BEFORE INSERT ON user_lots
+WHEN EXISTS (
+    SELECT 1 FROM user_lots AS ul JOIN lots ON lots.uuid = ul.lot
+    WHERE ul.username = NEW.username AND ul.name = NEW.name AND ul.lot != NEW.lot
+        AND lots.parent IS (SELECT parent FROM lots WHERE uuid = NEW.lot)
+)
+BEGIN
+    SELECT RAISE(ABORT, 'UNIQUE constraint failed: user_lots.username, lots.parent, user_lots.name');
+END;
+
+CREATE TRIGGER IF NOT EXISTS user_lots_sibling_name_update BEFORE UPDATE OF name ON user_lots
+WHEN EXISTS (
+    SELECT 1 FROM user_lots AS ul JOIN lots ON lots.uuid = ul.lot
+    WHERE ul.username = NEW.username AND ul.name = NEW.name AND ul.lot != NEW.lot
+        AND lots.parent IS (SELECT parent FROM lots WHERE uuid = NEW.lot)
+)
+BEGIN
+    SELECT RAISE(ABORT, 'UNIQUE constraint failed: user_lots.username, lots.parent, user_lots.name');
+END;
@@ -0,0 +1,4 @@
+-- Stash buffers now start with a versioned compression header, which
+-- older builds can't read, see `db::FORMAT_VERSION`.
+UPDATE meta SET value = '2'
+WHERE key = 'format_version' AND CAST(value AS INTEGER) < 2;
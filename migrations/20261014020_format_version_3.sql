@@ -0,0 +1,4 @@
+-- Stash headers are now version 2, carrying the schema version each
+-- buffer was encoded at, see `db::FORMAT_VERSION`.
+UPDATE meta SET value = '3'
+WHERE key = 'format_version' AND CAST(value AS INTEGER) < 3;
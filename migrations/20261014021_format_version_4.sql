@@ -0,0 +1,4 @@
+-- Record data is now at schema 2, with tags in their own field, which
+-- older builds can't read, see `db::FORMAT_VERSION`.
+UPDATE meta SET value = '4'
+WHERE key = 'format_version' AND CAST(value AS INTEGER) < 4;
@@ -0,0 +1,4 @@
+-- Record data is now at schema 3, with room for longer passwords, which
+-- older builds can't read, see `db::FORMAT_VERSION`.
+UPDATE meta SET value = '5'
+WHERE key = 'format_version' AND CAST(value AS INTEGER) < 5;
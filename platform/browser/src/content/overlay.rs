@@ -490,7 +490,7 @@ async fn do_generate(pw: &HtmlInputElement, username_field: Option<&HtmlInputEle
     match result {
         Ok(record) => {
             tracing::info!(label = %label, "password generated and saved");
-            fill::set_field_value(pw, record.password().as_str());
+            fill::set_field_value(pw, record.password().expose());
         }
         Err(e) => {
             tracing::warn!(label = %label, error = %e, "generate failed");
@@ -541,7 +541,7 @@ async fn do_fill(
                 }
             }
             tracing::debug!("filling password field");
-            fill::set_field_value(pw, record.password().as_str());
+            fill::set_field_value(pw, record.password().expose());
             tracing::info!("autofill complete");
         }
         Err(e) => {
@@ -31,7 +31,7 @@ pub fn password_is_valid(candidate: &str) -> bool {
 /// Generate a random 20-character password.
 #[wasm_bindgen]
 pub fn generate_password() -> String {
-    Password::generate().as_str().to_string()
+    Password::generate().expose().to_owned()
 }
 
 /// WASM module initialization hook
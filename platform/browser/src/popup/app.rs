@@ -468,7 +468,7 @@ fn unlocked_view(props: &UnlockedProps) -> Html {
                     .await;
                 match result {
                     Ok(record) => {
-                        if let Err(e) = browser::copy_to_clipboard(record.password().as_str()).await
+                        if let Err(e) = browser::copy_to_clipboard(record.password().expose()).await
                         {
                             tracing::debug!(error = ?e, "clipboard write failed");
                             set_message.emit(Message::Error(format!("Copy failed: {e:?}")));
@@ -0,0 +1,252 @@
+//! A local JSON API for autofill agents and browser extensions.
+//!
+//! [`Agent`] holds one [`Vault`] and serves it over HTTP on a Unix domain
+//! socket, readable only by the user who started it. Clients authenticate
+//! with the master password: `POST /unlock`, then `POST /search` and
+//! `POST /get` until `POST /lock`, or until the vault has been idle for
+//! the agent's timeout and locks itself.
+//!
+//! | route     | body                      | reply                       |
+//! |-----------|---------------------------|-----------------------------|
+//! | `/unlock` | `{"username", "password"}`| `204`                       |
+//! | `/lock`   |                           | `204`                       |
+//! | `/search` | `{"query"}`               | `[{"path", "uuid"}]`        |
+//! | `/get`    | `{"path", "field"?}`      | `{"value"}`                 |
+//!
+//! `field` is parsed as a [`CopyField`], so it defaults to the password.
+//! Errors are `{"error"}` with a matching status: `401` for a wrong
+//! password or a locked vault, `404` for a missing record or field.
+
+use crate::{
+    db::Database,
+    password::Password,
+    record::{CopyField, Path, Query},
+    vault::{self, Vault},
+};
+use axum::{
+    Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use serde::{Deserialize, Serialize};
+use std::{io, os::unix::fs::PermissionsExt, str::FromStr, sync::Arc, time::Duration};
+use tokio::{net::UnixListener, sync::Mutex, time::Instant};
+
+/// How long an unlocked vault may go unused before the agent locks it.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How often the agent checks whether the idle timeout has passed.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct Agent {
+    db: Database,
+    idle_timeout: Duration,
+    state: Mutex<AgentState>,
+}
+
+struct AgentState {
+    vault: Option<Vault>,
+    last_used: Instant,
+}
+
+impl Agent {
+    /// A locked agent for `db`, locking again after `idle_timeout` without
+    /// a request.
+    pub fn new(db: Database, idle_timeout: Duration) -> Arc<Self> {
+        Arc::new(Agent {
+            db,
+            idle_timeout,
+            state: Mutex::new(AgentState {
+                vault: None,
+                last_used: Instant::now(),
+            }),
+        })
+    }
+
+    /// The API's routes, for callers that want to serve them themselves.
+    pub fn router(self: &Arc<Self>) -> Router {
+        Router::new()
+            .route("/unlock", post(unlock))
+            .route("/lock", post(lock))
+            .route("/search", post(search))
+            .route("/get", post(get))
+            .with_state(self.clone())
+    }
+
+    /// Serve the API on a Unix socket at `path` until an I/O error.
+    ///
+    /// The socket file is replaced if it already exists, and made
+    /// readable and writable by its owner only. It's bound in a private
+    /// directory beside `path` and only moved into place once it is, so
+    /// nobody else can connect in between.
+    pub async fn serve(self: Arc<Self>, path: &std::path::Path) -> io::Result<()> {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => std::path::Path::new("."),
+        };
+        std::fs::create_dir_all(parent)?;
+        // Short names, since socket paths are limited to about 100 bytes.
+        let private = tempfile::Builder::new()
+            .prefix(".va")
+            .permissions(std::fs::Permissions::from_mode(0o700))
+            .tempdir_in(parent)?;
+        let bound = private.path().join("s");
+        let listener = UnixListener::bind(&bound)?;
+        std::fs::set_permissions(&bound, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&bound, path)?;
+
+        let idle = tokio::spawn(self.clone().lock_when_idle());
+        let served = axum::serve(listener, self.router()).await;
+        idle.abort();
+        served
+    }
+
+    /// Lock the vault once it has sat unused for the idle timeout, so its
+    /// keys don't stay in memory until the next request.
+    async fn lock_when_idle(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let mut state = self.state.lock().await;
+            if state.vault.is_some() && state.last_used.elapsed() >= self.idle_timeout {
+                tracing::info!("agent idle, locking");
+                state.vault = None;
+            }
+        }
+    }
+}
+
+impl AgentState {
+    /// The unlocked vault, counting this as a use.
+    fn vault(&mut self, idle_timeout: Duration) -> Result<&mut Vault, ApiError> {
+        if self.last_used.elapsed() >= idle_timeout {
+            self.vault = None;
+        }
+        self.last_used = Instant::now();
+        self.vault.as_mut().ok_or(ApiError::Locked)
+    }
+}
+
+#[derive(Deserialize)]
+struct UnlockBody {
+    username: String,
+    password: Password,
+}
+
+#[derive(Deserialize)]
+struct SearchBody {
+    query: String,
+}
+
+#[derive(Serialize)]
+struct SearchHit {
+    path: String,
+    uuid: String,
+}
+
+#[derive(Deserialize)]
+struct GetBody {
+    path: String,
+    #[serde(default)]
+    field: Option<String>,
+}
+
+#[derive(Serialize)]
+struct GetReply {
+    value: String,
+}
+
+async fn unlock(
+    State(agent): State<Arc<Agent>>,
+    Json(body): Json<UnlockBody>,
+) -> Result<StatusCode, ApiError> {
+    let mut state = agent.state.lock().await;
+    // Whoever was unlocked before is locked either way.
+    state.vault = None;
+    let vault = Vault::unlock(agent.db.clone(), &body.username, body.password).await?;
+    state.vault = Some(vault);
+    state.last_used = Instant::now();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn lock(State(agent): State<Arc<Agent>>) -> StatusCode {
+    agent.state.lock().await.vault = None;
+    StatusCode::NO_CONTENT
+}
+
+async fn search(
+    State(agent): State<Arc<Agent>>,
+    Json(body): Json<SearchBody>,
+) -> Result<Json<Vec<SearchHit>>, ApiError> {
+    let query = Query::from_str(&body.query).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let mut state = agent.state.lock().await;
+    let hits = state
+        .vault(agent.idle_timeout)?
+        .search(&query)
+        .await?
+        .into_iter()
+        .map(|(path, uuid)| SearchHit {
+            path: path.to_string(),
+            uuid: uuid.to_string(),
+        })
+        .collect();
+    Ok(Json(hits))
+}
+
+async fn get(
+    State(agent): State<Arc<Agent>>,
+    Json(body): Json<GetBody>,
+) -> Result<Json<GetReply>, ApiError> {
+    let path = Path::from_str(&body.path).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let field = match body.field {
+        Some(field) => CopyField::from_str(&field).unwrap_or_default(),
+        None => CopyField::default(),
+    };
+    let mut state = agent.state.lock().await;
+    let record = state
+        .vault(agent.idle_timeout)?
+        .get(&path)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    let value = record.copy_value(&field).ok_or(ApiError::NotFound)?;
+    Ok(Json(GetReply { value }))
+}
+
+enum ApiError {
+    Locked,
+    /// A wrong username or password, or a user who needs a TOTP code
+    /// the agent can't take yet. Which one is never said.
+    Unauthorized,
+    BadRequest(String),
+    NotFound,
+    Internal(vault::Error),
+}
+
+impl From<vault::Error> for ApiError {
+    fn from(err: vault::Error) -> Self {
+        match err {
+            vault::Error::Locked => ApiError::Locked,
+            vault::Error::User(crate::user::Error::Invalid) => ApiError::Unauthorized,
+            vault::Error::Lot(crate::lot::Error::NotFound(_)) => ApiError::NotFound,
+            e => ApiError::Internal(e),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            ApiError::Locked => (StatusCode::UNAUTHORIZED, "vault is locked".to_owned()),
+            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized".to_owned()),
+            ApiError::BadRequest(e) => (StatusCode::BAD_REQUEST, e),
+            ApiError::NotFound => (StatusCode::NOT_FOUND, "not found".to_owned()),
+            ApiError::Internal(e) => {
+                tracing::warn!("agent request failed: {e}");
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            }
+        };
+        (status, Json(serde_json::json!({ "error": error }))).into_response()
+    }
+}
@@ -1,24 +1,32 @@
-use clap::{CommandFactory, Parser, Subcommand, crate_description};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, crate_description};
 use clap_complete::{Shell, generate};
 use clap_repl::ClapEditor;
-use clap_repl::reedline::{DefaultPrompt, DefaultPromptSegment, FileBackedHistory};
+use clap_repl::reedline::{
+    Completer, DefaultPrompt, DefaultPromptSegment, FileBackedHistory, Span, Suggestion,
+};
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io;
-use std::io::Write;
+use std::io::{BufRead, Write};
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use valet::db::Database;
-use valet::lot::DEFAULT_LOT;
+use valet::lot::{DEFAULT_LOT, PATH_SEPARATOR};
 use valet::password::Password;
 use valet::protocol::EmbeddedHandler;
 use valet::protocol::message::{
-    CreateLot, CreateRecord, DeleteLot, Fetch, History, List, ListLots, ListUsers, Register,
-    Unlock, Validate,
+    CreateLot, CreateRecord, DeleteLot, Fetch, History, List, ListByLot, ListLots, ListUsers,
+    Register, Unlock, Validate,
+};
+use valet::record::{
+    CopyField, Data, Label, LabelName, NOTES_KEY, Path, Query, Record, SaveProgress, URL_KEY,
+    USERNAME_KEY,
 };
-use valet::record::{Data, Label, LabelName, Query, Record, SaveProgress};
-use valet::{Lot, SendHandler};
+use valet::totp::OTP_KEY;
+use valet::uuid::Uuid;
+use valet::{Lot, SendHandler, Vault};
+use zeroize::Zeroizing;
 
 #[derive(Parser)]
 #[command(version, about = crate_description!())]
@@ -26,6 +34,15 @@ struct Cli {
     #[arg(short, long, default_value_t = valet::db::default_url())]
     database: String,
 
+    /// Print `list`, `get` and `lot list` results as line-delimited JSON.
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Read the master password from the first line of stdin instead of
+    /// prompting. Otherwise `VALET_PASSWORD` is used when it's set.
+    #[arg(long, global = true)]
+    password_stdin: bool,
+
     #[command(subcommand)]
     command: ValetCommand,
 }
@@ -39,10 +56,28 @@ enum ValetCommand {
     Import {
         #[arg(short, long = "user")]
         username: Option<String>,
+        /// The exporter the file came from: `apple`, `chrome` or `firefox`.
         #[arg(short, long = "type", required = true)]
         ty: String,
+        /// Skip rows whose fields fail validation (e.g. an `otp` secret
+        /// that can't generate codes) instead of importing them anyway.
+        #[arg(long)]
+        strict: bool,
+        /// Parse and print what would be imported without saving it.
+        #[arg(long)]
+        dry_run: bool,
+        /// Leave records already in the lot alone instead of merging
+        /// rows into them.
+        #[arg(long)]
+        skip_existing: bool,
         filepath: String,
     },
+    /// List records that share a password, without printing any
+    /// passwords.
+    Audit {
+        #[arg(short, long = "user")]
+        username: Option<String>,
+    },
     // Export {
     //     #[arg(short, long = "user")]
     //     username: Option<String>,
@@ -54,6 +89,10 @@ enum ValetCommand {
 
     #[command(subcommand)]
     Config(ConfigCommand),
+
+    /// Database upkeep.
+    #[command(subcommand)]
+    Db(DbCommand),
 }
 
 #[derive(Subcommand)]
@@ -73,6 +112,17 @@ enum ConfigCommand {
     GenerateCompletions { shell: Shell },
 }
 
+#[derive(Subcommand)]
+enum DbCommand {
+    /// Checkpoint the WAL and refresh query planner statistics.
+    Optimize,
+    /// Rebuild the database file to reclaim space from deleted data.
+    Vacuum,
+    /// Print every table and how many rows it has, without reading any
+    /// of them.
+    Info,
+}
+
 #[derive(Parser)]
 enum Repl {
     #[command(subcommand)]
@@ -86,6 +136,18 @@ enum Repl {
         path: String,
         #[arg(long = "uuid")]
         uuid: bool,
+        /// Only records with this tag. Tags are encrypted, so every
+        /// matching record is decrypted to check.
+        #[arg(long = "tag")]
+        tag: Option<String>,
+        /// `flat` prints one label per line, `tree` nests labels under
+        /// their lots, and `json` prints that nesting as one JSON object.
+        #[arg(long = "format", value_enum, default_value_t)]
+        format: ListFormat,
+        /// Print each record's fields under its label, with passwords
+        /// and other secrets masked. Use `get` to see them in full.
+        #[arg(long = "fields")]
+        fields: bool,
     },
     Put {
         path: String,
@@ -99,11 +161,36 @@ enum Repl {
         /// first) instead of only the current password.
         #[arg(short = 'H', long = "history")]
         history: bool,
+        /// Print only this field: `password`, `username`, `otp` (the
+        /// current code) or the name of any other field.
+        #[arg(short, long = "field")]
+        field: Option<CopyField>,
+    },
+    /// Edit a record's password, or one of its fields, in `$VISUAL` or
+    /// `$EDITOR`, e.g. for multiline notes. Saved as a new revision.
+    Edit {
+        path: String,
+        /// Edit this field instead of the password.
+        #[arg(short, long = "field")]
+        field: Option<String>,
+    },
+    /// Print a record's `otp` secret as a QR code, to scan into an
+    /// authenticator app.
+    Qr {
+        path: String,
     },
     Clear,
     Lock,
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum ListFormat {
+    #[default]
+    Flat,
+    Tree,
+    Json,
+}
+
 #[derive(Subcommand)]
 enum LotCommand {
     Create {
@@ -120,21 +207,46 @@ enum LotCommand {
     },
 }
 
-fn get_password() -> Result<Password, valet::user::Error> {
+/// The environment variable read for the master password when
+/// `--password-stdin` isn't given.
+const PASSWORD_ENV: &str = "VALET_PASSWORD";
+
+/// The master password from stdin with `--password-stdin`, then
+/// [`PASSWORD_ENV`], then an interactive prompt.
+fn get_password(stdin: bool) -> Result<Password, valet::user::Error> {
+    if stdin {
+        return read_password_line(io::stdin().lock());
+    }
+    if let Ok(password) = std::env::var(PASSWORD_ENV) {
+        return parse_password(Zeroizing::new(password));
+    }
+    prompt_password()
+}
+
+/// One line of `reader`, without its line ending, as a password.
+fn read_password_line(mut reader: impl BufRead) -> Result<Password, valet::user::Error> {
+    let mut line = Zeroizing::new(String::new());
+    reader
+        .read_line(&mut line)
+        .map_err(|_| valet::user::Error::Invalid)?;
+    let len = line.trim_end_matches(['\r', '\n']).len();
+    line.truncate(len);
+    parse_password(line)
+}
+
+fn parse_password(password: Zeroizing<String>) -> Result<Password, valet::user::Error> {
+    Ok(password.as_str().try_into()?)
+}
+
+fn prompt_password() -> Result<Password, valet::user::Error> {
     print!("Password: ");
     io::stdout().flush().ok();
     // TODO: Can we write our own STDIN reader which avoids extra allocation
     // altogether by disabling the buffered input (raw mode) and copies each
-    // input character into a fixed length buffer. Maximum password lengths
-    // could be something like 200 characters.
-    let mut password_string = rpassword::read_password().unwrap();
-    let password: Password = if let Ok(password) = password_string.as_str().try_into() {
-        password
-    } else {
-        return Err(valet::user::Error::Invalid);
-    };
-    zeroize::Zeroize::zeroize(&mut password_string);
-    Ok(password)
+    // input character into a fixed length buffer of
+    // `valet::password::MAX_LENGTH` bytes.
+    let password_string = Zeroizing::new(rpassword::read_password().unwrap());
+    Ok(password_string.as_str().try_into()?)
 }
 
 #[tokio::main]
@@ -143,20 +255,27 @@ async fn main() -> Result<(), CliError> {
 
     match &cli.command {
         ValetCommand::User(UserCommand::Register { username }) => {
-            let client = open_client(&cli.database).await?;
-            let password = get_password()?;
+            // The only command that makes a new database, so a mistyped
+            // `--database` anywhere else is an error instead of an empty
+            // vault.
+            let client = handler(Database::create(&cli.database).await?);
+            let password = get_password(cli.password_stdin)?;
+            let strength = valet::encrypt::estimate_strength(password.expose());
             client
                 .call(Register {
                     username: username.clone(),
                     password,
                 })
                 .await?;
-            println!("{} registered", username);
+            println!("{} registered, password strength {}", username, strength);
+            if strength.is_weak() {
+                eprintln!("warning: weak password, consider changing it");
+            }
         }
         ValetCommand::User(UserCommand::Validate { username }) => {
             let client = open_client(&cli.database).await?;
             let username = get_default_username(username, &client).await?;
-            let password = get_password()?;
+            let password = get_password(cli.password_stdin)?;
             client
                 .call(Validate {
                     username: username.clone(),
@@ -174,7 +293,7 @@ async fn main() -> Result<(), CliError> {
         ValetCommand::Unlock { username } => {
             let client = open_client(&cli.database).await?;
             let username = get_default_username(username, &client).await?;
-            let password = get_password()?;
+            let password = get_password(cli.password_stdin)?;
             client
                 .call(Unlock {
                     username: username.clone(),
@@ -186,19 +305,28 @@ async fn main() -> Result<(), CliError> {
                 left_prompt: DefaultPromptSegment::Basic("valet".to_owned()),
                 ..DefaultPrompt::default()
             };
+            let completer = PathCompleter::default();
+            completer.refresh(&client, &username).await;
             let rl = ClapEditor::<Repl>::builder()
-                .with_editor_hook(|reed| {
-                    reed.with_history(Box::new(FileBackedHistory::new(0).unwrap()))
-                        .use_bracketed_paste(true)
+                .with_editor_hook({
+                    let completer = completer.clone();
+                    move |reed| {
+                        reed.with_history(Box::new(FileBackedHistory::new(0).unwrap()))
+                            .use_bracketed_paste(true)
+                            .with_completer(Box::new(completer.clone()))
+                    }
                 })
                 .with_prompt(Box::new(prompt.clone()))
                 .build();
 
-            run_repl(rl, client, username).await;
+            run_repl(rl, client, username, completer, cli.json).await;
         }
         ValetCommand::Import {
             username,
             ty,
+            strict,
+            dry_run,
+            skip_existing,
             filepath,
         } => {
             // Bulk import streams a progress callback through
@@ -208,17 +336,64 @@ async fn main() -> Result<(), CliError> {
             // parallel Database handle just for this operation.
             // TODO: fold import into the handler once we have a
             // streaming response protocol.
-            let db = Database::new(&cli.database).await?;
+            let db = Database::open(&cli.database).await?;
             let client = open_client(&cli.database).await?;
             let username = get_default_username(username, &client).await?;
-            let password = get_password()?;
-            let user = valet::User::load(&db, &username, password).await?;
-            if let Some(mut lot) = Lot::load(&db, DEFAULT_LOT, &user).await? {
-                if ty == "apple" {
-                    import_apple(&db, &mut lot, filepath).await;
+            let password = get_password(cli.password_stdin)?;
+            let mut vault = Vault::unlock(db.clone(), &username, password).await?;
+            let options = ImportOptions {
+                strict: *strict,
+                dry_run: *dry_run,
+                skip_existing: *skip_existing,
+            };
+            match vault.lot(DEFAULT_LOT).await {
+                Ok(lot) => {
+                    let open = || File::open(filepath).expect("failed to open file");
+                    let parsed = match ty.as_str() {
+                        "apple" => Some(parse_apple(open())),
+                        "chrome" => Some(parse_chrome(open())),
+                        "firefox" => Some(parse_firefox(open())),
+                        ty => {
+                            eprintln!("Unknown import type: {ty}");
+                            None
+                        }
+                    };
+                    if let Some(parsed) = parsed {
+                        import_rows(&db, lot, parsed, options).await;
+                    }
+                }
+                Err(valet::vault::Error::Lot(valet::lot::Error::NotFound(_))) => {
+                    eprintln!("Missing LOT: {}", DEFAULT_LOT);
+                }
+                Err(e) => return Err(e.into()),
+            }
+            vault.lock();
+        }
+        ValetCommand::Audit { username } => {
+            let db = Database::open(&cli.database).await?;
+            let client = open_client(&cli.database).await?;
+            let username = get_default_username(username, &client).await?;
+            let password = get_password(cli.password_stdin)?;
+            let mut vault = Vault::unlock(db, &username, password).await?;
+            let groups = vault.user()?.audit_reused_passwords(vault.db()).await?;
+            if groups.is_empty() {
+                println!("No reused passwords");
+            }
+            for group in groups {
+                println!("{}", group.join(", "));
+            }
+            vault.lock();
+        }
+        ValetCommand::Db(command) => {
+            let db = Database::open(&cli.database).await?;
+            match command {
+                DbCommand::Optimize => db.optimize().await?,
+                DbCommand::Vacuum => db.vacuum().await?,
+                DbCommand::Info => {
+                    for table in db.table_names().await? {
+                        println!("{table}: {}", db.row_count(&table).await?);
+                    }
                 }
-            } else {
-                eprintln!("Missing LOT: {}", DEFAULT_LOT);
             }
         }
         ValetCommand::Config(ConfigCommand::GenerateCompletions { shell }) => {
@@ -232,14 +407,300 @@ async fn main() -> Result<(), CliError> {
 }
 
 async fn open_client(database: &str) -> Result<Arc<EmbeddedHandler>, CliError> {
-    let db = Database::new(database).await?;
-    Ok(Arc::new(EmbeddedHandler::new(
-        db,
-        &tokio::runtime::Handle::current(),
-    )))
+    Ok(handler(Database::open(database).await?))
+}
+
+fn handler(db: Database) -> Arc<EmbeddedHandler> {
+    Arc::new(EmbeddedHandler::new(db, &tokio::runtime::Handle::current()))
+}
+
+/// Open `path` in `$VISUAL`, `$EDITOR` or `vi`, and wait for it to exit.
+fn run_editor(path: &std::path::Path) -> io::Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+    // Allow editors with arguments, like `code --wait`.
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{program} exited with {status}")))
+    }
+}
+
+/// Hand `current` to `editor` in a temporary file and return what it was
+/// edited to, less the trailing newline editors add.
+///
+/// The file is overwritten with zeroes before it's removed, so the
+/// plaintext doesn't linger on disk.
+fn edit_value(
+    current: &str,
+    editor: impl FnOnce(&std::path::Path) -> io::Result<()>,
+) -> io::Result<Zeroizing<String>> {
+    let mut file = tempfile::Builder::new().prefix("valet-edit-").tempfile()?;
+    file.write_all(current.as_bytes())?;
+    file.flush()?;
+    let result = editor(file.path()).and_then(|()| {
+        let mut edited = Zeroizing::new(std::fs::read_to_string(file.path())?);
+        if edited.ends_with('\n') {
+            edited.pop();
+            if edited.ends_with('\r') {
+                edited.pop();
+            }
+        }
+        Ok(edited)
+    });
+    // Overwrite in place, without truncating, so the plaintext's blocks
+    // are rewritten rather than freed. The editor may have replaced the
+    // file, so size the wipe from whatever is there now.
+    if let Ok(mut wipe) = std::fs::OpenOptions::new().write(true).open(file.path())
+        && let Ok(len) = wipe.metadata().map(|m| m.len())
+    {
+        let _ = io::copy(&mut io::Read::take(io::repeat(0), len), &mut wipe)
+            .and_then(|_| wipe.sync_all());
+    }
+    let _ = file.close();
+    result
 }
 
-async fn run_repl(rl: ClapEditor<Repl>, client: Arc<EmbeddedHandler>, username: String) {
+/// The password and data extras to save after editing `field` (or the
+/// password) of `data` to `edited`. `None` if an edited password is
+/// invalid.
+fn apply_edit(
+    data: &Data,
+    field: Option<&str>,
+    edited: &str,
+) -> Option<(Password, HashMap<String, String>)> {
+    let mut extra = data.extra().clone();
+    let password = match field {
+        Some(field) => {
+            extra.insert(field.to_owned(), edited.to_owned());
+            data.password().clone()
+        }
+        None => edited.try_into().ok()?,
+    };
+    Some((password, extra))
+}
+
+/// A `lot list` line under `--json`.
+#[derive(serde::Serialize)]
+struct LotOutput<'a> {
+    lot: &'a str,
+    uuid: String,
+}
+
+/// A `list` line under `--json`.
+#[derive(serde::Serialize)]
+struct ListOutput {
+    label: String,
+    uuid: String,
+}
+
+/// A `get` line under `--json`. `lot` is only known when the query named
+/// one literally. `extra` holds the record's data extras, sorted; like the
+/// plain output, `get` reveals them in full.
+#[derive(serde::Serialize)]
+struct GetOutput<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lot: Option<String>,
+    label: String,
+    value: String,
+    uuid: String,
+    #[serde(skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    extra: std::collections::BTreeMap<&'a str, &'a str>,
+}
+
+/// `list` results nested by lot, for `--format tree` and `--format json`.
+///
+/// Sub-lots hang off their parent by [`PATH_SEPARATOR`], so `work::aws`
+/// sits under `work` whether or not `work` has records of its own.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+struct ListTree {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    records: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    lots: BTreeMap<String, ListTree>,
+}
+
+impl ListTree {
+    /// Nest `(lot, record)` pairs, keeping records in the order given.
+    fn build(entries: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut root = ListTree::default();
+        for (lot, record) in entries {
+            let node = lot.split(PATH_SEPARATOR).fold(&mut root, |node, part| {
+                node.lots.entry(part.to_owned()).or_default()
+            });
+            node.records.push(record);
+        }
+        root
+    }
+
+    /// Two spaces of indent per level, each lot's records before its
+    /// sub-lots.
+    fn render(&self) -> String {
+        let mut out = String::new();
+        self.render_into(&mut out, 0);
+        out
+    }
+
+    fn render_into(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth);
+        for record in &self.records {
+            out.push_str(&format!("{indent}{record}\n"));
+        }
+        for (name, lot) in &self.lots {
+            out.push_str(&format!("{indent}{name}\n"));
+            lot.render_into(out, depth + 1);
+        }
+    }
+}
+
+/// The lot each of the user's records matching `query` is in, by record
+/// uuid.
+async fn record_lots(
+    client: &EmbeddedHandler,
+    username: &str,
+    query: &str,
+) -> Result<HashMap<Uuid<Record>, String>, valet::protocol::Error> {
+    let grouped = client
+        .call(ListByLot {
+            username: username.to_owned(),
+            queries: vec![query.to_owned()],
+        })
+        .await?;
+    let mut lots = HashMap::new();
+    for (lot, entries) in grouped {
+        for (record_uuid, _) in entries {
+            lots.insert(record_uuid, lot.clone());
+        }
+    }
+    Ok(lots)
+}
+
+fn print_json(value: &impl serde::Serialize) {
+    match serde_json::to_string(value) {
+        Ok(line) => println!("{line}"),
+        Err(e) => eprintln!("Failed to serialize output: {e}"),
+    }
+}
+
+/// Tab completion for the REPL: subcommand names for the first word,
+/// record paths after it.
+///
+/// Paths come from a snapshot of the unlocked user's lots, taken by
+/// [`PathCompleter::refresh`] at unlock and again after any command that
+/// adds records or lots, so completing never waits on the database.
+#[derive(Clone, Default)]
+struct PathCompleter {
+    /// `(lot, label)` for every record.
+    paths: Arc<Mutex<Vec<(String, String)>>>,
+    /// Plus the lots themselves, so empty ones still complete.
+    lots: Arc<Mutex<Vec<String>>>,
+}
+
+impl PathCompleter {
+    async fn refresh(&self, client: &EmbeddedHandler, username: &str) {
+        let Ok(lots) = client
+            .call(ListLots {
+                username: username.to_owned(),
+            })
+            .await
+        else {
+            return;
+        };
+        let mut paths = Vec::new();
+        for (_, lot) in &lots {
+            let Ok(entries) = client
+                .call(List {
+                    username: username.to_owned(),
+                    queries: vec![format!("{lot}::")],
+                })
+                .await
+            else {
+                continue;
+            };
+            for (_, label) in entries {
+                paths.push((lot.clone(), label.name().to_string()));
+            }
+        }
+        *self.lots.lock().unwrap() = lots.into_iter().map(|(_, lot)| lot).collect();
+        *self.paths.lock().unwrap() = paths;
+    }
+}
+
+impl Completer for PathCompleter {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &line[start..];
+        let values = if line[..start].trim().is_empty() {
+            Repl::command()
+                .get_subcommands()
+                .map(|c| c.get_name().to_owned())
+                .filter(|name| name.starts_with(word))
+                .collect()
+        } else {
+            complete_path(
+                &self.lots.lock().unwrap(),
+                &self.paths.lock().unwrap(),
+                word,
+            )
+        };
+        values
+            .into_iter()
+            .map(|value| Suggestion {
+                // Leave the cursor inside a lot to keep completing labels.
+                append_whitespace: !value.ends_with("::"),
+                value,
+                span: Span::new(start, pos),
+                ..Suggestion::default()
+            })
+            .collect()
+    }
+}
+
+/// Every completion of `word` as a record path.
+///
+/// A `lot::` prefix completes labels within that lot; without one, labels
+/// in the default lot, which is where a bare label points. Either way lot
+/// names starting with `word` complete to `lot::`.
+fn complete_path(lots: &[String], paths: &[(String, String)], word: &str) -> Vec<String> {
+    let mut completions: Vec<String> = lots
+        .iter()
+        .filter(|lot| lot.starts_with(word))
+        .map(|lot| format!("{lot}::"))
+        .collect();
+    match word.rsplit_once("::") {
+        Some((lot, prefix)) => completions.extend(
+            paths
+                .iter()
+                .filter(|(l, label)| l == lot && label.starts_with(prefix))
+                .map(|(l, label)| format!("{l}::{label}")),
+        ),
+        None => completions.extend(
+            paths
+                .iter()
+                .filter(|(l, label)| l == DEFAULT_LOT && label.starts_with(word))
+                .map(|(_, label)| label.clone()),
+        ),
+    }
+    completions.sort();
+    completions.dedup();
+    completions
+}
+
+async fn run_repl(
+    rl: ClapEditor<Repl>,
+    client: Arc<EmbeddedHandler>,
+    username: String,
+    completer: PathCompleter,
+    json: bool,
+) {
     rl.repl_async(async |command| match &command {
         Repl::Lot(LotCommand::Create { name }) => {
             if let Err(e) = client
@@ -251,6 +712,7 @@ async fn run_repl(rl: ClapEditor<Repl>, client: Arc<EmbeddedHandler>, username:
             {
                 println!("Failed to create lot: {e}");
             }
+            completer.refresh(&client, &username).await;
         }
         Repl::Lot(LotCommand::List { uuid }) => match client
             .call(ListLots {
@@ -260,7 +722,12 @@ async fn run_repl(rl: ClapEditor<Repl>, client: Arc<EmbeddedHandler>, username:
         {
             Ok(lots) => {
                 for (lot_uuid, name) in lots {
-                    if *uuid {
+                    if json {
+                        print_json(&LotOutput {
+                            lot: &name,
+                            uuid: lot_uuid.to_string(),
+                        });
+                    } else if *uuid {
                         println!("{name} <{lot_uuid}>");
                     } else {
                         println!("{name}");
@@ -279,9 +746,16 @@ async fn run_repl(rl: ClapEditor<Repl>, client: Arc<EmbeddedHandler>, username:
             {
                 println!("Failed to delete lot: {e}");
             }
+            completer.refresh(&client, &username).await;
         }
-        Repl::List { path, uuid } => {
-            let entries = match client
+        Repl::List {
+            path,
+            uuid,
+            tag,
+            format,
+            fields,
+        } => {
+            let mut entries = match client
                 .call(List {
                     username: username.clone(),
                     queries: vec![path.clone()],
@@ -294,21 +768,86 @@ async fn run_repl(rl: ClapEditor<Repl>, client: Arc<EmbeddedHandler>, username:
                     return;
                 }
             };
+            if let Some(tag) = tag {
+                let mut tagged = Vec::with_capacity(entries.len());
+                for (record_uuid, label) in entries {
+                    match client
+                        .call(Fetch {
+                            username: username.clone(),
+                            uuid: record_uuid.clone(),
+                        })
+                        .await
+                    {
+                        Ok(record) if record.data().has_tag(tag) => {
+                            tagged.push((record_uuid, label))
+                        }
+                        Ok(_) => {}
+                        Err(e) => println!("{e}"),
+                    }
+                }
+                entries = tagged;
+            }
             if entries.is_empty() {
                 println!("No records match: {path}");
                 return;
             }
+            if *format != ListFormat::Flat {
+                let lots = match record_lots(&client, &username, path).await {
+                    Ok(lots) => lots,
+                    Err(e) => {
+                        println!("{e}");
+                        return;
+                    }
+                };
+                // Only a record changed since the first listing can be
+                // missing, and it's skipped rather than put under no lot.
+                let tree =
+                    ListTree::build(entries.into_iter().filter_map(|(record_uuid, label)| {
+                        let lot = lots.get(&record_uuid)?.clone();
+                        let name = label.name();
+                        Some(match format {
+                            ListFormat::Tree if *uuid => (lot, format!("{name} <{record_uuid}>")),
+                            _ => (lot, name.to_string()),
+                        })
+                    }));
+                match format {
+                    ListFormat::Json => print_json(&tree),
+                    _ => print!("{}", tree.render()),
+                }
+                return;
+            }
             for (record_uuid, label) in entries {
                 let name = label.name();
-                if *uuid {
+                if json {
+                    print_json(&ListOutput {
+                        label: name.to_string(),
+                        uuid: record_uuid.to_string(),
+                    });
+                } else if *uuid {
                     println!("{name} <{record_uuid}>");
                 } else {
                     println!("{name}");
                 }
+                if *fields && !json {
+                    match client
+                        .call(Fetch {
+                            username: username.clone(),
+                            uuid: record_uuid.clone(),
+                        })
+                        .await
+                    {
+                        Ok(record) => {
+                            for line in record.data().display_masked().lines() {
+                                println!("  {line}");
+                            }
+                        }
+                        Err(e) => println!("  {e}"),
+                    }
+                }
             }
         }
         Repl::Put { path, data } => {
-            let target = match Query::from_str(path).and_then(Query::into_path) {
+            let target = match Path::from_str(path) {
                 Ok(p) => p,
                 Err(e) => {
                     println!("{e}: {path}");
@@ -333,11 +872,13 @@ async fn run_repl(rl: ClapEditor<Repl>, client: Arc<EmbeddedHandler>, username:
             {
                 println!("Failed to save record: {e}");
             }
+            completer.refresh(&client, &username).await;
         }
         Repl::Get {
             path,
             uuid,
             history,
+            field,
         } => {
             let query = match Query::from_str(path) {
                 Ok(q) => q,
@@ -420,7 +961,7 @@ async fn run_repl(rl: ClapEditor<Repl>, client: Arc<EmbeddedHandler>, username:
                             let ts = chrono::DateTime::<chrono::Utc>::from_timestamp(secs, nanos)
                                 .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
                                 .unwrap_or_else(|| rev.time_millis.to_string());
-                            println!("{ts} {}: {}", rev.label, rev.password);
+                            println!("{ts} {}: {}", rev.label, rev.password.expose());
                         }
                     }
                     Err(e) => {
@@ -435,15 +976,44 @@ async fn run_repl(rl: ClapEditor<Repl>, client: Arc<EmbeddedHandler>, username:
                     })
                     .await
                 {
+                    Ok(record) if field.is_some() => {
+                        let field = field.as_ref().expect("field is some");
+                        match record.copy_value(field) {
+                            Some(value) => println!("{value}"),
+                            None => println!("No {field} for {path}"),
+                        }
+                    }
+                    Ok(record) if json => {
+                        let lot = Path::from_str(path).ok().map(|p| p.lot);
+                        print_json(&GetOutput {
+                            lot,
+                            label: record.label().name().to_string(),
+                            value: record.password().expose().to_owned(),
+                            uuid: record.uuid().to_string(),
+                            extra: record
+                                .data()
+                                .extra()
+                                .iter()
+                                .map(|(k, v)| (k.as_str(), v.as_str()))
+                                .collect(),
+                        });
+                    }
                     Ok(record) => {
                         if *uuid {
-                            println!("{} <{}>", record.password(), record.uuid());
+                            println!("{} <{}>", record.password().expose(), record.uuid());
                         } else {
-                            println!("{}", record.password());
+                            println!("{}", record.password().expose());
                         }
                         for (k, v) in record.label().extra() {
                             println!("{k}: {v}");
                         }
+                        // `get` is an explicit reveal, so data extras
+                        // print in full too.
+                        let mut extra: Vec<_> = record.data().extra().iter().collect();
+                        extra.sort();
+                        for (k, v) in extra {
+                            println!("{k}: {v}");
+                        }
                     }
                     Err(e) => {
                         println!("Failed to load record: {e}");
@@ -451,6 +1021,61 @@ async fn run_repl(rl: ClapEditor<Repl>, client: Arc<EmbeddedHandler>, username:
                 }
             }
         }
+        Repl::Edit { path, field } => {
+            let target = match Path::from_str(path) {
+                Ok(p) => p,
+                Err(e) => {
+                    println!("{e}: {path}");
+                    return;
+                }
+            };
+            let Some(record) = find_record(&client, &username, path).await else {
+                return;
+            };
+            let current = match field {
+                Some(field) => record
+                    .data()
+                    .extra()
+                    .get(field)
+                    .cloned()
+                    .unwrap_or_default(),
+                None => record.password().expose().to_owned(),
+            };
+            let edited = match edit_value(&Zeroizing::new(current), run_editor) {
+                Ok(edited) => edited,
+                Err(e) => {
+                    println!("Failed to edit: {e}");
+                    return;
+                }
+            };
+            let Some((password, extra)) = apply_edit(record.data(), field.as_deref(), &edited)
+            else {
+                println!("Invalid password");
+                return;
+            };
+            if let Err(e) = client
+                .call(CreateRecord {
+                    username: username.clone(),
+                    lot: target.lot,
+                    label: record.label().clone(),
+                    password,
+                    extra,
+                })
+                .await
+            {
+                println!("Failed to save record: {e}");
+            }
+        }
+        Repl::Qr { path } => {
+            let Some(record) = find_record(&client, &username, path).await else {
+                return;
+            };
+            match record.otp_qr() {
+                Some(Ok(qr)) => println!("{qr}"),
+                Some(Err(e)) => println!("Failed to render QR code: {e}"),
+                None => println!("No otp secret for: {path}"),
+            }
+        }
         Repl::Clear => {
             // NOTE: Order matters here.
             // 2J first clears into scrollback
@@ -466,6 +1091,43 @@ async fn run_repl(rl: ClapEditor<Repl>, client: Arc<EmbeddedHandler>, username:
     .await;
 }
 
+/// The one record at the literal `path`, printing why if there isn't one.
+/// Names are unique within a lot, so a literal path matches at most one.
+async fn find_record(client: &EmbeddedHandler, username: &str, path: &str) -> Option<Record> {
+    let record_uuid = match client
+        .call(List {
+            username: username.to_owned(),
+            queries: vec![path.to_owned()],
+        })
+        .await
+    {
+        Ok(entries) => match entries.into_iter().next() {
+            Some((record_uuid, _)) => record_uuid,
+            None => {
+                println!("No records match: {path}");
+                return None;
+            }
+        },
+        Err(e) => {
+            println!("{e}");
+            return None;
+        }
+    };
+    match client
+        .call(Fetch {
+            username: username.to_owned(),
+            uuid: record_uuid,
+        })
+        .await
+    {
+        Ok(record) => Some(record),
+        Err(e) => {
+            println!("Failed to load record: {e}");
+            None
+        }
+    }
+}
+
 async fn get_default_username(
     provided: &Option<String>,
     client: &Arc<EmbeddedHandler>,
@@ -490,14 +1152,16 @@ enum CliError {
     User(valet::user::Error),
     Db(valet::db::Error),
     Protocol(valet::protocol::Error),
+    Vault(valet::vault::Error),
 }
 
 impl std::fmt::Display for CliError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            CliError::User(e) => write!(f, "{e:?}"),
-            CliError::Db(e) => write!(f, "{e:?}"),
+            CliError::User(e) => write!(f, "{e}"),
+            CliError::Db(e) => write!(f, "{e}"),
             CliError::Protocol(e) => write!(f, "{e}"),
+            CliError::Vault(e) => write!(f, "{e}"),
         }
     }
 }
@@ -522,16 +1186,48 @@ impl From<valet::protocol::Error> for CliError {
     }
 }
 
+impl From<valet::vault::Error> for CliError {
+    fn from(e: valet::vault::Error) -> Self {
+        CliError::Vault(e)
+    }
+}
+
 impl From<valet::lot::Error> for CliError {
     fn from(e: valet::lot::Error) -> Self {
         CliError::User(valet::user::Error::Lot(e))
     }
 }
 
-async fn import_apple(db: &Database, lot: &mut Lot, path: &str) {
-    let file = File::open(path).expect("failed to open file");
-    let mut rdr = csv::Reader::from_reader(file);
+/// How `import` treats rows, see [`ValetCommand::Import`].
+#[derive(Clone, Copy, Default)]
+struct ImportOptions {
+    strict: bool,
+    dry_run: bool,
+    skip_existing: bool,
+}
+
+/// An export's rows, and how many were skipped as malformed.
+#[derive(Default)]
+struct ParsedRows {
+    rows: Vec<(Label, Data)>,
+    malformed: usize,
+}
+
+impl ParsedRows {
+    /// Keep a row [`import_row`] made, or count one it couldn't.
+    fn push(&mut self, row: Option<(Label, Data)>) {
+        match row {
+            Some(row) => self.rows.push(row),
+            None => self.malformed += 1,
+        }
+    }
+}
 
+/// Apple Passwords exports `Title,URL,Username,Password,Notes,OTPAuth`,
+/// with titles like `example.com (alice)`. Secure notes are rows with
+/// only a title and notes, and become simple records with an empty
+/// password, named by the whole title with `-` for spaces.
+fn parse_apple(reader: impl io::Read) -> ParsedRows {
     #[derive(Debug, serde::Deserialize)]
     #[serde(rename_all = "PascalCase")]
     struct CsvRecord {
@@ -546,15 +1242,36 @@ async fn import_apple(db: &Database, lot: &mut Lot, path: &str) {
     }
 
     let title_re = Regex::new(r"(\S+)\s*(?:\((.*)\))?").unwrap();
-    let mut records = Vec::new();
-    for result in rdr.deserialize::<CsvRecord>() {
+    let mut parsed = ParsedRows::default();
+    for result in csv::Reader::from_reader(reader).deserialize::<CsvRecord>() {
         let csv_record = match result {
             Ok(r) => r,
             Err(e) => {
-                dbg!(e);
+                eprintln!("skipping row: {e}");
+                parsed.malformed += 1;
                 continue;
             }
         };
+        let notes_only = csv_record.url.is_empty()
+            && csv_record.username.is_empty()
+            && csv_record.password.is_empty()
+            && csv_record.notes.as_deref().is_some_and(|n| !n.is_empty());
+        if notes_only {
+            let label = csv_record
+                .title
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join("-");
+            parsed.push(import_row(
+                &label,
+                String::new(),
+                String::new(),
+                "",
+                csv_record.notes,
+                csv_record.otp,
+            ));
+            continue;
+        }
         let label = if let Some(captures) = title_re.captures(&csv_record.title) {
             let domain_or_label = captures[1].to_owned();
             if let Some(user) = captures.get(2) {
@@ -564,57 +1281,223 @@ async fn import_apple(db: &Database, lot: &mut Lot, path: &str) {
             }
         } else {
             eprintln!("Bad title: {}", csv_record.title);
+            parsed.malformed += 1;
             continue;
         };
+        parsed.push(import_row(
+            &label,
+            csv_record.url,
+            csv_record.username,
+            &csv_record.password,
+            csv_record.notes,
+            csv_record.otp,
+        ));
+    }
+    parsed
+}
 
-        let mut data = HashMap::new();
-        if let Some(notes) = csv_record.notes {
-            data.insert("notes".into(), notes);
-        }
-        if let Some(otp) = csv_record.otp {
-            data.insert("otp".into(), otp);
-        }
-        let parsed_label = match label.parse::<Label>() {
-            Ok(l) => l,
+/// Chrome exports `name,url,username,password,note`. `name` is usually
+/// the site's domain, but can be empty.
+fn parse_chrome(reader: impl io::Read) -> ParsedRows {
+    #[derive(Debug, serde::Deserialize)]
+    struct CsvRecord {
+        #[serde(default)]
+        name: String,
+        url: String,
+        username: String,
+        password: String,
+        note: Option<String>,
+    }
+
+    let mut parsed = ParsedRows::default();
+    for result in csv::Reader::from_reader(reader).deserialize::<CsvRecord>() {
+        let csv_record = match result {
+            Ok(r) => r,
             Err(e) => {
-                eprintln!("Invalid label {label:?}: {e}");
+                eprintln!("skipping row: {e}");
+                parsed.malformed += 1;
                 continue;
             }
         };
-        let parsed_label = match parsed_label
-            .add_extra("url", csv_record.url)
-            .and_then(|l| match l.name() {
-                LabelName::Domain { id, .. } if id == &csv_record.username => Ok(l),
-                _ => l.add_extra("username", csv_record.username),
-            }) {
-            Ok(l) => l,
+        let name = match csv_record.name.trim() {
+            "" => match url_domain(&csv_record.url) {
+                Some(domain) => domain,
+                None => {
+                    eprintln!("Bad url: {}", csv_record.url);
+                    parsed.malformed += 1;
+                    continue;
+                }
+            },
+            name => name.to_owned(),
+        };
+        let label = login_label(&name, &csv_record.username);
+        parsed.push(import_row(
+            &label,
+            csv_record.url,
+            csv_record.username,
+            &csv_record.password,
+            csv_record.note,
+            None,
+        ));
+    }
+    parsed
+}
+
+/// Firefox exports `url,username,password,httpRealm,formActionOrigin,...`
+/// with no name column, so labels come from the url's domain.
+fn parse_firefox(reader: impl io::Read) -> ParsedRows {
+    #[derive(Debug, serde::Deserialize)]
+    struct CsvRecord {
+        url: String,
+        username: String,
+        password: String,
+    }
+
+    let mut parsed = ParsedRows::default();
+    for result in csv::Reader::from_reader(reader).deserialize::<CsvRecord>() {
+        let csv_record = match result {
+            Ok(r) => r,
             Err(e) => {
-                eprintln!("{e} for {label:?}");
-                // TODO: Instead of this .and_then chain. Just try to add labels
-                // if it works, great, otherwise print warning but still add it.
-                // Maybe .add_extra should take an &mut self.
+                eprintln!("skipping row: {e}");
+                parsed.malformed += 1;
                 continue;
             }
         };
-        // TODO: Put text directly into a Password
-        let Ok(password) = csv_record.password.as_str().try_into() else {
+        let Some(domain) = url_domain(&csv_record.url) else {
+            eprintln!("Bad url: {}", csv_record.url);
+            parsed.malformed += 1;
             continue;
         };
-        // TODO: We need to load the lot and check for existing records before
-        // minting new UUIDs. In general I don't think we actually want a
-        // Record::new function at all, since we should always have a lot
-        // before creating records, so something like Record::get which either
-        // lazy loads or mints a fresh record is in order. We will also need a
-        // way to batch this or something here, so it's a little more complex...
-        records.push(Record::new(
-            &*lot,
-            parsed_label,
-            Data::new(password).with_extra(data),
+        let label = login_label(&domain, &csv_record.username);
+        parsed.push(import_row(
+            &label,
+            csv_record.url,
+            csv_record.username,
+            &csv_record.password,
+            None,
+            None,
         ));
     }
+    parsed
+}
 
-    let total = records.len();
+/// `username@name`, so several accounts on one site get distinct labels.
+fn login_label(name: &str, username: &str) -> String {
+    if username.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{username}@{name}")
+    }
+}
+
+/// The host of `url`, without any leading `www.`.
+fn url_domain(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    Some(host.strip_prefix("www.").unwrap_or(host).to_owned())
+}
+
+/// Parse `label`, tagging it with the row's `url` and `username` (unless
+/// empty, or the label's id already is the username), and pair it with
+/// the row's data. Returns `None`, after printing why, for rows that
+/// can't be imported.
+fn import_row(
+    label: &str,
+    url: String,
+    username: String,
+    password: &str,
+    notes: Option<String>,
+    otp: Option<String>,
+) -> Option<(Label, Data)> {
+    let mut data = HashMap::new();
+    if let Some(notes) = notes.filter(|n| !n.is_empty()) {
+        data.insert(NOTES_KEY.into(), notes);
+    }
+    if let Some(otp) = otp.filter(|o| !o.is_empty()) {
+        data.insert(OTP_KEY.into(), otp);
+    }
+    let parsed_label = match label.parse::<Label>() {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("Invalid label {label:?}: {e}");
+            return None;
+        }
+    };
+    let with_url = if url.is_empty() {
+        Ok(parsed_label)
+    } else {
+        parsed_label.add_extra(URL_KEY, url)
+    };
+    let parsed_label = match with_url.and_then(|l| match l.name() {
+        LabelName::Domain { id, .. } if id == &username => Ok(l),
+        _ if username.is_empty() => Ok(l),
+        _ => l.add_extra(USERNAME_KEY, username),
+    }) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("{e} for {label:?}");
+            // TODO: Instead of this .and_then chain. Just try to add labels
+            // if it works, great, otherwise print warning but still add it.
+            // Maybe .add_extra should take an &mut self.
+            return None;
+        }
+    };
+    // TODO: Put text directly into a Password
+    let password = password.try_into().ok()?;
+    Some((parsed_label, Data::new(password).with_extra(data)))
+}
+
+/// Validate, merge and save parsed import rows into `lot`. With `dry_run`
+/// nothing is saved, only what would be is printed. Returns the records
+/// saved, or that would have been.
+async fn import_rows(
+    db: &Database,
+    lot: &mut Lot,
+    parsed: ParsedRows,
+    options: ImportOptions,
+) -> Vec<Record> {
+    let mut records = prepare_import(db, lot, parsed.rows, options).await;
     let lot_name = lot.name().to_owned();
+    if options.dry_run {
+        records.retain(|record| {
+            let skip =
+                options.skip_existing && lot.index().find_by_name(record.label().name()).is_some();
+            if skip {
+                println!("Would skip existing {lot_name}::{}", record.label());
+            } else {
+                println!("Would put {lot_name}::{}", record.label());
+            }
+            !skip
+        });
+        println!(
+            "Would import {} records into {lot_name}, skipping {} malformed rows",
+            records.len(),
+            parsed.malformed
+        );
+        return records;
+    }
+    if options.skip_existing {
+        let mut inserted = Vec::with_capacity(records.len());
+        for record in records {
+            match record.insert_or_get(db, lot).await {
+                Ok((_, true)) => {
+                    println!("Put {lot_name}::{}", record.label());
+                    inserted.push(record);
+                }
+                Ok((_, false)) => println!("Skipped existing {lot_name}::{}", record.label()),
+                Err(e) => eprintln!("Failed to save {lot_name}::{}: {e}", record.label()),
+            }
+        }
+        println!(
+            "Imported {} records into {lot_name}, skipping {} malformed rows",
+            inserted.len(),
+            parsed.malformed
+        );
+        return inserted;
+    }
+
+    let total = records.len();
+
     println!("Importing {total} records into {lot_name}...");
     let mut put = 0usize;
     let result = Record::save_many(db, lot, &records, |ev| match ev {
@@ -636,7 +1519,417 @@ async fn import_apple(db: &Database, lot: &mut Lot, path: &str) {
         }
     })
     .await;
-    if let Err(e) = result {
-        dbg!(e);
+    match result {
+        Ok(_) => println!(
+            "Imported {total} records into {lot_name}, skipping {} malformed rows",
+            parsed.malformed
+        ),
+        Err(e) => eprintln!("Failed to import into {lot_name}: {e}"),
+    }
+    records
+}
+
+/// The records `rows` become in `lot`: validated, with repeats merged and
+/// rows matching an existing record merged into it, unless
+/// `skip_existing`.
+async fn prepare_import(
+    db: &Database,
+    lot: &Lot,
+    rows: Vec<(Label, Data)>,
+    options: ImportOptions,
+) -> Vec<Record> {
+    let mut records: Vec<Record> = Vec::new();
+    // Position in `records` of each label name seen so far, so repeated
+    // rows in one file merge instead of colliding in `save_many`.
+    let mut seen: HashMap<LabelName, usize> = HashMap::new();
+    for (parsed_label, data) in rows {
+        let label = parsed_label.to_string();
+        if let Err(e) = data.validate() {
+            if options.strict {
+                eprintln!("Skipping {label:?}: {e:?}");
+                continue;
+            }
+            eprintln!("Warning: {label:?}: {e:?}");
+        }
+        if let Some(&i) = seen.get(parsed_label.name()) {
+            let merged = records[i].data().merge(&data);
+            records[i] = Record::with_uuid(records[i].uuid().clone(), lot, parsed_label, merged);
+            continue;
+        }
+        // Re-importing over an existing record merges into it and keeps
+        // its uuid, so the save extends that record's history. Unless
+        // it's to be skipped, which `Record::insert_or_get` sees to.
+        let existing = if options.skip_existing {
+            None
+        } else {
+            lot.index().find_by_name(parsed_label.name()).cloned()
+        };
+        let record = match existing {
+            Some(uuid) => match Record::show(db, lot, &uuid).await {
+                Ok(Some(existing)) => {
+                    let merged = existing.data().merge(&data);
+                    Record::with_uuid(uuid, lot, parsed_label, merged)
+                }
+                Ok(None) => Record::with_uuid(uuid, lot, parsed_label, data),
+                Err(e) => {
+                    eprintln!("Failed to load existing {label:?}: {e:?}");
+                    continue;
+                }
+            },
+            None => Record::new(lot, parsed_label, data),
+        };
+        seen.insert(record.label().name().clone(), records.len());
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_flag_is_global() {
+        let cli = Cli::try_parse_from(["valet", "unlock", "--json"]).expect("failed to parse");
+        assert!(cli.json);
+        let cli = Cli::try_parse_from(["valet", "unlock"]).expect("failed to parse");
+        assert!(!cli.json);
+    }
+
+    #[test]
+    fn password_from_stdin() {
+        let cli =
+            Cli::try_parse_from(["valet", "unlock", "--password-stdin"]).expect("failed to parse");
+        assert!(cli.password_stdin);
+
+        let password = read_password_line(&b"hunter22\nrest"[..]).expect("failed to read");
+        assert_eq!("hunter22", password.expose());
+        let password = read_password_line(&b"hunter22\r\n"[..]).expect("failed to read");
+        assert_eq!("hunter22", password.expose());
+        let password = read_password_line(&b"no newline"[..]).expect("failed to read");
+        assert_eq!("no newline", password.expose());
+        let long = "x".repeat(valet::password::MAX_LENGTH + 1);
+        assert!(matches!(
+            read_password_line(long.as_bytes()),
+            Err(valet::user::Error::PasswordTooLong(e)) if e.len == long.len()
+        ));
+    }
+
+    #[test]
+    fn list_tree() {
+        let tree = ListTree::build(
+            [
+                ("main", "github"),
+                ("work", "alice@example.com"),
+                ("work::aws", "root"),
+                ("work::aws", "ci"),
+                ("main", "gitlab"),
+            ]
+            .map(|(lot, record)| (lot.to_owned(), record.to_owned())),
+        );
+        assert_eq!(
+            "main\n  github\n  gitlab\nwork\n  alice@example.com\n  aws\n    root\n    ci\n",
+            tree.render()
+        );
+        assert_eq!(
+            r#"{"lots":{"main":{"records":["github","gitlab"]},"work":{"records":["alice@example.com"],"lots":{"aws":{"records":["root","ci"]}}}}}"#,
+            serde_json::to_string(&tree).expect("failed to serialize")
+        );
+
+        // A sub-lot's parent is drawn even without records of its own.
+        let tree = ListTree::build([("a::b".to_owned(), "x".to_owned())]);
+        assert_eq!("a\n  b\n    x\n", tree.render());
+    }
+
+    #[test]
+    fn complete_paths() {
+        let lots = vec!["main".to_owned(), "work".to_owned(), "work::aws".to_owned()];
+        let paths: Vec<(String, String)> = [
+            ("main", "github"),
+            ("main", "gitlab"),
+            ("work", "alice@example.com"),
+            ("work::aws", "root"),
+        ]
+        .into_iter()
+        .map(|(lot, label)| (lot.to_owned(), label.to_owned()))
+        .collect();
+
+        assert_eq!(
+            vec!["github", "gitlab"],
+            complete_path(&lots, &paths, "git")
+        );
+        assert_eq!(
+            vec!["work::", "work::aws::"],
+            complete_path(&lots, &paths, "wo")
+        );
+        assert_eq!(
+            vec!["work::alice@example.com", "work::aws::"],
+            complete_path(&lots, &paths, "work::a")
+        );
+        assert_eq!(
+            vec!["work::aws::root"],
+            complete_path(&lots, &paths, "work::aws::")
+        );
+        assert!(complete_path(&lots, &paths, "nope::").is_empty());
+        assert_eq!(
+            vec!["github", "gitlab", "main::", "work::", "work::aws::"],
+            complete_path(&lots, &paths, "")
+        );
+    }
+
+    #[test]
+    fn edit_record() {
+        let data = Data::new("old".try_into().unwrap()).add_extra("notes".into(), "a".into());
+
+        let mut temp = None;
+        let edited = edit_value(data.password().expose(), |path| {
+            assert_eq!("old", std::fs::read_to_string(path)?);
+            temp = Some(path.to_owned());
+            std::fs::write(path, "new\n")
+        })
+        .expect("failed to edit");
+        assert!(!temp.expect("editor not run").exists());
+        let (password, extra) = apply_edit(&data, None, &edited).expect("invalid edit");
+        assert_eq!("new", password.expose());
+        assert_eq!(data.extra(), &extra);
+
+        let notes = data.extra()["notes"].as_str();
+        let edited = edit_value(notes, |path| std::fs::write(path, "line 1\nline 2\n"))
+            .expect("failed to edit");
+        let (password, extra) = apply_edit(&data, Some("notes"), &edited).expect("invalid edit");
+        assert_eq!("old", password.expose());
+        assert_eq!("line 1\nline 2", extra["notes"]);
+
+        let failed = edit_value("old", |_| Err(io::Error::other("editor crashed")));
+        assert!(failed.is_err());
+        let long = "x".repeat(valet::password::MAX_LENGTH + 1);
+        assert!(apply_edit(&data, None, &long).is_none());
+    }
+
+    #[test]
+    fn get_output_shape() {
+        let line = serde_json::to_string(&GetOutput {
+            lot: Some("main".into()),
+            label: "email".into(),
+            value: "hunter22".into(),
+            uuid: "0191e0a4-0000-7000-8000-000000000000".into(),
+            extra: std::collections::BTreeMap::new(),
+        })
+        .expect("failed to serialize");
+        assert_eq!(
+            line,
+            r#"{"lot":"main","label":"email","value":"hunter22","uuid":"0191e0a4-0000-7000-8000-000000000000"}"#
+        );
+
+        let line = serde_json::to_string(&GetOutput {
+            lot: None,
+            label: "email".into(),
+            value: "hunter22".into(),
+            uuid: "0191e0a4-0000-7000-8000-000000000000".into(),
+            extra: [("notes", "hi"), ("otp", "123456")].into(),
+        })
+        .expect("failed to serialize");
+        assert_eq!(
+            line,
+            r#"{"label":"email","value":"hunter22","uuid":"0191e0a4-0000-7000-8000-000000000000","extra":{"notes":"hi","otp":"123456"}}"#
+        );
+    }
+
+    fn extra<'a>(label: &'a Label, key: &str) -> Option<&'a str> {
+        label.extra().get(key).map(String::as_str)
+    }
+
+    #[test]
+    fn import_apple_fixture() {
+        let parsed = parse_apple(&include_bytes!("../../tests/fixtures/apple.csv")[..]);
+        assert_eq!(0, parsed.malformed);
+        let rows = parsed.rows;
+        assert_eq!(4, rows.len());
+
+        let (label, data) = &rows[0];
+        assert_eq!(
+            &LabelName::Domain {
+                id: "alice".into(),
+                domain: "example.com".into()
+            },
+            label.name()
+        );
+        assert_eq!(Some("https://example.com/"), extra(label, "url"));
+        assert_eq!("hunter22", data.password().expose());
+
+        let (label, data) = &rows[1];
+        assert_eq!(&LabelName::Simple("github.com".into()), label.name());
+        assert_eq!(Some("bob@example.com"), label.username());
+        assert_eq!(Some("work account"), data.notes());
+
+        // A secure note: no url, username or password to carry.
+        let (label, data) = &rows[2];
+        assert_eq!(&LabelName::Simple("Home-Wifi".into()), label.name());
+        assert!(label.extra().is_empty());
+        assert!(data.password().is_empty());
+        assert_eq!(Some("Network: home, key: correct horse"), data.notes());
+
+        // Not a note, it has a password, but the empty url is skipped.
+        let (label, data) = &rows[3];
+        assert_eq!(&LabelName::Simple("router".into()), label.name());
+        assert_eq!(None, extra(label, "url"));
+        assert_eq!(Some("admin"), label.username());
+        assert_eq!("pw123456", data.password().expose());
+    }
+
+    #[test]
+    fn import_chrome_fixture() {
+        let parsed = parse_chrome(&include_bytes!("../../tests/fixtures/chrome.csv")[..]);
+        assert_eq!(0, parsed.malformed);
+        let rows = parsed.rows;
+        assert_eq!(3, rows.len());
+
+        let (label, data) = &rows[0];
+        assert_eq!(
+            &LabelName::Domain {
+                id: "alice".into(),
+                domain: "example.com".into()
+            },
+            label.name()
+        );
+        assert_eq!(Some("https://example.com/login"), extra(label, "url"));
+        assert_eq!(None, extra(label, "username"));
+        assert_eq!("hunter22", data.password().expose());
+        assert!(data.extra().is_empty());
+
+        // No name, so the label falls back to the url's domain.
+        let (label, data) = &rows[1];
+        assert_eq!(
+            &LabelName::Domain {
+                id: "bob@example.com".into(),
+                domain: "github.com".into()
+            },
+            label.name()
+        );
+        assert_eq!("s3cret!pw", data.password().expose());
+        assert_eq!(Some(&"work account".to_owned()), data.extra().get("notes"));
+
+        let (label, _) = &rows[2];
+        assert_eq!(
+            &LabelName::Simple("news.ycombinator.com".into()),
+            label.name()
+        );
+        assert_eq!(None, label.username());
+    }
+
+    #[test]
+    fn import_firefox_fixture() {
+        let parsed = parse_firefox(&include_bytes!("../../tests/fixtures/firefox.csv")[..]);
+        assert_eq!(0, parsed.malformed);
+        let rows = parsed.rows;
+        assert_eq!(3, rows.len());
+
+        let (label, data) = &rows[0];
+        assert_eq!(
+            &LabelName::Domain {
+                id: "alice".into(),
+                domain: "example.com".into()
+            },
+            label.name()
+        );
+        assert_eq!(Some("https://example.com"), extra(label, "url"));
+        assert_eq!("hunter22", data.password().expose());
+
+        let (label, _) = &rows[1];
+        assert_eq!(Some("bob@example.com"), label.username());
+        assert_eq!(
+            &LabelName::Domain {
+                id: "bob@example.com".into(),
+                domain: "github.com".into()
+            },
+            label.name()
+        );
+
+        // The port is dropped from the label but kept in the url extra.
+        let (label, data) = &rows[2];
+        assert_eq!(
+            &LabelName::Simple("accounts.example.org".into()),
+            label.name()
+        );
+        assert_eq!(
+            Some("https://accounts.example.org:8443"),
+            extra(label, "url")
+        );
+        assert_eq!("pw123456", data.password().expose());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn import_dry_run() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (_, mut lot) = valet::User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+
+        let mut csv = include_bytes!("../../tests/fixtures/chrome.csv").to_vec();
+        csv.extend_from_slice(b",not a url,carol,pw,\n");
+        let parsed = parse_chrome(&csv[..]);
+        assert_eq!(1, parsed.malformed);
+        let options = ImportOptions {
+            dry_run: true,
+            ..ImportOptions::default()
+        };
+        let records = import_rows(&db, &mut lot, parsed, options).await;
+        let labels: Vec<String> = records
+            .iter()
+            .map(|r| r.label().name().to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "alice@example.com",
+                "bob@example.com@github.com",
+                "news.ycombinator.com"
+            ],
+            labels
+        );
+        assert!(lot.index().is_empty());
+        let count = db
+            .row_count("records")
+            .await
+            .expect("failed to count records");
+        assert_eq!(0, count);
+
+        // Without `skip_existing` rows already in the lot merge, so a dry
+        // run still lists them.
+        let csv = &include_bytes!("../../tests/fixtures/chrome.csv")[..];
+        import_rows(&db, &mut lot, parse_chrome(csv), ImportOptions::default()).await;
+        let records = import_rows(&db, &mut lot, parse_chrome(csv), options).await;
+        assert_eq!(3, records.len());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn import_skip_existing() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (_, mut lot) = valet::User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        let options = ImportOptions {
+            skip_existing: true,
+            ..ImportOptions::default()
+        };
+        let csv = &include_bytes!("../../tests/fixtures/chrome.csv")[..];
+
+        let first = import_rows(&db, &mut lot, parse_chrome(csv), options).await;
+        assert_eq!(3, first.len());
+        let again = import_rows(&db, &mut lot, parse_chrome(csv), options).await;
+        assert!(again.is_empty());
+        assert_eq!(3, lot.index().len());
+        let count = db
+            .row_count("records")
+            .await
+            .expect("failed to count records");
+        assert_eq!(3, count);
     }
 }
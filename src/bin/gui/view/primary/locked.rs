@@ -7,6 +7,7 @@ use egui_inbox::UiInbox;
 use std::sync::Arc;
 use tokio::runtime::Runtime;
 use valet::SendHandler;
+use valet::encrypt::StrengthReport;
 use valet::password::Password;
 use valet::protocol::EmbeddedHandler;
 use valet::protocol::message::{Register, Unlock};
@@ -61,6 +62,9 @@ impl<'a> View for Locked<'a> {
             ui.label("Password:");
             // TODO: Update PasswordInput to operate on Password directly.
             let password_re = ui.add(PasswordInput::new(&mut state.password));
+            if password_re.changed() {
+                state.strength = None;
+            }
             ui.add_space(5.);
             ui.horizontal(|ui| {
                 if ui.add(Button::new("Unlock")).clicked()
@@ -87,7 +91,15 @@ impl<'a> View for Locked<'a> {
                         }
                     });
                 }
-                if ui.add(Button::new("Create")).clicked() {
+                // Estimated once per edit, not every frame.
+                let strength = state.strength.get_or_insert_with(|| {
+                    valet::encrypt::estimate_strength(state.password.expose())
+                });
+                if ui
+                    .add(Button::new("Create"))
+                    .on_hover_text(format!("Password strength {strength}"))
+                    .clicked()
+                {
                     // XXX: This is obviously hacky, but I don't want to deal with sharing things now.
                     let username = state.username.clone();
                     let password = state.password.clone();
@@ -115,6 +127,7 @@ impl<'a> View for Locked<'a> {
 struct State {
     username: String,
     password: Password,
+    strength: Option<StrengthReport>,
 }
 
 impl State {
@@ -125,6 +138,7 @@ impl State {
                 d.get_temp(id.with("password"))
                     .unwrap_or(Password::default())
             }),
+            strength: ctx.data(|d| d.get_temp(id.with("strength"))),
         }
     }
 
@@ -132,6 +146,9 @@ impl State {
         ctx.data_mut(|d| {
             d.insert_temp(id.with("username"), self.username);
             d.insert_temp(id.with("password"), self.password);
+            if let Some(strength) = self.strength {
+                d.insert_temp(id.with("strength"), strength);
+            }
         });
     }
 }
@@ -6,13 +6,17 @@ use tokio::runtime::Runtime;
 use valet::SendHandler;
 use valet::{
     Record,
-    password::Password,
+    password::{MaskStyle, Password},
     protocol::EmbeddedHandler,
     protocol::message::Fetch,
-    record::{Label, LabelName},
+    record::{Label, LabelName, USERNAME_KEY},
     uuid::Uuid,
 };
 
+/// Drawn in place of a password that hasn't been fetched. Fixed width,
+/// since there is no secret to measure until the user asks for it.
+const MASK: MaskStyle = MaskStyle::BULLET;
+
 enum PasswordEvent {
     Copy(Password),
     Show(Password),
@@ -58,7 +62,7 @@ impl egui::Widget for RecordRow<'_> {
 
         for event in pw_inbox.read(ui.ctx()) {
             match event {
-                PasswordEvent::Copy(pw) => ui.ctx().copy_text(pw.to_string()),
+                PasswordEvent::Copy(pw) => ui.ctx().copy_text(pw.expose().to_owned()),
                 // TODO: The revealed Password sits in egui's temp data until
                 // the row collapses or egui evicts it. Zeroizes on drop, but
                 // we should auto-evict after an idle window.
@@ -68,25 +72,11 @@ impl egui::Widget for RecordRow<'_> {
 
         ui.data_mut(|d| d.insert_temp(pw_inbox_id, pw_inbox.clone()));
 
-        let (primary, secondary) = match self.label.name() {
-            LabelName::Domain { id, domain } => (
-                domain.as_str().to_string(),
-                self.label
-                    .extra()
-                    .get("username")
-                    .map(String::as_str)
-                    .unwrap_or(id.as_str())
-                    .to_string(),
-            ),
-            LabelName::Simple(s) => (
-                s.clone(),
-                self.label
-                    .extra()
-                    .get("username")
-                    .cloned()
-                    .unwrap_or_default(),
-            ),
+        let primary = match self.label.name() {
+            LabelName::Domain { domain, .. } => domain.clone(),
+            LabelName::Simple(s) => s.clone(),
         };
+        let secondary = self.label.username().unwrap_or_default().to_owned();
 
         ui.vertical(|ui| {
             ui.horizontal(|ui| {
@@ -172,10 +162,10 @@ impl egui::Widget for RecordRow<'_> {
 
                             let shown_pw = ui.data(|d| d.get_temp::<Password>(shown_pw_id));
                             let is_shown = shown_pw.is_some();
-                            let mut pw = shown_pw.unwrap_or_else(|| "xxxxxxxx".try_into().unwrap());
+                            let mut pw = shown_pw
+                                .unwrap_or_else(|| MASK.mask("").as_str().try_into().unwrap());
                             ui.add(
                                 egui::TextEdit::singleline(&mut pw)
-                                    .password(!is_shown)
                                     .interactive(false)
                                     .desired_width(text_width),
                             );
@@ -204,7 +194,7 @@ impl egui::Widget for RecordRow<'_> {
                         });
 
                         for (k, v) in self.label.extra() {
-                            if k == "username" {
+                            if k == USERNAME_KEY {
                                 continue;
                             }
                             ui.horizontal(|ui| {
@@ -1,6 +1,14 @@
-use sea_orm::DatabaseConnection;
+use crate::{lot::Lot, record::Record, uuid::Uuid};
+use sea_orm::{
+    ActiveValue::Set, ConnectionTrait, DatabaseConnection, DatabaseTransaction, EntityTrait,
+    TransactionTrait,
+};
+use serde::Serialize;
 use sqlx::SqlitePool;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
 use url::Url;
 
 /// Default SQLite path: `$XDG_DATA_HOME/valet/valet.sqlite`, falling back to
@@ -23,72 +31,903 @@ pub fn default_url() -> String {
     default_path().to_string_lossy().into_owned()
 }
 
+/// Highest `meta.format_version` this build knows how to read. Bumped
+/// alongside any migration that changes how existing data is encoded,
+/// so an older binary refuses a database a newer one has converted.
+pub const FORMAT_VERSION: i64 = 5;
+
+/// Every migration in `migrations/`, applied by [`Database::from_pool`].
+static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations");
+
+/// How many [`VaultEvent`]s a slow subscriber can fall behind by before
+/// it starts missing them, see [`broadcast::error::RecvError::Lagged`].
+const EVENT_CAPACITY: usize = 256;
+
+/// Rows per `audit_log` INSERT in [`Database::log`], four parameters
+/// each, well under SQLite's 32766 parameter limit.
+const LOG_CHUNK: usize = 1000;
+
+/// The `audit_log` table, see [`Database::log`].
+mod audit_log {
+    use sea_orm::entity::prelude::*;
+
+    #[sea_orm::model]
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "audit_log")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: i64,
+        /// Unix seconds.
+        pub at: i64,
+        pub op: String,
+        pub lot: String,
+        pub record: Option<String>,
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+/// A change to the vault, sent to every [`Database::subscribe`]r once it
+/// has been committed, so a UI can refresh just what changed, and kept
+/// in the [`Database::audit_log`].
+///
+/// Serializes with its kind as `op`, e.g.
+/// `{"op": "record_inserted", "lot": "..", "record": ".."}`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum VaultEvent {
+    RecordInserted {
+        lot: Uuid<Lot>,
+        record: Uuid<Record>,
+    },
+    /// A new revision of an existing record was saved.
+    RecordUpdated {
+        lot: Uuid<Lot>,
+        record: Uuid<Record>,
+    },
+    RecordRemoved {
+        lot: Uuid<Lot>,
+        record: Uuid<Record>,
+    },
+    LotCreated {
+        lot: Uuid<Lot>,
+    },
+    /// The lot and every record in it are gone.
+    LotDeleted {
+        lot: Uuid<Lot>,
+    },
+    /// A user was given a grant for an existing lot, by sharing it or by
+    /// [`Lot::restore`](crate::lot::Lot::restore).
+    LotGranted {
+        lot: Uuid<Lot>,
+    },
+    /// A user holding this lot generated a recovery phrase, which opens
+    /// it like their password, see
+    /// [`User::generate_recovery`](crate::user::User::generate_recovery).
+    RecoveryGenerated {
+        lot: Uuid<Lot>,
+    },
+}
+
+impl VaultEvent {
+    /// The `op` it serializes with.
+    fn op(&self) -> &'static str {
+        match self {
+            VaultEvent::RecordInserted { .. } => "record_inserted",
+            VaultEvent::RecordUpdated { .. } => "record_updated",
+            VaultEvent::RecordRemoved { .. } => "record_removed",
+            VaultEvent::LotCreated { .. } => "lot_created",
+            VaultEvent::LotDeleted { .. } => "lot_deleted",
+            VaultEvent::LotGranted { .. } => "lot_granted",
+            VaultEvent::RecoveryGenerated { .. } => "recovery_generated",
+        }
+    }
+
+    fn lot(&self) -> &Uuid<Lot> {
+        match self {
+            VaultEvent::RecordInserted { lot, .. }
+            | VaultEvent::RecordUpdated { lot, .. }
+            | VaultEvent::RecordRemoved { lot, .. }
+            | VaultEvent::LotCreated { lot }
+            | VaultEvent::LotDeleted { lot }
+            | VaultEvent::LotGranted { lot }
+            | VaultEvent::RecoveryGenerated { lot } => lot,
+        }
+    }
+
+    fn record(&self) -> Option<&Uuid<Record>> {
+        match self {
+            VaultEvent::RecordInserted { record, .. }
+            | VaultEvent::RecordUpdated { record, .. }
+            | VaultEvent::RecordRemoved { record, .. } => Some(record),
+            VaultEvent::LotCreated { .. }
+            | VaultEvent::LotDeleted { .. }
+            | VaultEvent::LotGranted { .. }
+            | VaultEvent::RecoveryGenerated { .. } => None,
+        }
+    }
+
+    /// The event an `audit_log` row records, `None` if it's malformed.
+    fn from_row(op: &str, lot: &str, record: Option<&str>) -> Option<Self> {
+        let lot = Uuid::parse(lot).ok()?;
+        let record = record.map(Uuid::parse).transpose().ok()?;
+        Some(match (op, record) {
+            ("record_inserted", Some(record)) => VaultEvent::RecordInserted { lot, record },
+            ("record_updated", Some(record)) => VaultEvent::RecordUpdated { lot, record },
+            ("record_removed", Some(record)) => VaultEvent::RecordRemoved { lot, record },
+            ("lot_created", None) => VaultEvent::LotCreated { lot },
+            ("lot_deleted", None) => VaultEvent::LotDeleted { lot },
+            ("lot_granted", None) => VaultEvent::LotGranted { lot },
+            ("recovery_generated", None) => VaultEvent::RecoveryGenerated { lot },
+            _ => return None,
+        })
+    }
+}
+
+/// A row of the [`Database::audit_log`]: what changed and when, by uuid
+/// alone, so it's safe to hand to anyone who can read the database file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AuditEntry {
+    /// Increasing in the order the changes were committed.
+    pub id: i64,
+    /// Stored to the second, and serialized as Unix seconds.
+    #[serde(serialize_with = "serialize_unix_seconds")]
+    pub at: SystemTime,
+    #[serde(flatten)]
+    pub event: VaultEvent,
+}
+
+fn serialize_unix_seconds<S: serde::Serializer>(
+    time: &SystemTime,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i64(unix_seconds(*time))
+}
+
+/// How timestamps are stored: whole seconds since the epoch, `0` for
+/// anything before it.
+pub(crate) fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// The time [`unix_seconds`] stored as `secs`.
+pub(crate) fn from_unix_seconds(secs: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
+
+/// A snapshot of the connection pool, see [`Database::pool_status`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolStatus {
+    /// Open connections, idle or not.
+    pub size: u32,
+    pub idle: usize,
+    /// Connections currently checked out for a query.
+    pub active: usize,
+}
+
+/// How [`Database::retry`] waits out another connection's lock: up to
+/// `attempts` tries in all, sleeping `initial_backoff` after the first
+/// [busy](Error::is_busy) one and twice as long after each after that, up
+/// to `max_backoff`.
+///
+/// SQLite's own busy timeout already waits on most locks. This is for
+/// the ones it can't, like a read transaction that finds another writer
+/// got in first when it tries to write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Surface the first busy error.
+    pub const NONE: RetryPolicy = RetryPolicy {
+        attempts: 1,
+        initial_backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+    };
+
+    /// The sleep after failed attempt `attempt`, counting from 1.
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Five attempts, backing off from 10ms to at most 500ms.
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 5,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
 #[derive(Clone)]
-pub struct Database(DatabaseConnection);
+pub struct Database {
+    connection: DatabaseConnection,
+    pool: SqlitePool,
+    record_quota: Option<usize>,
+    /// Set by [`Database::with_record_validation`].
+    validate_records: bool,
+    /// Cleared to simulate a backend that can't start transactions.
+    transactions: bool,
+    /// Set by [`Database::open_readonly`].
+    read_only: bool,
+    retry_policy: RetryPolicy,
+    /// Shared by every clone of this handle.
+    events: broadcast::Sender<VaultEvent>,
+}
+
+/// A [`Database`] handle for administrative tooling, required by calls
+/// that see across users, like
+/// [`User::all_usernames`](crate::user::User::all_usernames). There are no
+/// roles yet, so this only makes reaching for those calls deliberate: a
+/// plain [`Database`] can't be turned into one, so admin tooling opens
+/// its own with [`AdminDatabase::open`].
+#[derive(Clone)]
+pub struct AdminDatabase(Database);
+
+impl AdminDatabase {
+    /// [`Database::open`] for admin tooling.
+    pub async fn open(input: &str) -> Result<Self, Error> {
+        Ok(AdminDatabase(Database::open(input).await?))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn new(db: Database) -> Self {
+        AdminDatabase(db)
+    }
+
+    /// The underlying handle, for everything that isn't admin-only.
+    pub fn database(&self) -> &Database {
+        &self.0
+    }
+}
 
 impl Database {
+    /// Same as [`Database::create`].
     pub async fn new(input: &str) -> Result<Database, Error> {
-        let url = Self::parse_url(input)?;
+        Self::create(input).await
+    }
+
+    /// Open the database `input` names, creating the file (and its
+    /// directory) if it's missing. Use [`Database::open`] where a missing
+    /// file is a mistake, e.g. a mistyped path.
+    pub async fn create(input: &str) -> Result<Database, Error> {
+        let (path, query) = Self::parse_target(input)?;
 
         // Make sure the directory the sqlite file lives in exists, otherwise
         // sqlx errors out even with mode=rwc.
-        if let Ok(parsed) = Url::parse(&url) {
-            let path = parsed.path();
-            if !path.is_empty()
-                && path != "/"
-                && path != "/:memory:"
-                && let Some(parent) = std::path::Path::new(path).parent()
-                && !parent.as_os_str().is_empty()
-            {
-                let _ = std::fs::create_dir_all(parent);
-            }
+        if let Some(parent) = path.as_deref().and_then(std::path::Path::parent)
+            && !parent.as_os_str().is_empty()
+        {
+            let _ = std::fs::create_dir_all(parent);
         }
 
-        // Create the sqlx pool and run migrations on it.
+        let pool = SqlitePool::connect(&Self::format_url(path.as_deref(), query, "rwc")).await?;
+        Self::from_pool(pool, true).await
+    }
+
+    /// Open the existing database `input` names, failing with
+    /// [`Error::NotFound`] rather than creating an empty one. Pending
+    /// migrations are still applied.
+    pub async fn open(input: &str) -> Result<Database, Error> {
+        let (path, query) = Self::parse_target(input)?;
+        if let Some(path) = &path
+            && !path.exists()
+        {
+            return Err(Error::NotFound(path.clone()));
+        }
+        let pool = SqlitePool::connect(&Self::format_url(path.as_deref(), query, "rw")).await?;
+        Self::from_pool(pool, true).await
+    }
+
+    /// Open the existing database `input` names with `mode=ro`, whatever
+    /// mode the URL asks for. No migrations are run, so the file must
+    /// already be up to date, and writes through valet, like
+    /// [`User::register`](crate::user::User::register), [`Lot::save`] and
+    /// [`Record::save`], fail with [`Error::ReadOnly`] before touching
+    /// it. Loading and searching work as usual.
+    pub async fn open_readonly(input: &str) -> Result<Database, Error> {
+        let (path, query) = Self::parse_target(input)?;
+        if let Some(path) = &path
+            && !path.exists()
+        {
+            return Err(Error::NotFound(path.clone()));
+        }
+        let query = query.map(|query| {
+            query
+                .split('&')
+                .filter(|p| p.split('=').next() != Some("mode"))
+                .collect::<Vec<_>>()
+                .join("&")
+        });
+        let url = Self::format_url(path.as_deref(), query.as_deref(), "ro");
         let pool = SqlitePool::connect(&url).await?;
-        sqlx::migrate!("./migrations")
-            .run(&pool)
-            .await
-            .map_err(sqlx::Error::from)?;
+        let mut db = Self::from_pool(pool, false).await?;
+        db.read_only = true;
+        Ok(db)
+    }
+
+    /// A shared-cache in-memory database, which every [`Database`] opened
+    /// with the same `name` in this process sees, unlike `:memory:`, which
+    /// is private to one pool. It lasts while any connection to it is
+    /// open, so the pool keeps one open for as long as it lives.
+    pub async fn in_memory_named(name: &str) -> Result<Database, Error> {
+        let options = SqliteConnectOptions::new()
+            .filename(format!("file:valet-{name}"))
+            .in_memory(true)
+            .shared_cache(true);
+        let pool = SqlitePoolOptions::new()
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?;
+        Self::from_pool(pool, true).await
+    }
+
+    /// True if `input` names a database file that exists, so
+    /// [`Database::open`] would find it. Never true for an in-memory
+    /// database.
+    pub fn exists(input: &str) -> Result<bool, Error> {
+        let (path, _) = Self::parse_target(input)?;
+        Ok(path.is_some_and(|path| path.exists()))
+    }
+
+    /// Wrap an existing sqlx pool, for applications that already hold one
+    /// and want valet's tables to live alongside their own.
+    ///
+    /// With `migrate` the valet migrations are applied to the pool, as
+    /// [`Database::new`] does. Without it the caller is responsible for
+    /// having run them; either way a database stamped with a newer
+    /// [`FORMAT_VERSION`], or migrated further than this build can (see
+    /// [`Error::SchemaTooNew`]), is refused.
+    pub async fn from_pool(pool: SqlitePool, migrate: bool) -> Result<Database, Error> {
+        // The format check comes first: a newer binary may have applied
+        // migrations this one doesn't know about.
+        if let Some(found) = Self::read_format_version(&pool).await?
+            && found > FORMAT_VERSION
+        {
+            return Err(Error::UnsupportedFormat(found));
+        }
+        let schema = Self::read_schema_version(&pool).await?;
+        if schema > Self::latest_schema_version() {
+            return Err(Error::SchemaTooNew(schema));
+        }
+        if migrate {
+            MIGRATOR.run(&pool).await.map_err(sqlx::Error::from)?;
+        }
 
         // Convert to a sea-orm connection backed by the same pool.
-        let db = sea_orm::SqlxSqliteConnector::from_sqlx_sqlite_pool(pool);
-        Ok(Database(db))
+        let connection = sea_orm::SqlxSqliteConnector::from_sqlx_sqlite_pool(pool.clone());
+        Ok(Database {
+            connection,
+            pool,
+            record_quota: None,
+            validate_records: false,
+            transactions: true,
+            read_only: false,
+            retry_policy: RetryPolicy::default(),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        })
+    }
+
+    /// Receive a [`VaultEvent`] for every change made through this handle
+    /// or its clones from now on. Changes made by other processes, or
+    /// other [`Database`]s on the same file, aren't seen.
+    pub fn subscribe(&self) -> broadcast::Receiver<VaultEvent> {
+        self.events.subscribe()
+    }
+
+    /// Record `events` in the audit log on `conn`, which should be the
+    /// transaction making the change, so the rows land if and only if it
+    /// does. Tell subscribers with [`Database::notify`] once it commits.
+    pub(crate) async fn log(
+        conn: &impl ConnectionTrait,
+        events: &[VaultEvent],
+    ) -> Result<(), Error> {
+        let at = unix_seconds(SystemTime::now());
+        for chunk in events.chunks(LOG_CHUNK) {
+            let rows = chunk.iter().map(|event| audit_log::ActiveModel {
+                at: Set(at),
+                op: Set(event.op().to_owned()),
+                lot: Set(event.lot().to_string()),
+                record: Set(event.record().map(ToString::to_string)),
+                ..Default::default()
+            });
+            audit_log::Entity::insert_many(rows).exec(conn).await?;
+        }
+        Ok(())
+    }
+
+    /// Tell subscribers about committed changes, already written with
+    /// [`Database::log`]. Nobody listening is fine.
+    pub(crate) fn notify(&self, events: impl IntoIterator<Item = VaultEvent>) {
+        for event in events {
+            let _ = self.events.send(event);
+        }
+    }
+
+    /// Every change made through valet to this database since `since`,
+    /// oldest first, whichever process or user made it. Only uuids and
+    /// the kind of change are kept, never names, values or keys.
+    ///
+    /// A row that doesn't parse, say one written by a newer version, is
+    /// [`Error::InvalidAuditEntry`].
+    pub async fn audit_log(&self, since: SystemTime) -> Result<Vec<AuditEntry>, Error> {
+        let rows: Vec<(i64, i64, String, String, Option<String>)> = sqlx::query_as(
+            "SELECT id, at, op, lot, record FROM audit_log WHERE at >= ? ORDER BY id",
+        )
+        .bind(unix_seconds(since))
+        .fetch_all(self.pool())
+        .await?;
+        rows.into_iter()
+            .map(|(id, at, op, lot, record)| {
+                let event = VaultEvent::from_row(&op, &lot, record.as_deref())
+                    .ok_or(Error::InvalidAuditEntry(id))?;
+                Ok(AuditEntry {
+                    id,
+                    at: from_unix_seconds(at),
+                    event,
+                })
+            })
+            .collect()
+    }
+
+    /// Cap every lot at `quota` records. Saving a record that would take
+    /// a lot past it fails with
+    /// [`record::Error::QuotaExceeded`](crate::record::Error::QuotaExceeded);
+    /// updating existing records is always allowed. Unlimited by default.
+    pub fn with_record_quota(mut self, quota: usize) -> Self {
+        self.record_quota = Some(quota);
+        self
+    }
+
+    /// The per-lot record cap set by [`Database::with_record_quota`].
+    pub fn record_quota(&self) -> Option<usize> {
+        self.record_quota
+    }
+
+    /// Run [`Record::validate`](crate::record::Record::validate) on every
+    /// record before saving it, so a broken `otp` secret fails the save
+    /// with [`record::Error::Otp`](crate::record::Error::Otp) instead of
+    /// being stored. Off by default.
+    pub fn with_record_validation(mut self) -> Self {
+        self.validate_records = true;
+        self
+    }
+
+    /// Whether [`Database::with_record_validation`] is set.
+    pub fn validates_records(&self) -> bool {
+        self.validate_records
+    }
+
+    /// Retry lock contention with `policy`, see [`Database::retry`].
+    /// [`RetryPolicy::default`] unless set.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// The policy set by [`Database::with_retry_policy`].
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    /// Run `f`, running it again under this handle's [`RetryPolicy`] for
+    /// as long as it fails because the database is
+    /// [busy](Error::is_busy). Any other error, or the last busy one, is
+    /// returned as is.
+    ///
+    /// `f` must be safe to run more than once, e.g. a single query, which
+    /// SQLite either runs or doesn't when it's busy.
+    pub async fn retry<T, E, F>(&self, mut f: F) -> Result<T, E>
+    where
+        F: AsyncFnMut() -> Result<T, E>,
+        E: std::error::Error + 'static,
+    {
+        let mut attempt = 1;
+        loop {
+            match f().await {
+                Err(e) if attempt < self.retry_policy.attempts && is_busy(&e) => {
+                    let backoff = self.retry_policy.backoff(attempt);
+                    tracing::debug!("database busy, retrying in {backoff:?}: {e}");
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Whether this is a [`Database::open_readonly`] handle.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fail with [`Error::ReadOnly`] on a [`Database::open_readonly`]
+    /// handle, before a write gets as far as SQLite.
+    pub(crate) fn check_writable(&self) -> Result<(), Error> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        Ok(())
     }
 
     pub(crate) fn connection(&self) -> &DatabaseConnection {
-        &self.0
+        &self.connection
     }
 
-    fn parse_url(input: &str) -> Result<String, Error> {
-        // Apply default base.
-        let result = Url::parse(input).or_else(|err| match err {
-            url::ParseError::RelativeUrlWithoutBase => {
-                Ok(Url::parse(&format!("sqlite://{}", input))?)
+    /// Whether [`Database::transaction`] can be used, or callers have to
+    /// fall back to writing directly.
+    pub(crate) fn supports_transactions(&self) -> bool {
+        self.transactions
+    }
+
+    /// Run `f` inside a transaction, committing if it returns `Ok` and
+    /// rolling back if it returns `Err`, so none of its writes land.
+    ///
+    /// `f` gets the transaction to run its queries against. Its error type
+    /// is the caller's, as long as a failure to begin or commit converts
+    /// into it.
+    ///
+    /// If the database is [busy](Error::is_busy) at any point, the whole
+    /// transaction is rolled back and [retried](Database::retry) from
+    /// the start, so `f` must be safe to run more than once: do anything
+    /// with side effects outside the database before, and only write in
+    /// `f`.
+    pub async fn transaction<T, E, F>(&self, mut f: F) -> Result<T, E>
+    where
+        F: AsyncFnMut(&DatabaseTransaction) -> Result<T, E>,
+        E: From<Error> + std::error::Error + 'static,
+    {
+        self.retry(async || -> Result<T, E> {
+            let txn = self.connection.begin().await.map_err(Error::from)?;
+            match f(&txn).await {
+                Ok(value) => {
+                    txn.commit().await.map_err(Error::from)?;
+                    Ok(value)
+                }
+                Err(e) => {
+                    if let Err(rollback) = txn.rollback().await {
+                        tracing::warn!("failed to roll back transaction: {rollback}");
+                    }
+                    Err(e)
+                }
             }
-            _ => Err(Error::Url(err)),
-        });
+        })
+        .await
+    }
 
-        // Apply default mode.
-        let result = result.map(|mut url| {
-            if !url.query_pairs().any(|(k, _)| k == "mode") {
-                url.query_pairs_mut().append_pair("mode", "rwc");
+    #[cfg(test)]
+    pub(crate) fn without_transactions(mut self) -> Self {
+        self.transactions = false;
+        self
+    }
+
+    /// The sqlx pool behind [`Database::connection`], for the odd query
+    /// that sea-orm has no model for.
+    pub(crate) fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// The data format version this database is stamped with, see
+    /// [`FORMAT_VERSION`].
+    pub async fn format_version(&self) -> Result<i64, Error> {
+        Ok(Self::read_format_version(self.pool())
+            .await?
+            .unwrap_or(FORMAT_VERSION))
+    }
+
+    /// The version of the newest migration applied to this database, `0`
+    /// for one that has never been migrated. Never newer than the newest
+    /// migration this build has, see [`Error::SchemaTooNew`].
+    pub async fn schema_version(&self) -> Result<i64, Error> {
+        Self::read_schema_version(self.pool()).await
+    }
+
+    /// The version of the newest migration this build knows.
+    pub fn latest_schema_version() -> i64 {
+        MIGRATOR.iter().map(|m| m.version).max().unwrap_or(0)
+    }
+
+    /// Confirm the database answers a trivial query, e.g. for a service's
+    /// readiness probe. Fails if the pool is closed or SQLite is
+    /// unreachable.
+    pub async fn health_check(&self) -> Result<(), Error> {
+        sqlx::query("SELECT 1").execute(self.pool()).await?;
+        Ok(())
+    }
+
+    /// How many connections the pool holds, and how many are in use.
+    pub fn pool_status(&self) -> PoolStatus {
+        let size = self.pool.size();
+        let idle = self.pool.num_idle();
+        PoolStatus {
+            size,
+            idle,
+            active: (size as usize).saturating_sub(idle),
+        }
+    }
+
+    /// Cheap, routine upkeep: fold the WAL back into the main file and let
+    /// SQLite refresh its query planner statistics.
+    pub async fn optimize(&self) -> Result<(), Error> {
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(self.pool())
+            .await?;
+        sqlx::query("PRAGMA optimize").execute(self.pool()).await?;
+        Ok(())
+    }
+
+    /// Rebuild the database file, returning the pages freed by deleted
+    /// lots and records to the filesystem. Rewrites the whole file, so
+    /// this is slow and needs as much free disk as the database is big.
+    pub async fn vacuum(&self) -> Result<(), Error> {
+        sqlx::query("VACUUM").execute(self.pool()).await?;
+        Ok(())
+    }
+
+    /// Every table in the database by name, SQLite's internal tables
+    /// excluded. For support tooling, with [`Database::row_count`].
+    pub async fn table_names(&self) -> Result<Vec<String>, Error> {
+        Ok(sqlx::query_scalar(
+            "SELECT name FROM sqlite_master \
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )
+        .fetch_all(self.pool())
+        .await?)
+    }
+
+    /// How many rows `table` has. The name must be one of
+    /// [`Database::table_names`], anything else is
+    /// [`Error::UnknownTable`], since it can't be bound as a parameter.
+    pub async fn row_count(&self, table: &str) -> Result<i64, Error> {
+        if !self.table_names().await?.iter().any(|t| t == table) {
+            return Err(Error::UnknownTable(table.to_owned()));
+        }
+        let query = format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\""));
+        Ok(sqlx::query_scalar(&query).fetch_one(self.pool()).await?)
+    }
+
+    /// `None` until the `meta` table has been created.
+    async fn read_format_version(pool: &SqlitePool) -> Result<Option<i64>, Error> {
+        let has_meta: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'meta')",
+        )
+        .fetch_one(pool)
+        .await?;
+        if !has_meta {
+            return Ok(None);
+        }
+        Ok(sqlx::query_scalar(
+            "SELECT CAST(value AS INTEGER) FROM meta WHERE key = 'format_version'",
+        )
+        .fetch_optional(pool)
+        .await?)
+    }
+
+    /// `0` until sqlx has created its `_sqlx_migrations` table.
+    async fn read_schema_version(pool: &SqlitePool) -> Result<i64, Error> {
+        let has_migrations: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master \
+             WHERE type = 'table' AND name = '_sqlx_migrations')",
+        )
+        .fetch_one(pool)
+        .await?;
+        if !has_migrations {
+            return Ok(0);
+        }
+        let version: Option<i64> =
+            sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success")
+                .fetch_one(pool)
+                .await?;
+        Ok(version.unwrap_or(0))
+    }
+
+    /// Split `input` into the database file it names and its query
+    /// parameters. The file is `None` for an in-memory database, which
+    /// is only ever asked for explicitly: `:memory:`, or a `sqlite:` URL
+    /// with nothing after the scheme.
+    ///
+    /// Accepts bare filesystem paths (relative or absolute), `sqlite:`
+    /// URLs in either `sqlite://path` or `sqlite:path` form, and `file:`
+    /// URLs.
+    fn parse_target(input: &str) -> Result<(Option<PathBuf>, Option<&str>), Error> {
+        // Only URLs carry a query; a `?` in a bare path is part of the
+        // file name.
+        let (target, query) = match input.split_once('?') {
+            Some((target, query))
+                if target.starts_with("sqlite:") || target.starts_with("file:") =>
+            {
+                (target, Some(query))
             }
-            url
-        });
+            _ => (input, None),
+        };
+        let path = if let Some(rest) = target.strip_prefix("sqlite:") {
+            percent_decode(rest.strip_prefix("//").unwrap_or(rest))?
+        } else if target.starts_with("file:") {
+            let path = Url::parse(target)?
+                .to_file_path()
+                .map_err(|()| Error::InvalidPath(input.into()))?;
+            return Ok((Some(path), query));
+        } else {
+            target.to_owned()
+        };
+        if path.is_empty() || path == ":memory:" {
+            Ok((None, query))
+        } else {
+            Ok((Some(PathBuf::from(path)), query))
+        }
+    }
 
-        result.map(|url| url.to_string()).or_else(|err| match err {
-            Error::Url(url::ParseError::EmptyHost) => Ok("sqlite://:memory:".into()),
-            _ => Err(err),
-        })
+    /// The `sqlite://` URL sqlx opens `path` with, defaulting to `mode`
+    /// unless the query already sets one.
+    fn format_url(path: Option<&std::path::Path>, query: Option<&str>, mode: &str) -> String {
+        let Some(path) = path else {
+            return match query {
+                Some(query) => format!("sqlite://:memory:?{query}"),
+                None => "sqlite://:memory:".into(),
+            };
+        };
+        // sqlx percent-decodes the path and splits it from the query at
+        // the first `?`.
+        let path = path
+            .to_string_lossy()
+            .replace('%', "%25")
+            .replace('?', "%3F");
+        match query {
+            Some(query)
+                if query
+                    .split('&')
+                    .any(|p| p.split('=').next() == Some("mode")) =>
+            {
+                format!("sqlite://{path}?{query}")
+            }
+            Some(query) if !query.is_empty() => format!("sqlite://{path}?{query}&mode={mode}"),
+            _ => format!("sqlite://{path}?mode={mode}"),
+        }
     }
 }
 
+/// Decode `%XX` escapes, as in the path of a `sqlite:` URL.
+fn percent_decode(s: &str) -> Result<String, Error> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| Error::InvalidPath(s.into()))
+}
+
 #[derive(Debug)]
 pub enum Error {
+    /// The database is stamped with a `format_version` newer than
+    /// [`FORMAT_VERSION`].
+    UnsupportedFormat(i64),
+    /// The database has had a migration newer than any this build has,
+    /// see [`Database::schema_version`].
+    SchemaTooNew(i64),
     SeaOrm(sea_orm::DbErr),
     Sqlx(sqlx::Error),
     Url(url::ParseError),
+    /// A database URL that doesn't name a local file.
+    InvalidPath(String),
+    /// [`Database::open`] was given a file that doesn't exist.
+    NotFound(PathBuf),
+    /// [`Database::row_count`] was given a name that isn't a table.
+    UnknownTable(String),
+    /// A write through a [`Database::open_readonly`] handle.
+    ReadOnly,
+    /// The `audit_log` row with this id doesn't parse, see
+    /// [`Database::audit_log`].
+    InvalidAuditEntry(i64),
+}
+
+impl Error {
+    /// Whether SQLite gave up waiting on a lock another connection holds,
+    /// `SQLITE_BUSY` or `SQLITE_LOCKED`, so the same call may well work if
+    /// tried again. See [`Database::retry`].
+    pub fn is_busy(&self) -> bool {
+        match self {
+            Error::Sqlx(e) => sqlx_is_busy(e),
+            Error::SeaOrm(e) => sea_orm_is_busy(e),
+            _ => false,
+        }
+    }
+}
+
+/// [`Error::is_busy`] for any error caused by one, or by the sea-orm and
+/// sqlx errors it wraps.
+fn is_busy(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut next = Some(err);
+    while let Some(err) = next {
+        if err.downcast_ref::<Error>().is_some_and(Error::is_busy)
+            || err
+                .downcast_ref::<sea_orm::DbErr>()
+                .is_some_and(sea_orm_is_busy)
+            || err.downcast_ref::<sqlx::Error>().is_some_and(sqlx_is_busy)
+        {
+            return true;
+        }
+        next = err.source();
+    }
+    false
+}
+
+fn sqlx_is_busy(err: &sqlx::Error) -> bool {
+    // The extended result code, whose low byte is the primary one.
+    const SQLITE_BUSY: i32 = 5;
+    const SQLITE_LOCKED: i32 = 6;
+    let sqlx::Error::Database(err) = err else {
+        return false;
+    };
+    err.code()
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| matches!(code & 0xff, SQLITE_BUSY | SQLITE_LOCKED))
+}
+
+fn sea_orm_is_busy(err: &sea_orm::DbErr) -> bool {
+    use sea_orm::{DbErr, RuntimeErr};
+    match err {
+        DbErr::Conn(RuntimeErr::SqlxError(e))
+        | DbErr::Exec(RuntimeErr::SqlxError(e))
+        | DbErr::Query(RuntimeErr::SqlxError(e)) => sqlx_is_busy(e),
+        _ => false,
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnsupportedFormat(found) => write!(
+                f,
+                "database format version {found} is newer than this build's {FORMAT_VERSION}"
+            ),
+            Error::SchemaTooNew(found) => write!(
+                f,
+                "database schema version {found} is newer than this build's {}",
+                Database::latest_schema_version()
+            ),
+            Error::SeaOrm(e) => write!(f, "sea-orm: {e}"),
+            Error::Sqlx(e) => write!(f, "sqlx: {e}"),
+            Error::Url(e) => write!(f, "url: {e}"),
+            Error::InvalidPath(path) => write!(f, "not a local database path: {path}"),
+            Error::NotFound(path) => write!(f, "no database at {}", path.display()),
+            Error::UnknownTable(table) => write!(f, "unknown table '{table}'"),
+            Error::ReadOnly => write!(f, "database is open read-only"),
+            Error::InvalidAuditEntry(id) => write!(f, "invalid audit log entry {id}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::SeaOrm(e) => Some(e),
+            Error::Sqlx(e) => Some(e),
+            Error::Url(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<sea_orm::DbErr> for Error {
@@ -108,3 +947,571 @@ impl From<url::ParseError> for Error {
         Error::Url(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lot, Record, User, record::Data, record::Label};
+
+    #[test]
+    fn parse_url() {
+        let parse_url = |input| {
+            let (path, query) = Database::parse_target(input)?;
+            Ok::<_, Error>(Database::format_url(path.as_deref(), query, "rwc"))
+        };
+        for (input, expected) in [
+            ("/abs/path.sqlite", "sqlite:///abs/path.sqlite?mode=rwc"),
+            ("./rel.sqlite", "sqlite://./rel.sqlite?mode=rwc"),
+            ("rel.sqlite", "sqlite://rel.sqlite?mode=rwc"),
+            ("file:///tmp/x.sqlite", "sqlite:///tmp/x.sqlite?mode=rwc"),
+            ("sqlite:///tmp/x.sqlite", "sqlite:///tmp/x.sqlite?mode=rwc"),
+            (
+                "sqlite://./rel.sqlite?mode=ro",
+                "sqlite://./rel.sqlite?mode=ro",
+            ),
+            (
+                "sqlite:rel.sqlite?cache=shared",
+                "sqlite://rel.sqlite?cache=shared&mode=rwc",
+            ),
+            ("/tmp/what?.sqlite", "sqlite:///tmp/what%3F.sqlite?mode=rwc"),
+            (
+                "file:///tmp/a%20b.sqlite",
+                "sqlite:///tmp/a b.sqlite?mode=rwc",
+            ),
+            (":memory:", "sqlite://:memory:"),
+            ("sqlite://:memory:", "sqlite://:memory:"),
+            ("sqlite::memory:", "sqlite://:memory:"),
+            ("sqlite://", "sqlite://:memory:"),
+        ] {
+            assert_eq!(
+                expected,
+                parse_url(input).expect("failed to parse"),
+                "{input}"
+            );
+        }
+        assert!(matches!(
+            parse_url("file://remote/x.sqlite"),
+            Err(Error::InvalidPath(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn format_version_current() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        assert_eq!(
+            FORMAT_VERSION,
+            db.format_version()
+                .await
+                .expect("failed to read format version")
+        );
+    }
+
+    #[tokio::test]
+    async fn health_check() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        db.health_check().await.expect("unhealthy database");
+        let status = db.pool_status();
+        assert!(status.size >= 1);
+        assert_eq!(status.size as usize, status.idle + status.active);
+
+        db.pool().close().await;
+        assert!(db.health_check().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn format_version_newer_is_refused() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let path = dir.path().join("valet.sqlite");
+        let url = path.to_string_lossy().into_owned();
+        let db = Database::new(&url)
+            .await
+            .expect("failed to create database");
+        sqlx::query("UPDATE meta SET value = ? WHERE key = 'format_version'")
+            .bind((FORMAT_VERSION + 1).to_string())
+            .execute(db.pool())
+            .await
+            .expect("failed to stamp format version");
+        drop(db);
+
+        match Database::new(&url).await {
+            Err(Error::UnsupportedFormat(found)) => assert_eq!(FORMAT_VERSION + 1, found),
+            Err(e) => panic!("unexpected error: {e:?}"),
+            Ok(_) => panic!("opened a database from the future"),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn retry_busy() {
+        use sqlx::{ConnectOptions, Connection};
+        use std::str::FromStr;
+
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let url = format!(
+            "sqlite://{}?mode=rwc",
+            dir.path().join("valet.sqlite").display()
+        );
+        // Fail at once on a lock rather than waiting in SQLite.
+        let options = SqliteConnectOptions::from_str(&url)
+            .expect("failed to parse url")
+            .busy_timeout(Duration::ZERO);
+        let pool = SqlitePool::connect_with(options.clone())
+            .await
+            .expect("failed to open pool");
+        let db = Database::from_pool(pool, true)
+            .await
+            .expect("failed to create database")
+            .with_retry_policy(RetryPolicy {
+                attempts: 20,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(50),
+            });
+
+        // Another connection holds the write lock for a while.
+        let mut other = options.connect().await.expect("failed to connect");
+        sqlx::query("BEGIN IMMEDIATE")
+            .execute(&mut other)
+            .await
+            .expect("failed to lock");
+
+        let impatient = db.clone().with_retry_policy(RetryPolicy::NONE);
+        match User::new("bob", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&impatient)
+            .await
+        {
+            Err(crate::user::Error::Database(e)) => assert!(e.is_busy(), "{e}"),
+            Err(e) => panic!("unexpected error: {e:?}"),
+            Ok(_) => panic!("registered through a held lock"),
+        }
+
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            sqlx::query("COMMIT")
+                .execute(&mut other)
+                .await
+                .expect("failed to unlock");
+            other.close().await.expect("failed to close");
+        });
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user through contention");
+        release.await.expect("failed to release lock");
+        assert_eq!(1, db.row_count("users").await.expect("failed to count"));
+
+        // Saves that write in a transaction rerun it whole once the lock
+        // is released.
+        let mut other = options.connect().await.expect("failed to connect");
+        sqlx::query("BEGIN IMMEDIATE")
+            .execute(&mut other)
+            .await
+            .expect("failed to lock");
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            sqlx::query("COMMIT")
+                .execute(&mut other)
+                .await
+                .expect("failed to unlock");
+            other.close().await.expect("failed to close");
+        });
+        let mut lot = Lot::new("lot");
+        lot.save(&db, &user)
+            .await
+            .expect("failed to save lot through contention");
+        Record::new(
+            &lot,
+            "alice@example.com".parse::<Label>().unwrap(),
+            Data::new("secret".try_into().unwrap()),
+        )
+        .save(&db, &mut lot)
+        .await
+        .expect("failed to save record through contention");
+        release.await.expect("failed to release lock");
+        assert_eq!(1, db.row_count("lots").await.expect("failed to count"));
+        assert_eq!(1, db.row_count("records").await.expect("failed to count"));
+    }
+
+    #[test]
+    fn retry_backoff() {
+        let policy = RetryPolicy::default();
+        assert_eq!(Duration::from_millis(10), policy.backoff(1));
+        assert_eq!(Duration::from_millis(80), policy.backoff(4));
+        assert_eq!(Duration::from_millis(500), policy.backoff(10));
+        assert_eq!(Duration::from_millis(500), policy.backoff(100));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn audit_log() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (_, mut lot) = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        let before = db.audit_log(UNIX_EPOCH).await.expect("failed to read log");
+        let record = Record::new(
+            &lot,
+            "github".parse::<Label>().unwrap(),
+            Data::new("hunter22".try_into().unwrap()),
+        );
+        let uuid = record
+            .save(&db, &mut lot)
+            .await
+            .expect("failed to save record");
+
+        let log = db.audit_log(UNIX_EPOCH).await.expect("failed to read log");
+        let [inserted] = &log[before.len()..] else {
+            panic!("expected one new entry: {log:?}");
+        };
+        assert_eq!(
+            VaultEvent::RecordInserted {
+                lot: lot.uuid().clone(),
+                record: uuid.clone(),
+            },
+            inserted.event
+        );
+        let json = serde_json::to_value(inserted).expect("failed to serialize");
+        assert_eq!("record_inserted", json["op"]);
+        assert_eq!(uuid.to_string(), json["record"]);
+        assert!(!json.to_string().contains("hunter22"));
+        assert!(!json.to_string().contains("github"));
+
+        let json = serde_json::to_value(&record).expect("failed to serialize");
+        assert_eq!(uuid.to_string(), json["uuid"]);
+        assert_eq!(lot.uuid().to_string(), json["lot"]);
+        assert!(!json.to_string().contains("hunter22"));
+        assert!(!json.to_string().contains("github"));
+        let json = serde_json::to_value(&lot).expect("failed to serialize");
+        assert_eq!(lot.uuid().to_string(), json["uuid"]);
+        assert_eq!(lot.name(), json["name"]);
+        assert!(json["created_at"].is_i64(), "{json}");
+
+        assert!(
+            db.audit_log(SystemTime::now() + Duration::from_secs(60))
+                .await
+                .expect("failed to read log")
+                .is_empty()
+        );
+        assert!(
+            sqlx::query("DELETE FROM audit_log")
+                .execute(db.pool())
+                .await
+                .is_err()
+        );
+
+        // Logged in the change's transaction, so rolled back with it.
+        let before = db.audit_log(UNIX_EPOCH).await.expect("failed to read log");
+        let result = db
+            .transaction(async |txn| -> Result<(), Error> {
+                Database::log(
+                    txn,
+                    &[VaultEvent::LotDeleted {
+                        lot: lot.uuid().clone(),
+                    }],
+                )
+                .await?;
+                Err(Error::ReadOnly)
+            })
+            .await;
+        assert!(matches!(result, Err(Error::ReadOnly)));
+        assert_eq!(
+            before,
+            db.audit_log(UNIX_EPOCH).await.expect("failed to read log")
+        );
+    }
+
+    #[tokio::test]
+    async fn schema_version() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let files: Vec<i64> = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"))
+            .expect("failed to list migrations")
+            .map(|entry| {
+                let name = entry.expect("failed to read entry").file_name();
+                let name = name.to_string_lossy();
+                let (version, _) = name.split_once('_').expect("unversioned migration");
+                version.parse().expect("bad migration version")
+            })
+            .collect();
+        assert_eq!(
+            files.len() as i64,
+            db.row_count("_sqlx_migrations")
+                .await
+                .expect("failed to count")
+        );
+        let latest = *files.iter().max().expect("no migrations");
+        assert_eq!(latest, Database::latest_schema_version());
+        assert_eq!(
+            latest,
+            db.schema_version().await.expect("failed to read version")
+        );
+    }
+
+    #[tokio::test]
+    async fn schema_too_new_is_refused() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let url = dir
+            .path()
+            .join("valet.sqlite")
+            .to_string_lossy()
+            .into_owned();
+        let db = Database::new(&url)
+            .await
+            .expect("failed to create database");
+        let newer = Database::latest_schema_version() + 1;
+        sqlx::query(
+            "INSERT INTO _sqlx_migrations \
+             (version, description, success, checksum, execution_time) \
+             VALUES (?, 'from the future', TRUE, x'00', 0)",
+        )
+        .bind(newer)
+        .execute(db.pool())
+        .await
+        .expect("failed to record migration");
+        drop(db);
+
+        for result in [
+            Database::open(&url).await,
+            Database::open_readonly(&url).await,
+        ] {
+            match result {
+                Err(Error::SchemaTooNew(found)) => assert_eq!(newer, found),
+                Err(e) => panic!("unexpected error: {e:?}"),
+                Ok(_) => panic!("opened a database from the future"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn open_and_create() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let url = dir
+            .path()
+            .join("valet.sqlite")
+            .to_string_lossy()
+            .into_owned();
+        assert!(!Database::exists(&url).expect("failed to check"));
+        assert!(matches!(
+            Database::open(&url).await,
+            Err(Error::NotFound(_))
+        ));
+        assert!(!Database::exists(&url).expect("failed to check"));
+
+        let db = Database::create(&url)
+            .await
+            .expect("failed to create database");
+        drop(db);
+        assert!(Database::exists(&url).expect("failed to check"));
+        Database::open(&url).await.expect("failed to open database");
+
+        assert!(!Database::exists(":memory:").expect("failed to check"));
+        Database::open(":memory:")
+            .await
+            .expect("failed to open database");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn open_readonly() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let url = dir
+            .path()
+            .join("valet.sqlite")
+            .to_string_lossy()
+            .into_owned();
+        assert!(matches!(
+            Database::open_readonly(&url).await,
+            Err(Error::NotFound(_))
+        ));
+        let db = Database::create(&url)
+            .await
+            .expect("failed to create database");
+        let (alice, mut lot) = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        let label: Label = "nix@example.com".parse().unwrap();
+        Record::new(&lot, label.clone(), Data::new("secret".try_into().unwrap()))
+            .save(&db, &mut lot)
+            .await
+            .expect("failed to save record");
+        db.pool().close().await;
+
+        let db = Database::open_readonly(&format!("sqlite://{url}?mode=rwc"))
+            .await
+            .expect("failed to open database");
+        assert!(db.is_read_only());
+        let alice = User::load(&db, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load user");
+        let mut lot = Lot::load(&db, crate::lot::DEFAULT_LOT, &alice)
+            .await
+            .expect("failed to load lot")
+            .expect("missing lot");
+        let query = "nix@example.com".parse().unwrap();
+        let (_, uuid) = lot.index().search(&query).next().expect("no hit");
+        let record = Record::show(&db, &lot, &uuid.clone())
+            .await
+            .expect("failed to show record")
+            .expect("missing record");
+        assert_eq!("secret", record.password().expose());
+
+        assert!(matches!(
+            record.save(&db, &mut lot).await,
+            Err(crate::record::Error::Database(Error::ReadOnly))
+        ));
+        assert!(matches!(
+            lot.save(&db, &alice).await,
+            Err(crate::lot::Error::Database(Error::ReadOnly))
+        ));
+        assert!(matches!(
+            User::new("bob", "password".try_into().unwrap())
+                .expect("failed to make user")
+                .register(&db)
+                .await,
+            Err(crate::user::Error::Database(Error::ReadOnly))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn in_memory_named() {
+        let a = Database::in_memory_named("shared")
+            .await
+            .expect("failed to create database");
+        let b = Database::in_memory_named("shared")
+            .await
+            .expect("failed to create database");
+        let other = Database::in_memory_named("other")
+            .await
+            .expect("failed to create database");
+        User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&a)
+            .await
+            .expect("failed to register user");
+        assert_eq!(1, b.row_count("users").await.expect("failed to count"));
+        assert_eq!(0, other.row_count("users").await.expect("failed to count"));
+        User::load(&b, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load user");
+    }
+
+    #[tokio::test]
+    async fn introspection() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let tables = db.table_names().await.expect("failed to list tables");
+        for table in [
+            "audit_log",
+            "lots",
+            "meta",
+            "records",
+            "user_lots",
+            "user_recovery",
+            "users",
+        ] {
+            assert!(tables.iter().any(|t| t == table), "missing {table}");
+            if table != "meta" {
+                assert_eq!(0, db.row_count(table).await.expect("failed to count"));
+            }
+        }
+        assert!(!tables.iter().any(|t| t.starts_with("sqlite_")));
+        assert!(matches!(
+            db.row_count("users; DROP TABLE users").await,
+            Err(Error::UnknownTable(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn from_pool() {
+        let pool = SqlitePool::connect("sqlite::memory:")
+            .await
+            .expect("failed to connect pool");
+        // The application's own schema, which valet's has to live beside.
+        sqlx::query("CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT)")
+            .execute(&pool)
+            .await
+            .expect("failed to create app table");
+        sqlx::query("INSERT INTO app_settings VALUES ('theme', 'dark')")
+            .execute(&pool)
+            .await
+            .expect("failed to insert app row");
+        let db = Database::from_pool(pool.clone(), true)
+            .await
+            .expect("failed to wrap pool");
+        assert_eq!(
+            1,
+            db.row_count("app_settings").await.expect("failed to count")
+        );
+        User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&pool)
+            .await
+            .expect("failed to count users");
+        assert_eq!(1, count);
+
+        // Already migrated, so a second handle can skip it.
+        let again = Database::from_pool(pool, false)
+            .await
+            .expect("failed to wrap pool");
+        User::load(&again, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load user");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn vacuum_after_delete() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("main");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let records: Vec<Record> = (0..32)
+            .map(|i| {
+                Record::new(
+                    &lot,
+                    format!("site{i}").parse::<Label>().unwrap(),
+                    Data::new("secret".try_into().unwrap()),
+                )
+            })
+            .collect();
+        Record::save_many(&db, &mut lot, &records, |_| {})
+            .await
+            .expect("failed to save records");
+        for record in &records {
+            record
+                .delete(&db, &mut lot)
+                .await
+                .expect("failed to delete record");
+        }
+
+        db.optimize().await.expect("failed to optimize");
+        db.vacuum().await.expect("failed to vacuum");
+        assert!(
+            Lot::load(&db, "main", &user)
+                .await
+                .expect("failed to load lot")
+                .expect("no lot")
+                .index()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::encrypt::{Error, SALT_SIZE};
+use argon2::{Algorithm, Argon2, Params, Version};
+use std::time::{Duration, Instant};
+
+/// Derivation time [`KdfParams::calibrate`] is usually asked for at
+/// registration.
+pub const DEFAULT_TARGET: Duration = Duration::from_millis(500);
+
+/// Calibration stops growing memory here and spends the rest of the
+/// target on iterations, so a fast machine doesn't pick parameters a
+/// slower one can't afford to unlock with.
+const MAX_MEMORY_KIB: u32 = 256 * 1024;
+
+/// Argon2id cost parameters, stored per user so each account keeps the
+/// cost it was registered (or last re-keyed) with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// The [`argon2`] crate's defaults, which every user registered before
+    /// calibration was added derives with.
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Pick parameters that take roughly `target` to derive a key with on
+    /// this machine, never going below [`KdfParams::default`].
+    ///
+    /// Memory is scaled up first, since that's what makes guessing
+    /// expensive on GPUs, then iterations make up whatever time is left.
+    /// Each step times a real derivation, so this takes a few multiples
+    /// of `target` to run.
+    pub fn calibrate(target: Duration) -> KdfParams {
+        Self::calibrate_with(target, KdfParams::measure)
+    }
+
+    /// [`KdfParams::calibrate`], timing each derivation with `measure`.
+    fn calibrate_with(
+        target: Duration,
+        mut measure: impl FnMut(&KdfParams) -> Result<Duration, Error>,
+    ) -> KdfParams {
+        let mut params = KdfParams::default();
+        let Ok(elapsed) = measure(&params) else {
+            return params;
+        };
+        if elapsed >= target {
+            return params;
+        }
+
+        let scale = target.as_secs_f64() / elapsed.as_secs_f64();
+        params.memory_kib = ((params.memory_kib as f64 * scale) as u32)
+            .clamp(params.memory_kib, MAX_MEMORY_KIB.max(params.memory_kib));
+        let Ok(elapsed) = measure(&params) else {
+            return KdfParams::default();
+        };
+        if elapsed < target {
+            let scale = target.as_secs_f64() / elapsed.as_secs_f64();
+            params.iterations =
+                ((params.iterations as f64 * scale).round() as u32).max(params.iterations);
+        }
+        params
+    }
+
+    pub(crate) fn argon2(&self) -> Result<Argon2<'static>, Error> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .map_err(|e| Error::KeyDerivation(format!("{}", e)))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    /// Time one derivation under these parameters.
+    fn measure(&self) -> Result<Duration, Error> {
+        let argon2 = self.argon2()?;
+        let mut output = [0u8; 32];
+        let start = Instant::now();
+        argon2
+            .hash_password_into(b"calibration", &[0; SALT_SIZE], &mut output)
+            .map_err(|e| Error::KeyDerivation(format!("{}", e)))?;
+        Ok(start.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_matches_argon2() {
+        assert_eq!(
+            Argon2::default().params(),
+            KdfParams::default().argon2().unwrap().params()
+        );
+    }
+
+    #[test]
+    fn calibrate() {
+        // A machine where the defaults take 10ms, and time grows with
+        // memory and iterations alike.
+        let defaults = KdfParams::default();
+        let cost = |params: &KdfParams| {
+            Ok(Duration::from_millis(10)
+                * (params.memory_kib / defaults.memory_kib)
+                * (params.iterations / defaults.iterations))
+        };
+
+        let params = KdfParams::calibrate_with(Duration::from_millis(40), cost);
+        assert_eq!(defaults.memory_kib * 4, params.memory_kib);
+        assert_eq!(defaults.iterations, params.iterations);
+        assert_eq!(defaults.parallelism, params.parallelism);
+
+        // Past the memory cap, iterations make up the rest.
+        let target = Duration::from_millis(10) * (MAX_MEMORY_KIB / defaults.memory_kib) * 3;
+        let params = KdfParams::calibrate_with(target, cost);
+        assert_eq!(MAX_MEMORY_KIB, params.memory_kib);
+        assert_eq!(defaults.iterations * 3, params.iterations);
+
+        // Never weaker than the defaults, even on a slow machine.
+        assert_eq!(
+            defaults,
+            KdfParams::calibrate_with(Duration::from_millis(5), cost)
+        );
+        assert_eq!(
+            defaults,
+            KdfParams::calibrate_with(Duration::from_secs(1), |_| {
+                Err(Error::KeyDerivation("unavailable".into()))
+            })
+        );
+
+        // The real clock picks something at least as strong.
+        let params = KdfParams::calibrate(Duration::from_millis(1));
+        assert!(params.memory_kib >= defaults.memory_kib);
+    }
+}
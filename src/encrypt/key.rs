@@ -1,15 +1,24 @@
 use crate::{
-    encrypt::{Encrypted, Error},
+    encrypt::{Encrypted, Error, KdfParams},
     password::Password,
 };
 use aes_gcm_siv::{
     Aes256GcmSiv, KeySizeUser, Nonce,
-    aead::{Aead, Key as AesKey, KeyInit, Payload, generic_array::typenum::Unsigned},
+    aead::{
+        Aead, AeadCore, Key as AesKey, KeyInit, Payload,
+        generic_array::typenum::Unsigned,
+        stream::{DecryptorBE32, EncryptorBE32, Nonce as StreamNonce, StreamBE32},
+    },
 };
-use argon2::Argon2;
+use hkdf::Hkdf;
 use rand_core::{OsRng, RngCore};
-use std::marker::PhantomData;
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use sha2::Sha256;
+use std::{
+    io::{self, Read, Write},
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 /// A generic AES-GCM-SIV symmetric key used to achive privacy and integrity.
 ///
@@ -38,12 +47,25 @@ impl<T> Key<T> {
         Key(Aes256GcmSiv::generate_key(&mut OsRng), PhantomData)
     }
 
-    /// Derive a key from a password and salt using [`argon2`].
+    /// Derive a key from a password and salt using [`argon2`] with its
+    /// default parameters.
     ///
     /// [`argon2`]: https://docs.rs/argon2/latest/argon2/
     pub fn from_password(password: &Password, salt: &[u8]) -> Result<Self, Error> {
-        let argon2 = Argon2::default();
-        let mut output_key_material = [0u8; <Aes256GcmSiv as KeySizeUser>::KeySize::USIZE];
+        Self::from_password_with(password, salt, &KdfParams::default())
+    }
+
+    /// Derive a key from a password and salt using [`argon2`] with the
+    /// given cost parameters, see [`KdfParams::calibrate`].
+    ///
+    /// [`argon2`]: https://docs.rs/argon2/latest/argon2/
+    pub fn from_password_with(
+        password: &Password,
+        salt: &[u8],
+        params: &KdfParams,
+    ) -> Result<Self, Error> {
+        let argon2 = params.argon2()?;
+        let mut output_key_material = [0u8; KEY_SIZE];
         argon2
             .hash_password_into(password.as_bytes(), salt, &mut output_key_material)
             .map_err(|e| Error::KeyDerivation(format!("{}", e)))?;
@@ -54,16 +76,53 @@ impl<T> Key<T> {
         ))
     }
 
+    /// [`Key::from_password_with`], also returning how long the derivation
+    /// took, e.g. to estimate how long the next unlock will take or warn
+    /// when the hardware is unusually slow for `params`.
+    pub fn from_password_timed(
+        password: &Password,
+        salt: &[u8],
+        params: &KdfParams,
+    ) -> Result<(Self, Duration), Error> {
+        let start = Instant::now();
+        let key = Self::from_password_with(password, salt, params)?;
+        Ok((key, start.elapsed()))
+    }
+
+    /// Derive a key for one purpose from this one with HKDF-SHA256, so a
+    /// lot or user key isn't reused directly for everything. `context`
+    /// names the purpose, e.g. `lot.key().derive_subkey::<Attachment>(b"attachments")`;
+    /// the same key and context always give the same subkey, and
+    /// different contexts give unrelated ones.
+    pub fn derive_subkey<U>(&self, context: &[u8]) -> Key<U> {
+        let mut output_key_material = Zeroizing::new([0u8; KEY_SIZE]);
+        Hkdf::<Sha256>::new(None, self.0.as_slice())
+            .expand(context, output_key_material.as_mut_slice())
+            .expect("a key's length is a valid HKDF-SHA256 output length");
+        Key::from_bytes(output_key_material.as_slice())
+    }
+
     /// Construct a Key from a slice of bytes.
     pub fn from_bytes(bytes: &[u8]) -> Self {
         Key(AesKey::<Aes256GcmSiv>::clone_from_slice(bytes), PhantomData)
     }
 
     /// Returns this key as a slice of bytes.
+    ///
+    /// Borrowed, so fine for comparisons and one-shot reads. Anything
+    /// that needs its own copy of the key material (e.g. to wrap it
+    /// under another key) should use [`Key::expose_bytes`] instead, so
+    /// the copy can't outlive its use un-zeroized.
     pub fn as_bytes(&self) -> &[u8] {
         self.0.as_slice()
     }
 
+    /// Returns an owned copy of this key's bytes which is zeroized when
+    /// dropped.
+    pub fn expose_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.0.to_vec())
+    }
+
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<Encrypted, Error> {
         self.encrypt_with_aad(plaintext, &[])
     }
@@ -88,11 +147,109 @@ impl<T> Key<T> {
         })
     }
 
-    pub fn decrypt(&self, encrypted: &Encrypted) -> Result<Vec<u8>, Error> {
+    /// The plaintext is wrapped in [`Zeroizing`] so it is wiped on drop,
+    /// callers that need to keep it around should move it somewhere that
+    /// zeroizes too.
+    pub fn decrypt(&self, encrypted: &Encrypted) -> Result<Zeroizing<Vec<u8>>, Error> {
         self.decrypt_with_aad(encrypted, &[])
     }
 
-    pub fn decrypt_with_aad(&self, encrypted: &Encrypted, aad: &[u8]) -> Result<Vec<u8>, Error> {
+    /// Encrypt everything `reader` has into `writer`, for plaintext too
+    /// big to hold in memory like [`Key::encrypt`] does, e.g. a file.
+    ///
+    /// Uses the STREAM construction: a random nonce prefix, then the
+    /// plaintext in [`STREAM_CHUNK_SIZE`] chunks, each sealed on its own
+    /// under the next nonce in the sequence and the last one marked as
+    /// such, so chunks can't be reordered, dropped or cut short without
+    /// [`Key::decrypt_stream`] noticing.
+    pub fn encrypt_stream(
+        &self,
+        mut reader: impl Read,
+        mut writer: impl Write,
+    ) -> Result<(), Error> {
+        let mut nonce = StreamNonce::<Aes256GcmSiv, StreamBE32<Aes256GcmSiv>>::default();
+        OsRng.fill_bytes(nonce.as_mut_slice());
+        writer.write_all(&nonce).map_err(Error::Io)?;
+
+        let mut encryptor = EncryptorBE32::from_aead(Aes256GcmSiv::new(&self.0), &nonce);
+        let mut chunk = Zeroizing::new(Vec::with_capacity(STREAM_CHUNK_SIZE));
+        let mut next = Zeroizing::new(Vec::with_capacity(STREAM_CHUNK_SIZE));
+        read_chunk(&mut reader, &mut chunk, STREAM_CHUNK_SIZE)?;
+        // Read a chunk ahead, to know which one is last.
+        loop {
+            next.clear();
+            read_chunk(&mut reader, &mut next, STREAM_CHUNK_SIZE)?;
+            if next.is_empty() {
+                break;
+            }
+            let sealed = encryptor
+                .encrypt_next(chunk.as_slice())
+                .map_err(Error::Encryption)?;
+            writer.write_all(&sealed).map_err(Error::Io)?;
+            std::mem::swap(&mut chunk, &mut next);
+        }
+        let sealed = encryptor
+            .encrypt_last(chunk.as_slice())
+            .map_err(Error::Encryption)?;
+        writer.write_all(&sealed).map_err(Error::Io)
+    }
+
+    /// Decrypt a [`Key::encrypt_stream`] from `reader` into `writer`.
+    ///
+    /// Each chunk is checked before its plaintext is written, but a
+    /// tampered or truncated stream is only found out at the chunk where
+    /// it goes wrong, by which point the chunks before it are already in
+    /// `writer`. Discard everything written if this fails.
+    pub fn decrypt_stream(
+        &self,
+        mut reader: impl Read,
+        mut writer: impl Write,
+    ) -> Result<(), Error> {
+        let mut nonce = StreamNonce::<Aes256GcmSiv, StreamBE32<Aes256GcmSiv>>::default();
+        reader
+            .read_exact(nonce.as_mut_slice())
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::UnexpectedEof => Error::CorruptCiphertext,
+                _ => Error::Io(e),
+            })?;
+
+        let sealed_size = STREAM_CHUNK_SIZE + <Aes256GcmSiv as AeadCore>::TagSize::USIZE;
+        let mut decryptor = DecryptorBE32::from_aead(Aes256GcmSiv::new(&self.0), &nonce);
+        let mut chunk = Vec::with_capacity(sealed_size);
+        let mut next = Vec::with_capacity(sealed_size);
+        read_chunk(&mut reader, &mut chunk, sealed_size)?;
+        loop {
+            next.clear();
+            read_chunk(&mut reader, &mut next, sealed_size)?;
+            if next.is_empty() {
+                break;
+            }
+            let plaintext = Zeroizing::new(
+                decryptor
+                    .decrypt_next(chunk.as_slice())
+                    .map_err(Error::Decryption)?,
+            );
+            writer.write_all(&plaintext).map_err(Error::Io)?;
+            std::mem::swap(&mut chunk, &mut next);
+        }
+        let plaintext = Zeroizing::new(
+            decryptor
+                .decrypt_last(chunk.as_slice())
+                .map_err(Error::Decryption)?,
+        );
+        writer.write_all(&plaintext).map_err(Error::Io)
+    }
+
+    /// See [`Key::decrypt`].
+    pub fn decrypt_with_aad(
+        &self,
+        encrypted: &Encrypted,
+        aad: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, Error> {
+        // `from_slice` panics on any other length.
+        if encrypted.nonce.len() != super::NONCE_SIZE {
+            return Err(Error::CorruptCiphertext);
+        }
         let nonce = Nonce::from_slice(&encrypted.nonce);
         let cipher = Aes256GcmSiv::new(&self.0);
         let plaintext = cipher
@@ -104,10 +261,26 @@ impl<T> Key<T> {
                 },
             )
             .map_err(Error::Decryption)?;
-        Ok(plaintext)
+        Ok(Zeroizing::new(plaintext))
     }
 }
 
+/// Length in bytes of a [`Key`]'s AES-256 key material.
+pub const KEY_SIZE: usize = <Aes256GcmSiv as KeySizeUser>::KeySize::USIZE;
+
+/// Plaintext bytes per chunk of a [`Key::encrypt_stream`]; only the last
+/// chunk is shorter.
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Read into `buf` until it holds `size` bytes or `reader` runs out.
+fn read_chunk(reader: &mut impl Read, buf: &mut Vec<u8>, size: usize) -> Result<(), Error> {
+    reader
+        .take(size as u64)
+        .read_to_end(buf)
+        .map_err(Error::Io)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,6 +294,22 @@ mod tests {
         assert_eq!(256 / 8, key.0.len());
     }
 
+    #[test]
+    fn from_password_timed() {
+        let salt = generate_salt();
+        let password = "user1password".try_into().unwrap();
+        let (key, elapsed) =
+            Key::<()>::from_password_timed(&password, &salt, &KdfParams::default())
+                .expect("error generating key");
+        assert!(elapsed > Duration::ZERO);
+        assert_eq!(
+            Key::<()>::from_password(&password, &salt)
+                .unwrap()
+                .as_bytes(),
+            key.as_bytes()
+        );
+    }
+
     #[test]
     fn encrypt() {
         let key = Key::<()>::generate();
@@ -150,6 +339,114 @@ mod tests {
         assert_eq!(plaintext, &decrypted[..]);
     }
 
+    #[test]
+    fn zeroize_key() {
+        let mut key = Key::<()>::generate();
+        let bytes: Zeroizing<Vec<u8>> = key.expose_bytes();
+        assert_eq!(key.as_bytes(), &bytes[..]);
+        assert_ne!(&[0; 32], key.as_bytes());
+        // What `ZeroizeOnDrop` runs.
+        key.zeroize();
+        assert_eq!(&[0; 32], key.as_bytes());
+        // The exposed copy is its own.
+        assert_ne!(&[0; 32], &bytes[..]);
+    }
+
+    #[test]
+    fn decrypt_zeroizes() {
+        let key = Key::<()>::generate();
+        let encrypted = key.encrypt(b"secret").expect("error encrypting");
+        let mut decrypted: Zeroizing<Vec<u8>> = key.decrypt(&encrypted).expect("error decrypting");
+        assert_eq!(b"secret", &decrypted[..]);
+        // What `Zeroizing` runs on drop.
+        decrypted.zeroize();
+        assert!(decrypted.is_empty());
+        assert_eq!(
+            b"secret",
+            &key.decrypt(&encrypted).expect("error decrypting")[..]
+        );
+    }
+
+    #[test]
+    fn encrypt_decrypt_stream() {
+        let key = Key::<()>::generate();
+        let plaintext: Vec<u8> = (0..3 * 1024 * 1024 + 123)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        for len in [
+            0,
+            5,
+            STREAM_CHUNK_SIZE,
+            2 * STREAM_CHUNK_SIZE,
+            plaintext.len(),
+        ] {
+            let mut sealed = Vec::new();
+            key.encrypt_stream(&plaintext[..len], &mut sealed)
+                .expect("error encrypting");
+            assert!(sealed.len() > len);
+            let mut opened = Vec::new();
+            key.decrypt_stream(sealed.as_slice(), &mut opened)
+                .expect("error decrypting");
+            assert!(plaintext[..len] == opened[..], "length {len}");
+        }
+    }
+
+    #[test]
+    fn decrypt_stream_corrupt() {
+        let key = Key::<()>::generate();
+        let plaintext = vec![7; 3 * 1024 * 1024];
+        let mut sealed = Vec::new();
+        key.encrypt_stream(plaintext.as_slice(), &mut sealed)
+            .expect("error encrypting");
+
+        let mut flipped = sealed.clone();
+        let middle = flipped.len() / 2;
+        flipped[middle] ^= 1;
+        assert!(matches!(
+            key.decrypt_stream(flipped.as_slice(), &mut io::sink()),
+            Err(Error::Decryption(_))
+        ));
+
+        // Cut at a chunk boundary, so every chunk left is whole.
+        let chunk = STREAM_CHUNK_SIZE + 16;
+        let truncated = &sealed[..sealed.len() - chunk];
+        assert!(matches!(
+            key.decrypt_stream(truncated, &mut io::sink()),
+            Err(Error::Decryption(_))
+        ));
+
+        assert!(matches!(
+            Key::<()>::generate().decrypt_stream(sealed.as_slice(), &mut io::sink()),
+            Err(Error::Decryption(_))
+        ));
+        assert!(matches!(
+            key.decrypt_stream(&sealed[..3], &mut io::sink()),
+            Err(Error::CorruptCiphertext)
+        ));
+    }
+
+    #[test]
+    fn derive_subkey() {
+        struct Attachments;
+        let key = Key::<()>::generate();
+        let attachments: Key<Attachments> = key.derive_subkey(b"attachments");
+        assert_eq!(
+            attachments.as_bytes(),
+            key.derive_subkey::<Attachments>(b"attachments").as_bytes()
+        );
+        assert_ne!(
+            attachments.as_bytes(),
+            key.derive_subkey::<Attachments>(b"aad").as_bytes()
+        );
+        assert_ne!(key.as_bytes(), attachments.as_bytes());
+        assert_ne!(
+            attachments.as_bytes(),
+            Key::<()>::generate()
+                .derive_subkey::<Attachments>(b"attachments")
+                .as_bytes()
+        );
+    }
+
     #[test]
     #[should_panic]
     fn from_bytes_panic() {
@@ -1,6 +1,8 @@
 use aes_gcm_siv::aead;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use rand_core::{OsRng, RngCore};
 use std::io;
+use subtle::{Choice, ConstantTimeEq};
 
 // This value can be anything really, but is generally recommended to be about
 // 128-bits. The idea is that it just needs to contain more entropy than the
@@ -14,19 +16,74 @@ pub(crate) fn generate_salt() -> [u8; SALT_SIZE] {
 }
 
 /// Represents some encrypted data, which can be decrypted again.
-#[derive(Debug, PartialEq, Eq)]
+///
+/// Equality is constant time in the contents, see [`ConstantTimeEq`], so
+/// comparing a [`User`](crate::user::User)'s validation string doesn't
+/// leak how much of it matched. Only the lengths are compared early.
+#[derive(Debug)]
 pub struct Encrypted {
     pub(crate) data: Vec<u8>,
     pub(crate) nonce: Vec<u8>,
 }
 
+impl ConstantTimeEq for Encrypted {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.data.ct_eq(&other.data) & self.nonce.ct_eq(&other.nonce)
+    }
+}
+
+impl PartialEq for Encrypted {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for Encrypted {}
+
+/// Separates the nonce and ciphertext in [`Encrypted::to_base64`]. Not in
+/// the URL-safe base64 alphabet, so it can't occur inside either half.
+const BASE64_DELIMITER: char = '.';
+
+impl Encrypted {
+    /// Encode as `<nonce>.<ciphertext>`, each half URL-safe base64
+    /// without padding, for places that need text: config files, QR
+    /// codes, pasting into a terminal.
+    pub fn to_base64(&self) -> String {
+        format!(
+            "{}{BASE64_DELIMITER}{}",
+            URL_SAFE_NO_PAD.encode(&self.nonce),
+            URL_SAFE_NO_PAD.encode(&self.data)
+        )
+    }
+
+    /// Decode the output of [`Encrypted::to_base64`].
+    pub fn from_base64(s: &str) -> Result<Self, Error> {
+        let (nonce, data) = s
+            .trim()
+            .split_once(BASE64_DELIMITER)
+            .ok_or(Error::MalformedEncoding)?;
+        Ok(Encrypted {
+            data: URL_SAFE_NO_PAD.decode(data).map_err(Error::Base64)?,
+            nonce: URL_SAFE_NO_PAD.decode(nonce).map_err(Error::Base64)?,
+        })
+    }
+}
+
 /// AES-GCM-SIV nonce size in bytes. Fixed at 96 bits; used to split packed
 /// `nonce || ciphertext` blobs.
-#[cfg(feature = "db")]
 pub(crate) const NONCE_SIZE: usize = 12;
 
 #[cfg(feature = "db")]
 impl Encrypted {
+    /// Reassemble separate `data` and `nonce` columns, failing with
+    /// [`Error::CorruptCiphertext`] unless `nonce` is [`NONCE_SIZE`] bytes.
+    pub(crate) fn from_parts(data: Vec<u8>, nonce: Vec<u8>) -> Result<Self, Error> {
+        if nonce.len() != NONCE_SIZE {
+            return Err(Error::CorruptCiphertext);
+        }
+        Ok(Encrypted { data, nonce })
+    }
+
     /// Pack `nonce || ciphertext` into a single blob for storage where a
     /// separate nonce column is not desired.
     pub(crate) fn pack(&self) -> Vec<u8> {
@@ -37,15 +94,30 @@ impl Encrypted {
         out
     }
 
-    /// Split a packed blob back into nonce + ciphertext. Panics in debug
-    /// builds if `bytes` is shorter than [`NONCE_SIZE`].
-    pub(crate) fn unpack(bytes: &[u8]) -> Self {
-        debug_assert!(bytes.len() >= NONCE_SIZE);
+    /// Split a packed blob back into nonce + ciphertext. Fails with
+    /// [`Error::CorruptCiphertext`] if `bytes` is shorter than
+    /// [`NONCE_SIZE`].
+    pub(crate) fn unpack(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < NONCE_SIZE {
+            return Err(Error::CorruptCiphertext);
+        }
         let (nonce, data) = bytes.split_at(NONCE_SIZE);
-        Encrypted {
+        Ok(Encrypted {
             data: data.to_vec(),
             nonce: nonce.to_vec(),
+        })
+    }
+
+    /// Like [`Encrypted::unpack`], but reuses `bytes` as the ciphertext
+    /// buffer so a large blob fetched from the database isn't copied a
+    /// second time. Only the nonce is allocated.
+    pub(crate) fn unpack_owned(mut bytes: Vec<u8>) -> Result<Self, Error> {
+        if bytes.len() < NONCE_SIZE {
+            return Err(Error::CorruptCiphertext);
         }
+        let nonce = bytes[..NONCE_SIZE].to_vec();
+        bytes.drain(..NONCE_SIZE);
+        Ok(Encrypted { data: bytes, nonce })
     }
 }
 
@@ -57,6 +129,20 @@ pub enum Error {
     Decoding(bitcode::Error),
     Compression(io::Error),
     Decompression(io::Error),
+    Base64(base64::DecodeError),
+    /// Text passed to [`Encrypted::from_base64`] is missing its delimiter.
+    MalformedEncoding,
+    /// A [`Stash`] buffer with a header version or [`Compression`] this
+    /// build doesn't know.
+    UnknownHeader([u8; 2]),
+    /// A [`Stash`] buffer written at a [`Stash::SCHEMA_VERSION`] its type
+    /// can't decode, e.g. by a newer build.
+    UnknownSchema(u16),
+    /// A nonce that isn't [`NONCE_SIZE`] bytes, e.g. a truncated database
+    /// column.
+    CorruptCiphertext,
+    /// Reading or writing a [`Key::encrypt_stream`].
+    Io(io::Error),
 }
 
 impl std::fmt::Display for Error {
@@ -68,13 +154,148 @@ impl std::fmt::Display for Error {
             Error::Decoding(e) => write!(f, "decoding: {e}"),
             Error::Compression(e) => write!(f, "compression: {e}"),
             Error::Decompression(e) => write!(f, "decompression: {e}"),
+            Error::Base64(e) => write!(f, "base64: {e}"),
+            Error::MalformedEncoding => write!(f, "malformed encoding"),
+            Error::UnknownHeader([version, tag]) => {
+                write!(f, "unknown header: version {version}, compression {tag}")
+            }
+            Error::UnknownSchema(version) => write!(f, "unknown schema version {version}"),
+            Error::CorruptCiphertext => write!(f, "corrupt ciphertext: bad nonce length"),
+            Error::Io(e) => write!(f, "io: {e}"),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Compression(e) | Error::Decompression(e) | Error::Io(e) => Some(e),
+            Error::Base64(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
+mod kdf;
 mod key;
 mod stash;
-pub use self::key::Key;
-pub use self::stash::Stash;
+#[cfg(feature = "strength")]
+mod strength;
+pub use self::kdf::{DEFAULT_TARGET, KdfParams};
+pub use self::key::{KEY_SIZE, Key, STREAM_CHUNK_SIZE};
+pub use self::stash::{COMPRESSION_THRESHOLD, Compression, HEADER_VERSION, Stash};
+#[cfg(feature = "strength")]
+pub(crate) use self::strength::warn_if_weak;
+#[cfg(feature = "strength")]
+pub use self::strength::{StrengthReport, WEAK_SCORE, estimate_strength};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_roundtrip() {
+        let key = Key::<()>::generate();
+        let encrypted = key.encrypt(b"plaintext").expect("failed to encrypt");
+        let encoded = encrypted.to_base64();
+        assert_eq!(1, encoded.matches(BASE64_DELIMITER).count());
+        assert_eq!(
+            encrypted,
+            Encrypted::from_base64(&encoded).expect("failed to decode")
+        );
+    }
+
+    #[cfg(feature = "db")]
+    #[test]
+    fn unpack_owned_matches_unpack() {
+        let key = Key::<()>::generate();
+        for plaintext in [&b""[..], b"short", &[7; 64 * 1024]] {
+            let packed = key.encrypt(plaintext).expect("failed to encrypt").pack();
+            let owned = Encrypted::unpack_owned(packed.clone()).expect("failed to unpack");
+            assert_eq!(Encrypted::unpack(&packed).expect("failed to unpack"), owned);
+            assert_eq!(
+                plaintext,
+                &key.decrypt(&owned).expect("failed to decrypt")[..]
+            );
+        }
+    }
+
+    #[test]
+    fn ct_eq() {
+        let key = Key::<()>::generate();
+        let a = key.encrypt(b"plaintext").expect("failed to encrypt");
+        let b = key.encrypt(b"plaintext").expect("failed to encrypt");
+        let same_nonce = Encrypted {
+            data: b.data.clone(),
+            nonce: a.nonce.clone(),
+        };
+        let short = Encrypted {
+            data: a.data[1..].to_vec(),
+            nonce: a.nonce.clone(),
+        };
+        let copy = Encrypted {
+            data: a.data.clone(),
+            nonce: a.nonce.clone(),
+        };
+        for (x, y) in [(&a, &copy), (&a, &b), (&a, &same_nonce), (&a, &short)] {
+            let structural = x.data == y.data && x.nonce == y.nonce;
+            assert_eq!(structural, bool::from(x.ct_eq(y)));
+            assert_eq!(structural, x == y);
+        }
+        assert!(a == copy);
+        assert!(a != b);
+    }
+
+    #[cfg(feature = "db")]
+    #[test]
+    fn short_nonce() {
+        let key = Key::<()>::generate();
+        let encrypted = key.encrypt(b"plaintext").expect("failed to encrypt");
+        for nonce in [
+            vec![],
+            encrypted.nonce[..4].to_vec(),
+            vec![0; NONCE_SIZE + 1],
+        ] {
+            assert!(matches!(
+                Encrypted::from_parts(encrypted.data.clone(), nonce.clone()),
+                Err(Error::CorruptCiphertext)
+            ));
+            let short = Encrypted {
+                data: encrypted.data.clone(),
+                nonce,
+            };
+            assert!(matches!(key.decrypt(&short), Err(Error::CorruptCiphertext)));
+        }
+        assert!(matches!(
+            Encrypted::unpack(&[0; NONCE_SIZE - 1]),
+            Err(Error::CorruptCiphertext)
+        ));
+        assert!(matches!(
+            Encrypted::unpack_owned(vec![0; NONCE_SIZE - 1]),
+            Err(Error::CorruptCiphertext)
+        ));
+    }
+
+    #[test]
+    fn base64_edge_cases() {
+        for (data, nonce) in [
+            (vec![], vec![0; 12]),
+            (b"a.b.c".to_vec(), b"..........".to_vec()),
+            (vec![0xff; 64], vec![]),
+        ] {
+            let encrypted = Encrypted { data, nonce };
+            assert_eq!(
+                encrypted,
+                Encrypted::from_base64(&encrypted.to_base64()).expect("failed to decode")
+            );
+        }
+        assert!(matches!(
+            Encrypted::from_base64("no delimiter"),
+            Err(Error::MalformedEncoding)
+        ));
+        assert!(matches!(
+            Encrypted::from_base64("AAAA.not base64!"),
+            Err(Error::Base64(_))
+        ));
+    }
+}
@@ -2,6 +2,42 @@ use crate::encrypt::{Encrypted, Error, Key};
 use bitcode::{DecodeOwned, Encode};
 use std::io;
 
+/// Version of the header [`Stash::compress`] puts in front of its output.
+/// Bump it alongside any change to the header's layout.
+///
+/// Version 1 headers are `[1, compression]`; version 2 adds the type's
+/// [`Stash::SCHEMA_VERSION`] as a little-endian `u16`, for
+/// `[2, compression, schema, schema]`.
+pub const HEADER_VERSION: u8 = 2;
+
+/// Encodings shorter than this are stored uncompressed by default, snappy
+/// framing costs more than it saves on a few bytes.
+pub const COMPRESSION_THRESHOLD: usize = 64;
+
+/// The first byte of snappy's stream identifier chunk. Buffers written
+/// before the header existed start with it, and are read as
+/// [`Compression::Snappy`].
+const SNAPPY_STREAM_ID: u8 = 0xff;
+
+/// How a [`Stash`]'s encoding is compressed, recorded in the second byte
+/// of its header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Compression {
+    None = 0,
+    Snappy = 1,
+}
+
+impl Compression {
+    fn from_header(version: u8, tag: u8) -> Result<Self, Error> {
+        match (version, tag) {
+            (1..=HEADER_VERSION, 0) => Ok(Compression::None),
+            (1..=HEADER_VERSION, 1) => Ok(Compression::Snappy),
+            _ => Err(Error::UnknownHeader([version, tag])),
+        }
+    }
+}
+
 /// Types that serialize to a bitcode + snap-compressed byte buffer and seal
 /// under a [`Key<T>`] via AES-GCM-SIV.
 ///
@@ -20,7 +56,15 @@ use std::io;
 /// let sealed = foo.encrypt_with_aad(lot.key(), b"aad")?;
 /// let foo2 = Foo::decrypt_with_aad(&sealed, lot.key(), b"aad")?;
 /// ```
+///
+/// Every buffer records the [`Stash::SCHEMA_VERSION`] it was encoded at.
+/// A type whose layout changes bumps it and overrides
+/// [`Stash::decode_version`] to upgrade buffers written at older ones.
 pub trait Stash<T>: Encode + DecodeOwned + Sized {
+    /// The layout [`Stash::encode`] writes. Buffers from before schema
+    /// versions were recorded are version 1.
+    const SCHEMA_VERSION: u16 = 1;
+
     fn encode(&self) -> Vec<u8> {
         bitcode::encode(self)
     }
@@ -29,19 +73,62 @@ pub trait Stash<T>: Encode + DecodeOwned + Sized {
         bitcode::decode(buf).map_err(Error::Decoding)
     }
 
+    /// Decode `buf`, encoded at schema `version`. Only the current
+    /// [`Stash::SCHEMA_VERSION`] is understood unless this is overridden.
+    fn decode_version(version: u16, buf: &[u8]) -> Result<Self, Error> {
+        if version == Self::SCHEMA_VERSION {
+            Self::decode(buf)
+        } else {
+            Err(Error::UnknownSchema(version))
+        }
+    }
+
+    /// Which [`Compression`] to store `encoded` with. Override to opt out
+    /// for types that don't compress, like key material.
+    fn compression(encoded: &[u8]) -> Compression {
+        if encoded.len() < COMPRESSION_THRESHOLD {
+            Compression::None
+        } else {
+            Compression::Snappy
+        }
+    }
+
     fn compress(&self) -> Result<Vec<u8>, Error> {
         let encoded = self.encode();
-        let mut compressed = Vec::new();
-        let mut encoder = snap::read::FrameEncoder::new(encoded.as_slice());
-        io::copy(&mut encoder, &mut compressed).map_err(Error::Compression)?;
-        Ok(compressed)
+        compress_with(&encoded, Self::SCHEMA_VERSION, Self::compression(&encoded))
+    }
+
+    /// Like [`Stash::compress`], ignoring [`Stash::compression`].
+    fn compress_with(&self, compression: Compression) -> Result<Vec<u8>, Error> {
+        compress_with(&self.encode(), Self::SCHEMA_VERSION, compression)
     }
 
     fn decompress(buf: &[u8]) -> Result<Self, Error> {
-        let mut decompressed = Vec::new();
-        let mut decoder = snap::read::FrameDecoder::new(buf);
-        io::copy(&mut decoder, &mut decompressed).map_err(Error::Decompression)?;
-        Self::decode(&decompressed)
+        let (compression, schema, body) = match buf {
+            [SNAPPY_STREAM_ID, ..] => (Compression::Snappy, 1, buf),
+            [1, tag, body @ ..] => (Compression::from_header(1, *tag)?, 1, body),
+            [HEADER_VERSION, tag, lo, hi, body @ ..] => (
+                Compression::from_header(HEADER_VERSION, *tag)?,
+                u16::from_le_bytes([*lo, *hi]),
+                body,
+            ),
+            [version, tag, ..] if *version != HEADER_VERSION => {
+                return Err(Error::UnknownHeader([*version, *tag]));
+            }
+            _ => {
+                let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+                return Err(Error::Decompression(eof));
+            }
+        };
+        match compression {
+            Compression::None => Self::decode_version(schema, body),
+            Compression::Snappy => {
+                let mut decompressed = Vec::new();
+                let mut decoder = snap::read::FrameDecoder::new(body);
+                io::copy(&mut decoder, &mut decompressed).map_err(Error::Decompression)?;
+                Self::decode_version(schema, &decompressed)
+            }
+        }
     }
 
     fn encrypt(&self, key: &Key<T>) -> Result<Encrypted, Error> {
@@ -60,3 +147,79 @@ pub trait Stash<T>: Encode + DecodeOwned + Sized {
         Self::decompress(&key.decrypt_with_aad(buf, aad)?)
     }
 }
+
+/// Prefix `encoded` with its header, compressing it with `compression`.
+fn compress_with(encoded: &[u8], schema: u16, compression: Compression) -> Result<Vec<u8>, Error> {
+    let mut out = vec![HEADER_VERSION, compression as u8];
+    out.extend_from_slice(&schema.to_le_bytes());
+    match compression {
+        Compression::None => out.extend_from_slice(encoded),
+        Compression::Snappy => {
+            let mut encoder = snap::read::FrameEncoder::new(encoded);
+            io::copy(&mut encoder, &mut out).map_err(Error::Compression)?;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcode::Decode;
+
+    /// A note as it was stored at schema version 1.
+    #[derive(Encode, Decode)]
+    struct NoteV1 {
+        text: String,
+    }
+
+    impl Stash<()> for NoteV1 {}
+
+    /// The same note once version 2 added `pinned`.
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Note {
+        text: String,
+        pinned: bool,
+    }
+
+    impl Stash<()> for Note {
+        const SCHEMA_VERSION: u16 = 2;
+
+        fn decode_version(version: u16, buf: &[u8]) -> Result<Self, Error> {
+            match version {
+                1 => NoteV1::decode(buf).map(|v1| Note {
+                    text: v1.text,
+                    pinned: false,
+                }),
+                2 => Self::decode(buf),
+                _ => Err(Error::UnknownSchema(version)),
+            }
+        }
+    }
+
+    #[test]
+    fn decode_older_schema() {
+        let old = NoteV1 { text: "hi".into() }
+            .compress()
+            .expect("failed to compress");
+        assert_eq!(
+            Note {
+                text: "hi".into(),
+                pinned: false,
+            },
+            Note::decompress(&old).expect("failed to decompress")
+        );
+
+        let note = Note {
+            text: "hi".into(),
+            pinned: true,
+        };
+        let new = note.compress().expect("failed to compress");
+        assert_eq!(2u16.to_le_bytes(), new[2..4]);
+        assert_eq!(note, Note::decompress(&new).expect("failed to decompress"));
+        assert!(matches!(
+            NoteV1::decompress(&new),
+            Err(Error::UnknownSchema(2))
+        ));
+    }
+}
@@ -0,0 +1,70 @@
+use crate::password::Password;
+use std::fmt;
+
+/// Scores below this are logged as weak by [`warn_if_weak`].
+pub const WEAK_SCORE: u8 = 3;
+
+/// A [`zxcvbn`] estimate of how guessable a password is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrengthReport {
+    /// From 0 (trivially guessable) to 4 (very unguessable).
+    pub score: u8,
+    /// Human readable time to crack offline against a slow hash, e.g.
+    /// "3 hours" or "centuries".
+    pub crack_time: String,
+}
+
+impl StrengthReport {
+    pub fn is_weak(&self) -> bool {
+        self.score < WEAK_SCORE
+    }
+}
+
+impl fmt::Display for StrengthReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/4 ({} to crack)", self.score, self.crack_time)
+    }
+}
+
+/// Estimate the strength of `password`.
+///
+/// The crack time assumes an attacker with the hashes, guessing offline
+/// at 10k/s, which is generous for our Argon2 derivation.
+pub fn estimate_strength(password: &str) -> StrengthReport {
+    let entropy = zxcvbn::zxcvbn(password, &[]);
+    StrengthReport {
+        score: entropy.score().into(),
+        crack_time: entropy
+            .crack_times()
+            .offline_slow_hashing_1e4_per_second()
+            .to_string(),
+    }
+}
+
+/// Log a warning when `password` is weak. Weak passwords are never
+/// rejected, `what` just names the password in the log line.
+pub(crate) fn warn_if_weak(password: &Password, what: &str) {
+    let report = estimate_strength(password.expose());
+    if report.is_weak() {
+        tracing::warn!("weak {what} password: {report}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weak() {
+        let report = estimate_strength("password");
+        assert!(report.score <= 1);
+        assert!(report.is_weak());
+    }
+
+    #[test]
+    fn strong() {
+        let report = estimate_strength("x7#Qm!2vLp9@Rk4&Zt8w");
+        assert_eq!(4, report.score);
+        assert!(!report.is_weak());
+    }
+}
@@ -0,0 +1,186 @@
+//! Breached password checks against [Have I Been Pwned].
+//!
+//! Uses the k-anonymity [range API]: only the first 5 hex characters of
+//! a password's SHA-1 leave the machine, every suffix sharing that prefix
+//! comes back, and the match is made locally. Requests also ask for
+//! padding, so the response size doesn't narrow down the prefix either.
+//!
+//! [Have I Been Pwned]: https://haveibeenpwned.com/Passwords
+//! [range API]: https://haveibeenpwned.com/API/v3#PwnedPasswords
+
+use crate::password::Password;
+use sha1::{Digest, Sha1};
+use std::fmt;
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+pub const API_URL: &str = "https://api.pwnedpasswords.com";
+
+/// Length of the hash prefix sent to the API.
+const PREFIX_LEN: usize = 5;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many times `password` appears in known breaches, `0` if never.
+pub async fn breach_count(password: &Password) -> Result<u64, Error> {
+    breach_count_at(API_URL, password).await
+}
+
+/// [`breach_count`] against the range API served from `base_url`.
+pub async fn breach_count_at(base_url: &str, password: &Password) -> Result<u64, Error> {
+    let hash = hex_sha1(password.as_bytes());
+    let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+    let client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(Error::Unavailable)?;
+    let body = client
+        .get(format!("{base_url}/range/{prefix}"))
+        .header("Add-Padding", "true")
+        .header("User-Agent", concat!("valet/", env!("CARGO_PKG_VERSION")))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(Error::Unavailable)?
+        .text()
+        .await
+        .map_err(Error::Unavailable)?;
+    count_in_range(&body, suffix)
+}
+
+/// Upper case hex SHA-1, as the range API expects.
+fn hex_sha1(bytes: &[u8]) -> Zeroizing<String> {
+    let digest = Sha1::digest(bytes);
+    let mut hex = Zeroizing::new(String::with_capacity(digest.len() * 2));
+    for byte in digest {
+        hex.push_str(&format!("{byte:02X}"));
+    }
+    hex
+}
+
+/// Find `suffix` in a range response of `SUFFIX:COUNT` lines. Padding
+/// entries have a count of 0, so they never match as breached.
+fn count_in_range(body: &str, suffix: &str) -> Result<u64, Error> {
+    for line in body.lines() {
+        let (candidate, count) = line.trim().split_once(':').ok_or(Error::InvalidResponse)?;
+        if candidate.eq_ignore_ascii_case(suffix) {
+            return count.trim().parse().map_err(|_| Error::InvalidResponse);
+        }
+    }
+    Ok(0)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The API couldn't be reached (e.g. offline) or returned an error
+    /// status. Nothing is known about the password.
+    Unavailable(reqwest::Error),
+    /// The API responded with something other than `SUFFIX:COUNT` lines.
+    InvalidResponse,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Unavailable(e) => write!(f, "breach check unavailable: {e}"),
+            Error::InvalidResponse => write!(f, "invalid breach check response"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// SHA-1 of `password`.
+    const HASH: &str = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8";
+
+    /// Serve one canned range response, returning the request line.
+    async fn mock(body: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.expect("failed to accept");
+            let mut buf = vec![0; 4096];
+            let n = stream.read(&mut buf).await.expect("failed to read");
+            let request = String::from_utf8_lossy(&buf[..n]).into_owned();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream
+                .write_all(response.as_bytes())
+                .await
+                .expect("failed to write");
+            request
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn hash() {
+        assert_eq!(HASH, hex_sha1(b"password").as_str());
+    }
+
+    #[tokio::test]
+    async fn breached() {
+        let (url, request) = mock(
+            "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n\
+             1E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824\r\n\
+             FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF:0\r\n",
+        )
+        .await;
+        let password = "password".try_into().unwrap();
+        assert_eq!(
+            9545824,
+            breach_count_at(&url, &password)
+                .await
+                .expect("failed to check")
+        );
+
+        // Only the 5 character prefix is ever sent.
+        let request = request.await.unwrap();
+        assert!(request.starts_with("GET /range/5BAA6 HTTP/1.1"));
+        assert!(!request.contains(&HASH[PREFIX_LEN..]));
+        assert!(!request.contains("password"));
+    }
+
+    #[tokio::test]
+    async fn not_breached() {
+        let (url, _) = mock("0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n").await;
+        let password = "password".try_into().unwrap();
+        assert_eq!(
+            0,
+            breach_count_at(&url, &password)
+                .await
+                .expect("failed to check")
+        );
+    }
+
+    #[tokio::test]
+    async fn offline() {
+        // Bind then drop, so nothing is listening on the port.
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let password = "password".try_into().unwrap();
+        assert!(matches!(
+            breach_count_at(&url, &password).await,
+            Err(Error::Unavailable(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_response() {
+        assert!(matches!(
+            count_in_range("<html>", "ABC"),
+            Err(Error::InvalidResponse)
+        ));
+    }
+}
@@ -2,11 +2,15 @@
 //!
 //! TODO
 
+#[cfg(feature = "agent")]
+pub mod agent;
 #[cfg(feature = "db")]
 pub mod db;
 pub mod encrypt;
 #[cfg(feature = "ffi")]
 pub mod ffi;
+#[cfg(feature = "hibp")]
+pub mod hibp;
 #[cfg(feature = "logging")]
 pub mod logging;
 pub mod lot;
@@ -14,8 +18,11 @@ pub mod password;
 pub mod prelude;
 pub mod protocol;
 pub mod record;
+pub mod totp;
 pub mod user;
 pub mod uuid;
+#[cfg(feature = "db")]
+pub mod vault;
 
 pub use self::protocol::message::{Request, Response};
 pub use self::protocol::{Handler, SendHandler};
@@ -25,3 +32,5 @@ pub use self::protocol::{Handler, SendHandler};
 pub use self::lot::Lot;
 pub use self::record::Record;
 pub use self::user::User;
+#[cfg(feature = "db")]
+pub use self::vault::Vault;
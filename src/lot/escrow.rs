@@ -0,0 +1,132 @@
+use super::{Error, Lot};
+use crate::{
+    encrypt::{self, Encrypted, KEY_SIZE, Key, NONCE_SIZE},
+    uuid::Uuid,
+};
+use rand_core::OsRng;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+/// Length of an X25519 public or secret key, as [`Lot::export_key`] and
+/// [`Lot::import_key`] take them.
+pub const RECOVERY_KEY_SIZE: usize = 32;
+
+/// Tags the key an escrowed lot key is sealed under.
+struct Escrow;
+
+impl Lot {
+    /// Seal this lot's key to `recovery_pubkey`, the X25519 public key of
+    /// whoever recovers lots when a password is lost, for them to keep
+    /// out-of-band. Only the matching secret key opens the blob, with
+    /// [`Lot::import_key`].
+    ///
+    /// The blob is `ephemeral public key || nonce || ciphertext`. A fresh
+    /// ephemeral key is used every time, so no two blobs are alike.
+    pub fn export_key(&self, recovery_pubkey: &[u8]) -> Result<Vec<u8>, Error> {
+        let recovery = PublicKey::from(recovery_key(recovery_pubkey)?);
+        let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let shared = ephemeral.diffie_hellman(&recovery);
+        if !shared.was_contributory() {
+            return Err(Error::InvalidRecoveryKey);
+        }
+        let sealed = wrapping_key(shared.as_bytes()).encrypt_with_aad(
+            &self.key.expose_bytes(),
+            &escrow_aad(&self.uuid, &ephemeral_public),
+        )?;
+
+        let mut out = Vec::with_capacity(RECOVERY_KEY_SIZE + NONCE_SIZE + sealed.data.len());
+        out.extend_from_slice(ephemeral_public.as_bytes());
+        out.extend_from_slice(&sealed.nonce);
+        out.extend_from_slice(&sealed.data);
+        Ok(out)
+    }
+
+    /// Open a [`Lot::export_key`] `blob` for the lot `uuid` with the
+    /// recovery secret key. A blob sealed for another lot or to another
+    /// recovery key fails with [`encrypt::Error::Decryption`].
+    pub fn import_key(
+        uuid: &Uuid<Lot>,
+        blob: &[u8],
+        recovery_secret: &[u8],
+    ) -> Result<Key<Lot>, Error> {
+        let recovery = StaticSecret::from(recovery_key(recovery_secret)?);
+        let (ephemeral_public, sealed) = blob
+            .split_first_chunk::<RECOVERY_KEY_SIZE>()
+            .filter(|(_, sealed)| sealed.len() >= NONCE_SIZE)
+            .ok_or(encrypt::Error::CorruptCiphertext)?;
+        let ephemeral_public = PublicKey::from(*ephemeral_public);
+        let (nonce, data) = sealed.split_at(NONCE_SIZE);
+        let sealed = Encrypted {
+            data: data.to_vec(),
+            nonce: nonce.to_vec(),
+        };
+        let shared = recovery.diffie_hellman(&ephemeral_public);
+        let key = wrapping_key(shared.as_bytes())
+            .decrypt_with_aad(&sealed, &escrow_aad(uuid, &ephemeral_public))?;
+        if key.len() != KEY_SIZE {
+            return Err(encrypt::Error::CorruptCiphertext.into());
+        }
+        Ok(Key::from_bytes(&key))
+    }
+}
+
+fn recovery_key(bytes: &[u8]) -> Result<[u8; RECOVERY_KEY_SIZE], Error> {
+    bytes.try_into().map_err(|_| Error::InvalidRecoveryKey)
+}
+
+/// The raw Diffie-Hellman output isn't uniformly random, so it's run
+/// through HKDF before use.
+fn wrapping_key(shared: &[u8; 32]) -> Key<Escrow> {
+    Key::<Escrow>::from_bytes(shared).derive_subkey(b"valet lot key escrow")
+}
+
+fn escrow_aad(uuid: &Uuid<Lot>, ephemeral_public: &PublicKey) -> Vec<u8> {
+    [
+        b"e".as_slice(),
+        uuid.to_uuid().as_bytes(),
+        ephemeral_public.as_bytes(),
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_import_key() {
+        let secret = StaticSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+        let lot = Lot::new("escrowed");
+
+        let blob = lot.export_key(public.as_bytes()).expect("failed to seal");
+        assert_ne!(
+            blob,
+            lot.export_key(public.as_bytes()).expect("failed to seal")
+        );
+        let key = Lot::import_key(lot.uuid(), &blob, secret.as_bytes()).expect("failed to unseal");
+        assert_eq!(lot.key().as_bytes(), key.as_bytes());
+
+        let other = StaticSecret::random_from_rng(OsRng);
+        assert!(matches!(
+            Lot::import_key(lot.uuid(), &blob, other.as_bytes()),
+            Err(Error::Encrypt(encrypt::Error::Decryption(_)))
+        ));
+        assert!(matches!(
+            Lot::import_key(Lot::new("other").uuid(), &blob, secret.as_bytes()),
+            Err(Error::Encrypt(encrypt::Error::Decryption(_)))
+        ));
+        assert!(matches!(
+            Lot::import_key(lot.uuid(), &blob[..40], secret.as_bytes()),
+            Err(Error::Encrypt(encrypt::Error::CorruptCiphertext))
+        ));
+        assert!(matches!(
+            lot.export_key(&public.as_bytes()[..31]),
+            Err(Error::InvalidRecoveryKey)
+        ));
+        assert!(matches!(
+            lot.export_key(&[0; RECOVERY_KEY_SIZE]),
+            Err(Error::InvalidRecoveryKey)
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+use super::{Error, Lot, PATH_SEPARATOR};
+use crate::{
+    db::Database,
+    record::Record,
+    user::{LotExport, RecordExport, User},
+};
+
+impl Lot {
+    /// This lot's records, decrypted into a [`LotExport`] JSON document.
+    ///
+    /// Unlike [`User::export_all`] this covers just the one lot, so it can
+    /// be moved to another vault with [`Lot::import_json`]. Every
+    /// password in it is in the clear.
+    pub async fn export_json(&self, db: &Database) -> Result<serde_json::Value, Error> {
+        let export = LotExport {
+            name: self.name().to_owned(),
+            parent: None,
+            records: self.export_records(db).await?,
+        };
+        Ok(serde_json::to_value(export)?)
+    }
+
+    /// Create a new lot for `user` holding the records of a
+    /// [`Lot::export_json`] document, re-encrypted under a fresh key, all
+    /// in one transaction.
+    ///
+    /// The lot is named `name`, or the last segment of the document's
+    /// name without one, and nested under the document's parent path
+    /// when it has one, which must be a lot `user` already has. Fails
+    /// with [`Error::NameTaken`] if that parent already has a lot by the
+    /// name.
+    pub async fn import_json(
+        db: &Database,
+        user: &User,
+        name: Option<&str>,
+        value: serde_json::Value,
+    ) -> Result<Lot, Error> {
+        let export: LotExport = serde_json::from_value(value)?;
+        let name = name.unwrap_or_else(|| {
+            let path = export.name.as_str();
+            path.rsplit(PATH_SEPARATOR).next().unwrap_or(path)
+        });
+        let mut lot = match &export.parent {
+            Some(path) => {
+                let parent = Lot::load(db, path, user)
+                    .await?
+                    .ok_or_else(|| Error::NotFound(path.clone()))?;
+                Lot::new_child(name, &parent)
+            }
+            None => Lot::new(name),
+        };
+        let records = export.to_records(&lot)?;
+        lot.save_with_records(db, user, &records).await?;
+        Ok(lot)
+    }
+
+    /// Decrypt every record in this lot, in index order.
+    pub(crate) async fn export_records(&self, db: &Database) -> Result<Vec<RecordExport>, Error> {
+        let mut records = Vec::with_capacity(self.index().len());
+        for (_, uuid) in self.index().iter() {
+            if let Some(record) = Record::show(db, self, uuid).await? {
+                records.push(RecordExport::from_record(&record));
+            }
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::{Data, Label};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn json_roundtrip() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let alice = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("work");
+        lot.save(&db, &alice).await.expect("failed to save lot");
+        Record::new(
+            &lot,
+            "alice@example.com"
+                .parse::<Label>()
+                .unwrap()
+                .add_extra("url", "https://example.com")
+                .unwrap()
+                .add_extra("env", "prod")
+                .unwrap(),
+            Data::new("secret".try_into().unwrap()).add_extra("notes".into(), "hi".into()),
+        )
+        .save(&db, &mut lot)
+        .await
+        .expect("failed to save record");
+
+        let json = lot.export_json(&db).await.expect("failed to export");
+        assert_eq!("work", json["name"]);
+
+        let bob = User::new("bob", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let imported = Lot::import_json(&db, &bob, Some("shared"), json.clone())
+            .await
+            .expect("failed to import");
+        assert_ne!(lot.uuid(), imported.uuid());
+
+        let loaded = Lot::load(&db, "shared", &bob)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        let reexported = loaded.export_json(&db).await.expect("failed to export");
+        assert_eq!(json["records"], reexported["records"]);
+        assert_eq!("prod", reexported["records"][0]["label_extra"]["env"]);
+
+        assert!(matches!(
+            Lot::import_json(&db, &bob, Some("shared"), json.clone()).await,
+            Err(Error::NameTaken(_))
+        ));
+        assert!(matches!(
+            Lot::import_json(&db, &bob, Some("bad"), serde_json::json!({"name": 1})).await,
+            Err(Error::Json(_))
+        ));
+
+        // Without a name the document's is used, under its parent.
+        let work = Lot::import_json(&db, &bob, None, json.clone())
+            .await
+            .expect("failed to import");
+        assert_eq!("work", work.name());
+        let mut nested = json.clone();
+        nested["parent"] = "work".into();
+        Lot::import_json(&db, &bob, None, nested.clone())
+            .await
+            .expect("failed to import nested");
+        let loaded = Lot::load(&db, "work::work", &bob)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        assert_eq!(
+            json["records"],
+            loaded.export_json(&db).await.expect("failed to export")["records"]
+        );
+        assert!(matches!(
+            Lot::import_json(&db, &bob, None, nested).await,
+            Err(Error::NameTaken(_))
+        ));
+        let mut orphan = json.clone();
+        orphan["parent"] = "nowhere".into();
+        assert!(matches!(
+            Lot::import_json(&db, &bob, None, orphan).await,
+            Err(Error::NotFound(_))
+        ));
+    }
+}
@@ -2,8 +2,8 @@
 use crate::encrypt::Encrypted;
 #[cfg(feature = "db")]
 use crate::{
-    db::{self, Database},
-    record::{self, Record, RecordIndex},
+    db::{self, Database, VaultEvent, from_unix_seconds, unix_seconds},
+    record::{self, Data, Label, LabelName, Record, RecordIndex, UrlMatch, label},
     user::User,
 };
 use crate::{
@@ -11,20 +11,29 @@ use crate::{
     uuid::Uuid,
 };
 #[cfg(feature = "db")]
+use futures::{Stream, TryStreamExt, stream};
+#[cfg(feature = "db")]
 use sea_orm::{
     ActiveValue::{Set, Unchanged},
-    IntoActiveModel,
+    IntoActiveModel, QuerySelect,
     entity::prelude::*,
+    sea_query::Expr,
 };
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::sync::Arc;
 #[cfg(feature = "db")]
+use std::time::SystemTime;
+#[cfg(feature = "db")]
 use storgit::SubmoduleLayout;
 #[cfg(feature = "db")]
 use storgit::layout::submodule::{ModuleFetcher, Modules, Parts};
 
 pub const DEFAULT_LOT: &str = "main";
 
+/// Separates the segments of a nested lot path, e.g. `work::aws`.
+pub const PATH_SEPARATOR: &str = "::";
+
 /// An encrypted collection of secrets.
 ///
 /// Each lot has its own _lot key_, i.e. [`Key<Lot>`] which is used to encrypt
@@ -45,9 +54,16 @@ pub const DEFAULT_LOT: &str = "main";
 /// |--------|-----------------------------------|-----------------------------------|
 /// | `Ka`   | `= Decrypt_A(tvuZQ1XS, 6jLC3aP9)` | `= Decrypt_B(dWPiZfO9, oQ/2Y845)` |
 /// | `Kb`   | `= Decrypt_A(LyZJM8GA, SCW2EWjc)` | N/A                               |
+///
+/// Lots may nest: a lot created with [`Lot::new_child`] records its
+/// parent's uuid, and [`Lot::load`] resolves [`PATH_SEPARATOR`]
+/// delimited paths like `work::aws` by walking that chain. Nesting is
+/// purely organisational, each lot still has its own key and a user
+/// needs a `user_lots` grant for every lot they open.
 pub struct Lot {
     uuid: Uuid<Self>,
     name: String,
+    parent: Option<Uuid<Self>>,
     /// Shared so the fetcher closure installed on [`Lot::store`] can
     /// hold an [`Arc`] clone of the same live key - no byte copy of
     /// the secret, and one authoritative zeroize on final drop.
@@ -73,6 +89,78 @@ pub struct Lot {
     /// hold `&mut Lot` for the mutation.
     #[cfg(feature = "db")]
     index: RecordIndex,
+    /// What the user this lot was loaded for may do with it. Lots made
+    /// with [`Lot::new`] are writable by their creator.
+    #[cfg(feature = "db")]
+    permission: Permission,
+    /// When the `lots` row was first and last written by [`Lot::save`],
+    /// `None` until then.
+    #[cfg(feature = "db")]
+    created_at: Option<SystemTime>,
+    #[cfg(feature = "db")]
+    updated_at: Option<SystemTime>,
+}
+
+/// What a user may do with a lot they were granted, stored on their
+/// `user_lots` row and passed to [`Lot::share`].
+///
+/// This is enforced by valet, not by the encryption: a read grant still
+/// unwraps the lot key. It keeps well behaved clients from editing lots
+/// their user was only meant to see.
+#[cfg(feature = "db")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Permission {
+    /// Load and read records only. Saving the lot, or saving or deleting
+    /// its records, fails with [`Error::ReadOnly`].
+    Read,
+    #[default]
+    Write,
+}
+
+#[cfg(feature = "db")]
+impl Permission {
+    fn as_str(self) -> &'static str {
+        match self {
+            Permission::Read => "read",
+            Permission::Write => "write",
+        }
+    }
+
+    /// Anything unrecognised is read-only.
+    fn from_column(s: &str) -> Self {
+        match s {
+            "write" => Permission::Write,
+            _ => Permission::Read,
+        }
+    }
+}
+
+/// How [`Lot::records_sorted`] orders a lot's records.
+#[cfg(feature = "db")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Alphabetically by label name, as displayed.
+    #[default]
+    Label,
+    /// Oldest first, by the (v7) record UUID.
+    Created,
+    /// Least recently saved first, by the time of the record's latest
+    /// storgit commit.
+    Updated,
+}
+
+/// Lightweight listing entry for a lot, see [`User::lots_summary`].
+///
+/// Built from the `user_lots` row and a count of `records` rows, so
+/// neither the lot key nor any record ciphertext is opened to produce
+/// one.
+///
+/// [`User::lots_summary`]: crate::user::User::lots_summary
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LotSummary {
+    pub uuid: Uuid<Lot>,
+    pub name: String,
+    pub record_count: u64,
 }
 
 impl PartialEq for Lot {
@@ -80,7 +168,10 @@ impl PartialEq for Lot {
         // Store identity is uuid + name + key. The live storgit handle
         // carries session-scoped state (scratch dir, dirty tracking)
         // that is not part of the lot's persisted identity.
-        self.uuid == other.uuid && self.name == other.name && self.key == other.key
+        self.uuid == other.uuid
+            && self.name == other.name
+            && self.parent == other.parent
+            && self.key == other.key
     }
 }
 impl Eq for Lot {}
@@ -102,6 +193,7 @@ impl Lot {
         Lot {
             uuid: Uuid::now(),
             name: name.into(),
+            parent: None,
             key: Arc::new(Key::generate()),
             #[cfg(feature = "db")]
             store,
@@ -109,13 +201,42 @@ impl Lot {
             _scratch: scratch,
             #[cfg(feature = "db")]
             index: RecordIndex::default(),
+            #[cfg(feature = "db")]
+            permission: Permission::Write,
+            #[cfg(feature = "db")]
+            created_at: None,
+            #[cfg(feature = "db")]
+            updated_at: None,
         }
     }
 
+    /// A new, empty lot under a known `key` rather than a fresh one.
+    /// [`Lot::save`] wraps whatever key the lot has for its user.
+    ///
+    /// To get back a lot that's already in the database, use
+    /// [`Lot::restore`], since saving this one would start it over empty.
+    pub fn with_key(name: &str, key: Key<Lot>) -> Self {
+        let mut lot = Lot::new(name);
+        lot.key = Arc::new(key);
+        lot
+    }
+
+    /// Create a new lot nested under `parent`.
+    pub fn new_child(name: &str, parent: &Lot) -> Self {
+        let mut lot = Lot::new(name);
+        lot.parent = Some(parent.uuid.clone());
+        lot
+    }
+
     pub fn uuid(&self) -> &Uuid<Self> {
         &self.uuid
     }
 
+    /// The uuid of the lot this one is nested under, if any.
+    pub fn parent(&self) -> Option<&Uuid<Self>> {
+        self.parent.as_ref()
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -124,6 +245,52 @@ impl Lot {
         &self.key
     }
 
+    /// What the user this lot was loaded for may do with it.
+    #[cfg(feature = "db")]
+    pub fn permission(&self) -> Permission {
+        self.permission
+    }
+
+    #[cfg(feature = "db")]
+    pub fn is_read_only(&self) -> bool {
+        self.permission == Permission::Read
+    }
+
+    /// Whether record names in this lot match ignoring case, so `Email`
+    /// and `email` are the same record. Off unless turned on with
+    /// [`Lot::set_case_insensitive_labels`].
+    #[cfg(feature = "db")]
+    pub fn case_insensitive_labels(&self) -> bool {
+        self.index.is_case_insensitive()
+    }
+
+    /// Match record names in this lot ignoring case, by Unicode case
+    /// folding, or exactly again. Stored with the lot by the next
+    /// [`Lot::save`].
+    ///
+    /// Turning it on fails with [`record::Error::LabelCollision`] if two
+    /// records' names already differ only by case.
+    #[cfg(feature = "db")]
+    pub fn set_case_insensitive_labels(&mut self, case_insensitive: bool) -> Result<(), Error> {
+        self.index
+            .set_case_insensitive(case_insensitive)
+            .map_err(Error::Record)
+    }
+
+    /// When this lot was first saved, to the second. `None` for a lot
+    /// that hasn't been, or was saved before lots were timestamped.
+    #[cfg(feature = "db")]
+    pub fn created_at(&self) -> Option<SystemTime> {
+        self.created_at
+    }
+
+    /// When this lot was last saved with [`Lot::save`], to the second.
+    /// Saving its records doesn't count.
+    #[cfg(feature = "db")]
+    pub fn updated_at(&self) -> Option<SystemTime> {
+        self.updated_at
+    }
+
     /// AAD for the `user_lots.data` ciphertext (the lot key wrapped under
     /// the user key). Username is part of the AAD because `user_lots` is
     /// per-user: each grant is scoped to a specific owner.
@@ -197,10 +364,11 @@ impl Lot {
                 return Ok(None);
             };
             let aad = Record::module_aad(&record_uuid, &lot_uuid);
-            let bytes = lot_key
-                .decrypt_with_aad(&Encrypted::unpack(&model.module), &aad)
+            let mut bytes = Encrypted::unpack_owned(model.module)
+                .and_then(|encrypted| lot_key.decrypt_with_aad(&encrypted, &aad))
                 .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync + 'static>)?;
-            Ok(Some(bytes))
+            // storgit takes ownership of the module bytes.
+            Ok(Some(std::mem::take(&mut *bytes)))
         })
     }
 
@@ -216,6 +384,80 @@ impl Lot {
         &self.index
     }
 
+    /// True if a record in this lot is named `label`, ignoring extras.
+    /// A `label` that doesn't parse as a [`LabelName`] names nothing.
+    #[cfg(feature = "db")]
+    pub fn contains(&self, label: &str) -> bool {
+        label
+            .parse::<LabelName>()
+            .is_ok_and(|name| self.index.find_by_name(&name).is_some())
+    }
+
+    /// The name of every record in this lot, in [`RecordIndex::labels`]
+    /// order, e.g. for completing labels at a prompt. Names aren't stored
+    /// as strings, so borrow [`LabelName`]s to print.
+    #[cfg(feature = "db")]
+    pub fn labels(&self) -> impl Iterator<Item = &LabelName> {
+        self.index.labels().map(Label::name)
+    }
+
+    /// Every `(label, uuid)` pair in this lot whose label is for the site
+    /// at `url`, see [`Label::matches_url`]. Only labels are looked at, so
+    /// nothing is decrypted.
+    #[cfg(feature = "db")]
+    pub fn records_for_url<'a>(
+        &'a self,
+        url: &'a str,
+        policy: UrlMatch,
+    ) -> impl Iterator<Item = (&'a Label, &'a Uuid<Record>)> {
+        self.index
+            .iter()
+            .filter(move |(label, _)| label.matches_url(url, policy))
+    }
+
+    /// Every `(label, uuid)` pair in this lot ordered `by`, ties broken by
+    /// UUID. The index itself, and any [`Lot::reorder`] positions, are
+    /// left as they are.
+    ///
+    /// [`SortKey::Updated`] reads each record's latest commit from the
+    /// store, the others only look at the index.
+    #[cfg(feature = "db")]
+    pub fn records_sorted(&self, by: SortKey) -> Result<Vec<(&Label, &Uuid<Record>)>, Error> {
+        let mut entries: Vec<_> = self.index.iter().collect();
+        match by {
+            SortKey::Label => entries
+                .sort_by_cached_key(|(label, uuid)| (label.name().to_string(), uuid.to_uuid())),
+            SortKey::Created => entries.sort_by_key(|(_, uuid)| uuid.to_uuid()),
+            SortKey::Updated => {
+                let mut times = HashMap::with_capacity(entries.len());
+                for (_, uuid) in &entries {
+                    let time = self
+                        .store
+                        .get(&Record::storgit_id(uuid))
+                        .map_err(|e| Error::Record(record::Error::Storgit(e)))?
+                        .map_or(std::time::UNIX_EPOCH, |entry| entry.time);
+                    times.insert(uuid.to_uuid(), time);
+                }
+                entries.sort_by_key(|(_, uuid)| (times[&uuid.to_uuid()], uuid.to_uuid()));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Every record in this lot tagged `tag`, see [`Data::tags`].
+    ///
+    /// Tags live in the encrypted [`Data`], so every record in the lot is
+    /// decrypted to check.
+    ///
+    /// [`Data`]: crate::record::Data
+    /// [`Data::tags`]: crate::record::Data::tags
+    #[cfg(feature = "db")]
+    pub async fn records_with_tag(&self, tag: &str) -> Result<Vec<Record>, Error> {
+        let mut records = Record::load_all(self, record::default_concurrency()).await?;
+        records.retain(|r| r.data().has_tag(tag));
+        Ok(records)
+    }
+
     /// Mutable access to the index. Used by
     /// [`Record::save`](crate::record::Record::save) and
     /// [`Record::delete`](crate::record::Record::delete) to mirror a
@@ -233,54 +475,190 @@ impl Lot {
     /// per-user `user_lots` row binding `user` to this lot under the
     /// user's key. Only the lot name is mutable on an existing
     /// `user_lots` row; lot-key rotation is not supported.
+    ///
+    /// Both writes share a [`Database::transaction`]. Where the database
+    /// can't start one the save goes ahead without it, and a failure
+    /// between the two writes is reported as [`Error::NonAtomicSave`].
+    ///
+    /// Fails with [`Error::ReadOnly`] if this lot is [`Lot::is_read_only`].
     #[cfg(feature = "db")]
     pub async fn save(&mut self, db: &Database, user: &User) -> Result<Uuid<Self>, Error> {
-        let uuid = self.uuid.to_string();
-        // Persist whatever parent state the store currently has. A
-        // fresh store snapshots an empty-parent tarball (dirty on
-        // open); a loaded store with no mutations returns None and
-        // we skip the write. We upsert on conflict so a dirty parent
-        // flushed through here overwrites the existing row rather
-        // than being discarded.
-        if let Some(parent_bytes) = self
+        db.check_writable()?;
+        if self.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+        let created = self::orm::Entity::find_by_id(self.uuid.to_string())
+            .one(db.connection())
+            .await?
+            .is_none();
+        // Stored to the second, so keep only that much in memory too.
+        let now = from_unix_seconds(unix_seconds(SystemTime::now()));
+        // Taken once up front, since the transaction may be retried.
+        let store = self.snapshot_store()?;
+        let events = if created {
+            vec![VaultEvent::LotCreated {
+                lot: self.uuid.clone(),
+            }]
+        } else {
+            Vec::new()
+        };
+        if db.supports_transactions() {
+            db.transaction(async |txn| -> Result<(), Error> {
+                self.save_store(txn, now, store.as_deref()).await?;
+                self.save_binding(txn, user).await?;
+                Ok(Database::log(txn, &events).await?)
+            })
+            .await?;
+        } else {
+            tracing::warn!("transactions unavailable, saving non-atomically");
+            let wrote = self
+                .save_store(db.connection(), now, store.as_deref())
+                .await?;
+            let logged = match self.save_binding(db.connection(), user).await {
+                Ok(()) => Database::log(db.connection(), &events)
+                    .await
+                    .map_err(Error::from),
+                Err(e) => Err(e),
+            };
+            logged.map_err(|e| {
+                if wrote {
+                    Error::NonAtomicSave(Box::new(e))
+                } else {
+                    e
+                }
+            })?;
+        }
+        if created {
+            self.created_at = Some(now);
+        }
+        self.updated_at = Some(now);
+        db.notify(events);
+        Ok(self.uuid.clone())
+    }
+
+    /// [`Lot::save`] a lot that isn't in the database yet together with
+    /// `records` of it, all in one [`Database::transaction`], so a failed
+    /// record leaves no empty lot behind. Unlike [`Lot::save`] there is
+    /// no non-atomic fallback, and a sibling of the same name is
+    /// [`Error::NameTaken`].
+    #[cfg(feature = "db")]
+    pub(crate) async fn save_with_records(
+        &mut self,
+        db: &Database,
+        user: &User,
+        records: &[Record],
+    ) -> Result<Uuid<Self>, Error> {
+        db.check_writable()?;
+        if self.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+        let now = from_unix_seconds(unix_seconds(SystemTime::now()));
+        // The lot's own tarball first, then the records on top of it.
+        let store = self.snapshot_store()?;
+        let batch = Record::prepare_many(db, self, records, &mut |_| {}).await?;
+        let events = [VaultEvent::LotCreated {
+            lot: self.uuid.clone(),
+        }];
+        let lot_uuid = self.uuid.to_string();
+        let parent = self.parent.as_ref().map(|p| p.to_string());
+        db.transaction(async |txn| -> Result<(), Error> {
+            if Lot::sibling_name_taken(txn, user, &self.name, parent.as_deref()).await? {
+                return Err(Error::NameTaken(self.name.clone()));
+            }
+            self.save_store(txn, now, store.as_deref()).await?;
+            self.save_binding(txn, user).await?;
+            Database::log(txn, &events).await?;
+            if let Some(batch) = &batch {
+                Record::write_many(db, txn, &lot_uuid, batch).await?;
+            }
+            Ok(())
+        })
+        .await?;
+        self.created_at = Some(now);
+        self.updated_at = Some(now);
+        db.notify(events);
+        if let Some(batch) = batch {
+            Record::finish_many(db, self, records, batch, |_| {});
+        }
+        Ok(self.uuid.clone())
+    }
+
+    /// The encrypted parent tarball to persist, if the store has one to
+    /// flush. A fresh store snapshots an empty-parent tarball (dirty on
+    /// open); a loaded store with no mutations returns `None`.
+    #[cfg(feature = "db")]
+    fn snapshot_store(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let parent = self
             .store
             .snapshot()
             .map_err(|e| Error::Record(record::Error::Storgit(e)))?
-            .parent
-        {
-            let initial_store = self.encrypt_store(&parent_bytes)?;
-            let active = self::orm::ActiveModel {
-                uuid: Unchanged(uuid.clone()),
-                store: Set(initial_store),
-            };
-            let on_conflict = sea_orm::sea_query::OnConflict::column(self::orm::Column::Uuid)
-                .update_column(self::orm::Column::Store)
-                .to_owned();
-            self::orm::Entity::insert(active)
-                .on_conflict(on_conflict)
-                .exec(db.connection())
+            .parent;
+        Ok(parent.map(|bytes| self.encrypt_store(&bytes)).transpose()?)
+    }
+
+    /// Upsert the `lots` row with `store` from [`Lot::snapshot_store`],
+    /// returning whether it was written. Its `updated_at` is set to `now`
+    /// either way.
+    #[cfg(feature = "db")]
+    async fn save_store(
+        &self,
+        conn: &impl ConnectionTrait,
+        now: SystemTime,
+        store: Option<&[u8]>,
+    ) -> Result<bool, Error> {
+        let uuid = self.uuid.to_string();
+        let now = unix_seconds(now);
+        // We upsert on conflict so a dirty parent flushed through here
+        // overwrites the existing row rather than being discarded.
+        let Some(store) = store else {
+            self::orm::Entity::update_many()
+                .col_expr(self::orm::Column::UpdatedAt, Expr::value(now))
+                .col_expr(
+                    self::orm::Column::CaseInsensitiveLabels,
+                    Expr::value(self.index.is_case_insensitive()),
+                )
+                .filter(self::orm::Column::Uuid.eq(uuid))
+                .exec(conn)
                 .await?;
-        }
+            return Ok(false);
+        };
+        let active = self::orm::ActiveModel {
+            uuid: Unchanged(uuid),
+            store: Set(store.to_vec()),
+            parent_uuid: Set(self.parent.as_ref().map(|p| p.to_string())),
+            created_at: Set(Some(now)),
+            updated_at: Set(Some(now)),
+            case_insensitive_labels: Set(self.index.is_case_insensitive()),
+        };
+        let on_conflict = sea_orm::sea_query::OnConflict::column(self::orm::Column::Uuid)
+            .update_columns([
+                self::orm::Column::Store,
+                self::orm::Column::UpdatedAt,
+                self::orm::Column::CaseInsensitiveLabels,
+            ])
+            .to_owned();
+        self::orm::Entity::insert(active)
+            .on_conflict(on_conflict)
+            .exec(conn)
+            .await?;
+        Ok(true)
+    }
 
+    /// Write or rename the `user_lots` row binding `user` to this lot.
+    #[cfg(feature = "db")]
+    async fn save_binding(&self, conn: &impl ConnectionTrait, user: &User) -> Result<(), Error> {
+        let uuid = self.uuid.to_string();
         // Load existing user_lot once to detect changes.
         let existing_ul =
-            self::orm::user_lots::Entity::find_by_id((user.username().to_owned(), uuid.to_owned()))
-                .one(db.connection())
+            self::orm::user_lots::Entity::find_by_id((user.username().to_owned(), uuid))
+                .one(conn)
                 .await?;
 
-        let aad = Lot::user_lot_aad(user.username(), &self.uuid);
         match existing_ul {
             None => {
-                let encrypted = user.key().encrypt_with_aad(self.key.as_bytes(), &aad)?;
-                let active = self::orm::user_lots::ActiveModel {
-                    username: Set(user.username().into()),
-                    lot_uuid: Set(uuid),
-                    name: Set(self.name.clone()),
-                    data: Set(encrypted.data),
-                    nonce: Set(encrypted.nonce),
-                };
+                let active = Lot::grant(&self.key, &self.uuid, &self.name, user, self.permission)?;
                 self::orm::user_lots::Entity::insert(active)
-                    .exec(db.connection())
+                    .exec(conn)
                     .await?;
             }
             Some(existing) => {
@@ -296,142 +674,729 @@ impl Lot {
                 if existing.name != self.name {
                     let mut active = existing.into_active_model();
                     active.name = Set(self.name.clone());
-                    active.update(db.connection()).await?;
+                    active.update(conn).await?;
                 }
             }
         }
+        Ok(())
+    }
 
-        Ok(self.uuid.clone())
+    /// Give `grantee` access to this lot under the same name, by wrapping
+    /// the lot key under their user key. A read-only member can only
+    /// share the lot read-only. Fails with [`Error::NameTaken`] if the
+    /// grantee already has a lot by this name beside it.
+    #[cfg(feature = "db")]
+    pub async fn share(
+        &self,
+        db: &Database,
+        grantee: &User,
+        permission: Permission,
+    ) -> Result<(), Error> {
+        db.check_writable()?;
+        if permission == Permission::Write && self.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+        let active = Lot::grant(&self.key, &self.uuid, &self.name, grantee, permission)?;
+        let parent = self.parent.as_ref().map(|p| p.to_string());
+        let events = [VaultEvent::LotGranted {
+            lot: self.uuid.clone(),
+        }];
+        db.transaction(async |txn| -> Result<(), Error> {
+            if Lot::sibling_name_taken(txn, grantee, &self.name, parent.as_deref()).await? {
+                return Err(Error::NameTaken(self.name.clone()));
+            }
+            self::orm::user_lots::Entity::insert(active.clone())
+                .exec(txn)
+                .await?;
+            Ok(Database::log(txn, &events).await?)
+        })
+        .await?;
+        db.notify(events);
+        Ok(())
     }
 
-    /// Load a user's lot by name.
+    /// Re-wrap `from_user`'s grant for this lot under `to_key`, e.g. a key
+    /// derived on a second device with a different salt.
+    ///
+    /// The `user_lots` row is replaced in place, keeping its name and
+    /// permission, so afterwards only `to_key` unwraps it. Unlike
+    /// [`Lot::share`] no other user is involved, and the row must already
+    /// unwrap under `from_user`'s key.
     #[cfg(feature = "db")]
-    pub async fn load(db: &Database, name: &str, user: &User) -> Result<Option<Self>, Error> {
-        let ul = self::orm::user_lots::Entity::find()
-            .filter(self::orm::user_lots::Column::Username.eq(user.username()))
-            .filter(self::orm::user_lots::Column::Name.eq(name))
-            .one(db.connection())
-            .await?
-            .ok_or(Error::MissingLotKey)?;
-        if let Some(model) = self::orm::Entity::find_by_id(&ul.lot_uuid)
+    pub async fn rewrap_for(
+        &self,
+        db: &Database,
+        from_user: &User,
+        to_key: &Key<User>,
+    ) -> Result<(), Error> {
+        db.check_writable()?;
+        let ul = self::orm::user_lots::Entity::find_by_id((
+            from_user.username().to_owned(),
+            self.uuid.to_string(),
+        ))
+        .one(db.connection())
+        .await?
+        .ok_or(Error::MissingLotKey)?;
+        let key = Lot::unwrap_key(from_user, &self.uuid, &ul)?;
+        let aad = Lot::user_lot_aad(from_user.username(), &self.uuid);
+        let encrypted = to_key.encrypt_with_aad(&key.expose_bytes(), &aad)?;
+        self::orm::user_lots::Entity::update(self::orm::user_lots::ActiveModel {
+            username: Unchanged(ul.username),
+            lot_uuid: Unchanged(ul.lot_uuid),
+            data: Set(encrypted.data),
+            nonce: Set(encrypted.nonce),
+            ..Default::default()
+        })
+        .exec(db.connection())
+        .await?;
+        Ok(())
+    }
+
+    /// Build the `user_lots` row that grants `grantee` the lot `uuid`
+    /// under `name`, with `key` wrapped under the grantee's user key.
+    #[cfg(feature = "db")]
+    pub(crate) fn grant(
+        key: &Key<Lot>,
+        uuid: &Uuid<Lot>,
+        name: &str,
+        grantee: &User,
+        permission: Permission,
+    ) -> Result<self::orm::user_lots::ActiveModel, Error> {
+        let aad = Lot::user_lot_aad(grantee.username(), uuid);
+        let encrypted = grantee.key().encrypt_with_aad(&key.expose_bytes(), &aad)?;
+        Ok(self::orm::user_lots::ActiveModel {
+            username: Set(grantee.username().into()),
+            lot_uuid: Set(uuid.to_string()),
+            name: Set(name.into()),
+            data: Set(encrypted.data),
+            nonce: Set(encrypted.nonce),
+            permission: Set(permission.as_str().into()),
+        })
+    }
+
+    /// The permission recorded on a `user_lots` row.
+    #[cfg(feature = "db")]
+    pub(crate) fn grant_permission(ul: &self::orm::user_lots::Model) -> Permission {
+        Permission::from_column(&ul.permission)
+    }
+
+    /// Like [`Lot::share`], but working from `owner`'s `user_lots` row for
+    /// `uuid` rather than an open lot, so no store is built. Returns the
+    /// row to insert; errors if `owner` has no grant, `grantee` already
+    /// has a lot by that name, or `owner` is read-only and asked to grant
+    /// write.
+    #[cfg(feature = "db")]
+    pub(crate) async fn grant_from(
+        db: &Database,
+        owner: &User,
+        grantee: &User,
+        uuid: &Uuid<Lot>,
+        permission: Permission,
+    ) -> Result<self::orm::user_lots::ActiveModel, Error> {
+        let ul = self::orm::user_lots::Entity::find_by_id((
+            owner.username().to_owned(),
+            uuid.to_string(),
+        ))
+        .one(db.connection())
+        .await?
+        .ok_or(Error::MissingLotKey)?;
+        if permission == Permission::Write && Lot::grant_permission(&ul) == Permission::Read {
+            return Err(Error::ReadOnly);
+        }
+        let parent = self::orm::Entity::find_by_id(uuid.to_string())
             .one(db.connection())
             .await?
-        {
-            let lot = Self::decrypt_and_build(db, user, model, ul)?;
-            Ok(Some(lot))
-        } else {
-            Ok(None)
+            .ok_or_else(|| Error::NotFound(uuid.to_string()))?
+            .parent_uuid;
+        if Lot::sibling_name_taken(db.connection(), grantee, &ul.name, parent.as_deref()).await? {
+            return Err(Error::NameTaken(ul.name));
         }
+        let key = Lot::unwrap_key(owner, uuid, &ul)?;
+        Lot::grant(&key, uuid, &ul.name, grantee, permission)
     }
 
-    /// Load a user's lots.
+    /// Whether `user` already has a lot `name` under `parent`, the same
+    /// rule the `user_lots` triggers enforce.
     #[cfg(feature = "db")]
-    pub async fn load_all(db: &Database, user: &User) -> Result<Vec<Self>, Error> {
+    async fn sibling_name_taken(
+        conn: &impl ConnectionTrait,
+        user: &User,
+        name: &str,
+        parent: Option<&str>,
+    ) -> Result<bool, Error> {
         let uls = self::orm::user_lots::Entity::find()
             .filter(self::orm::user_lots::Column::Username.eq(user.username()))
-            .all(db.connection())
+            .filter(self::orm::user_lots::Column::Name.eq(name))
+            .all(conn)
             .await?;
-        let mut lots = Vec::new();
         for ul in uls {
-            if let Some(model) = self::orm::Entity::find_by_id(&ul.lot_uuid)
-                .one(db.connection())
-                .await?
-            {
-                let lot = Self::decrypt_and_build(db, user, model, ul)?;
-                lots.push(lot);
+            let model = self::orm::Entity::find_by_id(ul.lot_uuid).one(conn).await?;
+            if model.is_some_and(|m| m.parent_uuid.as_deref() == parent) {
+                return Ok(true);
             }
         }
-        Ok(lots)
+        Ok(false)
     }
 
-    /// Delete this lot, cascading to records and user_lots.
+    /// Unwrap the lot key held in `user`'s `user_lots` row for `uuid`.
+    #[cfg(feature = "db")]
+    pub(crate) fn unwrap_key(
+        user: &User,
+        uuid: &Uuid<Lot>,
+        ul: &self::orm::user_lots::Model,
+    ) -> Result<Key<Lot>, Error> {
+        let encrypted = Encrypted::from_parts(ul.data.clone(), ul.nonce.clone())?;
+        let aad = Lot::user_lot_aad(user.username(), uuid);
+        let key_bytes = user.key().decrypt_with_aad(&encrypted, &aad)?;
+        Ok(Key::from_bytes(&key_bytes))
+    }
+
+    /// Load a user's lot by name, or by a [`PATH_SEPARATOR`] delimited
+    /// path of names such as `work::aws`.
     ///
-    /// Consumes the handle so callers can't accidentally read its
-    /// stale cached index after the row is gone.
+    /// The first segment must name one of the user's top level lots,
+    /// one with no parent or a parent they have no grant for, and each
+    /// segment after it a lot whose parent is the lot named by the
+    /// previous segment; `Ok(None)` if the chain doesn't line up. Every
+    /// segment needs a `user_lots` grant; [`Error::NotFound`] names the
+    /// first one that has none.
     #[cfg(feature = "db")]
-    pub async fn delete(self, db: &Database) -> Result<(), Error> {
-        self::orm::Entity::delete_by_id(self.uuid.to_string())
-            .exec(db.connection())
-            .await?;
-        Ok(())
+    pub async fn load(db: &Database, name: &str, user: &User) -> Result<Option<Self>, Error> {
+        let mut segments = name.split(PATH_SEPARATOR);
+        let first = segments.next().unwrap_or(name);
+        let Some((mut model, mut ul)) = Self::find_model(db, first, None, user).await? else {
+            return Ok(None);
+        };
+        for segment in segments {
+            let Some((child, child_ul)) =
+                Self::find_model(db, segment, Some(model.uuid.as_str()), user).await?
+            else {
+                return Ok(None);
+            };
+            model = child;
+            ul = child_ul;
+        }
+        let mut lot = Self::decrypt_and_build(db, user, model, ul)?;
+        lot.load_order(db).await?;
+        Ok(Some(lot))
     }
 
+    /// Take back the existing lot `uuid` with its `key`, e.g. one from
+    /// [`Lot::import_key`] after its owner lost their password, granting
+    /// `user` write access to it as `name`.
+    ///
+    /// The key is checked by opening the stored lot before anything is
+    /// written, so a wrong one fails with [`encrypt::Error::Decryption`].
+    /// Fails with [`Error::NameTaken`] if `user` already has a lot `name`
+    /// beside it, checked in the same transaction as the grant.
     #[cfg(feature = "db")]
-    fn decrypt_and_build(
+    pub async fn restore(
         db: &Database,
         user: &User,
-        model: self::orm::Model,
-        ul: self::orm::user_lots::Model,
+        uuid: &Uuid<Lot>,
+        name: &str,
+        key: Key<Lot>,
     ) -> Result<Lot, Error> {
-        let uuid = Uuid::<Lot>::parse(&model.uuid)?;
-        let encrypted = Encrypted {
-            data: ul.data,
-            nonce: ul.nonce,
-        };
-        let aad = Lot::user_lot_aad(user.username(), &uuid);
-        let key_bytes = user.key().decrypt_with_aad(&encrypted, &aad)?;
-        let key = Arc::new(Key::<Lot>::from_bytes(&key_bytes));
-
-        // Decrypt the parent tarball under the (just-derived) lot key.
-        let store_aad = Lot::store_aad(&uuid);
-        let parent_bytes = key.decrypt_with_aad(&Encrypted::unpack(&model.store), &store_aad)?;
-
-        let fetcher = Lot::make_fetcher(db.clone(), key.clone(), uuid.clone());
-        let scratch = tempfile::Builder::new()
-            .prefix("valet-lot-")
-            .tempdir()
-            .map_err(|e| Error::Record(record::Error::Storgit(storgit::Error::Io(e))))?;
-        let store = storgit::Store::<SubmoduleLayout>::new(scratch.path().join("repo"))
-            .and_then(|s| {
-                s.with_parts(Parts {
-                    parent: parent_bytes,
-                    modules: Modules::new(),
-                })
+        db.check_writable()?;
+        let model = db
+            .retry(async || {
+                self::orm::Entity::find_by_id(uuid.to_string())
+                    .one(db.connection())
+                    .await
             })
-            .map(|s| s.with_fetcher(fetcher))
-            .map_err(|e| Error::Record(record::Error::Storgit(e)))?;
-        let index = RecordIndex::from_store(&store).map_err(Error::Record)?;
-
-        Ok(Lot {
-            uuid,
-            name: ul.name,
-            key,
-            store,
-            _scratch: scratch,
-            index,
+            .await?
+            .ok_or_else(|| Error::NotFound(uuid.to_string()))?;
+        let parent = model.parent_uuid.clone();
+        let mut lot = Self::build(db, model, name.to_owned(), key, Permission::Write)?;
+        let events = [VaultEvent::LotGranted { lot: uuid.clone() }];
+        db.transaction(async |txn| -> Result<(), Error> {
+            if Lot::sibling_name_taken(txn, user, name, parent.as_deref()).await? {
+                return Err(Error::NameTaken(name.to_owned()));
+            }
+            lot.save_binding(txn, user).await?;
+            Ok(Database::log(txn, &events).await?)
         })
+        .await?;
+        db.notify(events);
+        lot.load_order(db).await?;
+        Ok(lot)
     }
 
-    /// Encrypt the storgit parent tarball under this lot's key with the
-    /// lot-scoped store AAD. Packed for the `lots.store` column.
+    /// Look up the `lots` and `user_lots` rows for a single lot name
+    /// without decrypting anything.
+    ///
+    /// Names are per user, so the lookup goes through `user`'s own
+    /// `user_lots` rows: another user's lot of the same name is never a
+    /// candidate. Names are only unique among siblings, so the lot must
+    /// also be a child of `parent`, or with `None` one of `user`'s top
+    /// level lots as in [`LotTree::build`].
     #[cfg(feature = "db")]
-    pub(crate) fn encrypt_store(&self, parent_bytes: &[u8]) -> Result<Vec<u8>, encrypt::Error> {
-        let aad = Lot::store_aad(&self.uuid);
-        let encrypted = self.key.encrypt_with_aad(parent_bytes, &aad)?;
-        Ok(encrypted.pack())
+    async fn find_model(
+        db: &Database,
+        name: &str,
+        parent: Option<&str>,
+        user: &User,
+    ) -> Result<Option<(self::orm::Model, self::orm::user_lots::Model)>, Error> {
+        let uls = db
+            .retry(async || {
+                self::orm::user_lots::Entity::find()
+                    .filter(self::orm::user_lots::Column::Username.eq(user.username()))
+                    .filter(self::orm::user_lots::Column::Name.eq(name))
+                    .all(db.connection())
+                    .await
+            })
+            .await?;
+        if uls.is_empty() {
+            return Err(Error::NotFound(name.to_owned()));
+        }
+        // A lot under a parent the user can't see is top level for them,
+        // but one that really has no parent wins.
+        let mut orphan = None;
+        for ul in uls {
+            let Some(model) = db
+                .retry(async || {
+                    self::orm::Entity::find_by_id(&ul.lot_uuid)
+                        .one(db.connection())
+                        .await
+                })
+                .await?
+            else {
+                continue;
+            };
+            match (parent, model.parent_uuid.as_deref()) {
+                (Some(parent), Some(p)) if p == parent => return Ok(Some((model, ul))),
+                (None, None) => return Ok(Some((model, ul))),
+                (None, Some(p)) if orphan.is_none() => {
+                    let granted = db
+                        .retry(async || {
+                            self::orm::user_lots::Entity::find_by_id((
+                                user.username().to_owned(),
+                                p.to_owned(),
+                            ))
+                            .one(db.connection())
+                            .await
+                        })
+                        .await?
+                        .is_some();
+                    if !granted {
+                        orphan = Some((model, ul));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(orphan)
     }
-}
 
-impl fmt::Debug for Lot {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Lot")
-            .field("uuid", &self.uuid)
-            .field("name", &self.name)
-            .finish()
+    /// Load a user's lots.
+    #[cfg(feature = "db")]
+    pub async fn load_all(db: &Database, user: &User) -> Result<Vec<Self>, Error> {
+        Self::stream_all(db, user).try_collect().await
     }
-}
 
-#[derive(Debug)]
-pub enum Error {
-    MissingLotKey,
-    Uuid(crate::uuid::Error),
-    Encrypt(encrypt::Error),
+    /// A user's lots, each yielded as soon as it's loaded and decrypted,
+    /// so a UI can show the first before the last is ready. Stops at the
+    /// first error.
     #[cfg(feature = "db")]
-    Record(record::Error),
+    pub fn stream_all<'a>(
+        db: &'a Database,
+        user: &'a User,
+    ) -> impl Stream<Item = Result<Self, Error>> + 'a {
+        let grants = async move {
+            let uls = db
+                .retry(async || {
+                    self::orm::user_lots::Entity::find()
+                        .filter(self::orm::user_lots::Column::Username.eq(user.username()))
+                        .all(db.connection())
+                        .await
+                })
+                .await?;
+            Ok::<_, Error>(stream::iter(uls.into_iter().map(Ok::<_, Error>)))
+        };
+        stream::once(grants)
+            .try_flatten()
+            .try_filter_map(move |ul| async move {
+                let Some(model) = db
+                    .retry(async || {
+                        self::orm::Entity::find_by_id(&ul.lot_uuid)
+                            .one(db.connection())
+                            .await
+                    })
+                    .await?
+                else {
+                    return Ok(None);
+                };
+                let mut lot = Self::decrypt_and_build(db, user, model, ul)?;
+                lot.load_order(db).await?;
+                Ok::<_, Error>(Some(lot))
+            })
+    }
+
+    /// Rename the field `from_key` to `to_key` on every record in this
+    /// lot that has it, e.g. to tidy up after an import that called
+    /// usernames `login`. Both [`Data::extra`] and [`Label::extra`] are
+    /// renamed, and a value already under `to_key` is replaced. Changed
+    /// records are saved together, extending their history. Returns how
+    /// many changed.
+    ///
+    /// [`Data::extra`]: crate::record::Data::extra
+    /// [`Label::extra`]: crate::record::Label::extra
+    #[cfg(feature = "db")]
+    pub async fn rename_field(
+        &mut self,
+        db: &Database,
+        from_key: &str,
+        to_key: &str,
+    ) -> Result<usize, Error> {
+        db.check_writable()?;
+        if self.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+        if from_key == to_key {
+            return Ok(0);
+        }
+        let mut changed = Vec::new();
+        for record in Record::load_all(self, record::default_concurrency()).await? {
+            let (label, data) = (record.label(), record.data());
+            if !label.extra().contains_key(from_key) && !data.extra().contains_key(from_key) {
+                continue;
+            }
+            let mut label_extra = label.extra().clone();
+            if let Some(value) = label_extra.remove(from_key) {
+                label_extra.insert(to_key.to_owned(), value);
+            }
+            let mut data_extra = data.extra().clone();
+            if let Some(value) = data_extra.remove(from_key) {
+                data_extra.insert(to_key.to_owned(), value);
+            }
+            let label = Label::from(label.name().clone()).with_extra(label_extra)?;
+            let data = Data::new(data.password().clone())
+                .with_extra(data_extra)
+                .with_tags(data.tags());
+            changed.push(Record::with_uuid(record.uuid().clone(), self, label, data));
+        }
+        Record::save_many(db, self, &changed, |_| {}).await?;
+        Ok(changed.len())
+    }
+
+    /// Give this lot's records a custom order, by label name. Records not
+    /// named in `label_order` lose any position they had and sort after
+    /// the named ones; see [`RecordIndex::sorted`].
+    #[cfg(feature = "db")]
+    pub async fn reorder(&mut self, db: &Database, label_order: &[&str]) -> Result<(), Error> {
+        db.check_writable()?;
+        if self.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+        let mut order = HashMap::with_capacity(label_order.len());
+        for (position, name) in label_order.iter().enumerate() {
+            let uuid = name
+                .parse::<LabelName>()
+                .ok()
+                .and_then(|name| self.index.find_by_name(&name))
+                .ok_or_else(|| Error::NoSuchRecord((*name).to_owned()))?;
+            order.insert(uuid.clone(), position as i64);
+        }
+
+        db.transaction(async |txn| -> Result<(), Error> {
+            record::orm::Entity::update_many()
+                .col_expr(
+                    record::orm::Column::SortIndex,
+                    Expr::value(Option::<i64>::None),
+                )
+                .filter(record::orm::Column::LotUuid.eq(self.uuid.to_string()))
+                .exec(txn)
+                .await?;
+            for (uuid, position) in &order {
+                record::orm::Entity::update_many()
+                    .col_expr(record::orm::Column::SortIndex, Expr::value(*position))
+                    .filter(record::orm::Column::Uuid.eq(uuid.to_string()))
+                    .exec(txn)
+                    .await?;
+            }
+            Ok(())
+        })
+        .await?;
+
+        self.index.set_order(order);
+        Ok(())
+    }
+
+    /// Pull the custom record positions written by [`Lot::reorder`] into
+    /// the index.
+    #[cfg(feature = "db")]
+    async fn load_order(&mut self, db: &Database) -> Result<(), Error> {
+        let rows = record::orm::Entity::find()
+            .filter(record::orm::Column::LotUuid.eq(self.uuid.to_string()))
+            .filter(record::orm::Column::SortIndex.is_not_null())
+            .select_only()
+            .column(record::orm::Column::Uuid)
+            .column(record::orm::Column::SortIndex)
+            .into_tuple::<(String, i64)>()
+            .all(db.connection())
+            .await?;
+        let mut order = HashMap::with_capacity(rows.len());
+        for (uuid, position) in rows {
+            order.insert(Uuid::<Record>::parse(&uuid)?, position);
+        }
+        self.index.set_order(order);
+        Ok(())
+    }
+
+    /// Delete this lot, cascading to records and user_lots.
+    ///
+    /// Consumes the handle so callers can't accidentally read its
+    /// stale cached index after the row is gone.
+    #[cfg(feature = "db")]
+    pub async fn delete(self, db: &Database) -> Result<(), Error> {
+        db.check_writable()?;
+        if self.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+        let events = [VaultEvent::LotDeleted {
+            lot: self.uuid.clone(),
+        }];
+        db.transaction(async |txn| -> Result<(), Error> {
+            self::orm::Entity::delete_by_id(self.uuid.to_string())
+                .exec(txn)
+                .await?;
+            Ok(Database::log(txn, &events).await?)
+        })
+        .await?;
+        db.notify(events);
+        Ok(())
+    }
+
+    #[cfg(feature = "db")]
+    fn decrypt_and_build(
+        db: &Database,
+        user: &User,
+        model: self::orm::Model,
+        ul: self::orm::user_lots::Model,
+    ) -> Result<Lot, Error> {
+        let uuid = Uuid::<Lot>::parse(&model.uuid)?;
+        let key = Lot::unwrap_key(user, &uuid, &ul)?;
+        let permission = Lot::grant_permission(&ul);
+        Self::build(db, model, ul.name, key, permission)
+    }
+
+    /// Open the `lots` row `model` under its lot `key`.
+    #[cfg(feature = "db")]
+    fn build(
+        db: &Database,
+        model: self::orm::Model,
+        name: String,
+        key: Key<Lot>,
+        permission: Permission,
+    ) -> Result<Lot, Error> {
+        let uuid = Uuid::<Lot>::parse(&model.uuid)?;
+        let parent = model
+            .parent_uuid
+            .as_deref()
+            .map(Uuid::<Lot>::parse)
+            .transpose()?;
+        let key = Arc::new(key);
+
+        // Decrypt the parent tarball under the (just-derived) lot key.
+        let store_aad = Lot::store_aad(&uuid);
+        let mut parent_bytes =
+            key.decrypt_with_aad(&Encrypted::unpack_owned(model.store)?, &store_aad)?;
+
+        let fetcher = Lot::make_fetcher(db.clone(), key.clone(), uuid.clone());
+        let scratch = tempfile::Builder::new()
+            .prefix("valet-lot-")
+            .tempdir()
+            .map_err(|e| Error::Record(record::Error::Storgit(storgit::Error::Io(e))))?;
+        let store = storgit::Store::<SubmoduleLayout>::new(scratch.path().join("repo"))
+            .and_then(|s| {
+                s.with_parts(Parts {
+                    parent: std::mem::take(&mut *parent_bytes),
+                    modules: Modules::new(),
+                })
+            })
+            .map(|s| s.with_fetcher(fetcher))
+            .map_err(|e| Error::Record(record::Error::Storgit(e)))?;
+        let index = RecordIndex::from_store(&store, model.case_insensitive_labels)
+            .map_err(Error::Record)?;
+
+        Ok(Lot {
+            uuid,
+            name,
+            parent,
+            key,
+            store,
+            _scratch: scratch,
+            index,
+            permission,
+            created_at: model.created_at.map(from_unix_seconds),
+            updated_at: model.updated_at.map(from_unix_seconds),
+        })
+    }
+
+    /// Encrypt the storgit parent tarball under this lot's key with the
+    /// lot-scoped store AAD. Packed for the `lots.store` column.
+    #[cfg(feature = "db")]
+    pub(crate) fn encrypt_store(&self, parent_bytes: &[u8]) -> Result<Vec<u8>, encrypt::Error> {
+        let aad = Lot::store_aad(&self.uuid);
+        let encrypted = self.key.encrypt_with_aad(parent_bytes, &aad)?;
+        Ok(encrypted.pack())
+    }
+}
+
+impl fmt::Debug for Lot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lot")
+            .field("uuid", &self.uuid)
+            .field("name", &self.name)
+            .field("parent", &self.parent)
+            .finish()
+    }
+}
+
+/// The uuids, name and timestamps (as Unix seconds), for the likes of
+/// [`Database::audit_log`]. Never the key or any record.
+impl serde::Serialize for Lot {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut lot = serializer.serialize_struct("Lot", 5)?;
+        lot.serialize_field("uuid", &self.uuid)?;
+        lot.serialize_field("name", &self.name)?;
+        lot.serialize_field("parent", &self.parent)?;
+        #[cfg(feature = "db")]
+        {
+            lot.serialize_field("created_at", &self.created_at.map(unix_seconds))?;
+            lot.serialize_field("updated_at", &self.updated_at.map(unix_seconds))?;
+        }
+        lot.end()
+    }
+}
+
+/// A lot together with the lots nested under it, see [`User::lot_tree`].
+///
+/// [`User::lot_tree`]: crate::user::User::lot_tree
+#[derive(Debug, PartialEq, Eq)]
+pub struct LotTree {
+    pub lot: Lot,
+    pub children: Vec<LotTree>,
+}
+
+impl LotTree {
+    /// Arrange a flat list of lots by their parent links. A lot whose
+    /// parent isn't in `lots` (e.g. one the user has no grant for) is
+    /// treated as a root. Sibling order follows the input order.
+    pub fn build(lots: Vec<Lot>) -> Vec<LotTree> {
+        let present: HashSet<Uuid<Lot>> = lots.iter().map(|l| l.uuid.clone()).collect();
+        let mut children: HashMap<Uuid<Lot>, Vec<Lot>> = HashMap::new();
+        let mut roots = Vec::new();
+        for lot in lots {
+            match lot.parent.clone() {
+                Some(parent) if present.contains(&parent) => {
+                    children.entry(parent).or_default().push(lot)
+                }
+                _ => roots.push(lot),
+            }
+        }
+        fn attach(lot: Lot, children: &mut HashMap<Uuid<Lot>, Vec<Lot>>) -> LotTree {
+            let kids = children.remove(&lot.uuid).unwrap_or_default();
+            LotTree {
+                children: kids.into_iter().map(|k| attach(k, children)).collect(),
+                lot,
+            }
+        }
+        roots
+            .into_iter()
+            .map(|root| attach(root, &mut children))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    MissingLotKey,
+    /// The user has no lot by this name.
+    NotFound(String),
+    /// The user already has a lot by this name.
+    NameTaken(String),
+    /// [`Lot::reorder`] was given a label name with no record in the lot.
+    NoSuchRecord(String),
+    /// The user only has [`Permission::Read`] on this lot.
+    #[cfg(feature = "db")]
+    ReadOnly,
+    /// A recovery key for [`Lot::export_key`] or [`Lot::import_key`] that
+    /// isn't a usable X25519 key.
+    InvalidRecoveryKey,
+    /// [`Lot::save`] ran without a transaction and failed after writing
+    /// the `lots` row but before the `user_lots` row, so the two now
+    /// disagree. A new lot left like this can't be opened by anyone.
+    #[cfg(feature = "db")]
+    NonAtomicSave(Box<Error>),
+    Uuid(crate::uuid::Error),
+    Encrypt(encrypt::Error),
+    /// [`Lot::rename_field`] was given a `to_key` labels can't hold, or
+    /// an imported label was invalid.
+    #[cfg(feature = "db")]
+    Label(label::Error),
+    /// An imported password was longer than [`MAX_LENGTH`].
+    ///
+    /// [`MAX_LENGTH`]: crate::password::MAX_LENGTH
+    PasswordTooLong(crate::password::TooLong),
+    /// [`Lot::import_json`] was given a document that isn't a
+    /// [`LotExport`](crate::user::LotExport).
+    #[cfg(feature = "db")]
+    Json(serde_json::Error),
+    #[cfg(feature = "db")]
+    Record(record::Error),
     #[cfg(feature = "db")]
     Database(db::Error),
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingLotKey => write!(f, "missing lot key"),
+            Error::NotFound(name) => write!(f, "no lot named '{name}'"),
+            Error::NameTaken(name) => write!(f, "a lot named '{name}' already exists"),
+            Error::NoSuchRecord(name) => write!(f, "no record named '{name}' in lot"),
+            #[cfg(feature = "db")]
+            Error::ReadOnly => write!(f, "lot is read-only"),
+            Error::InvalidRecoveryKey => write!(f, "invalid recovery key"),
+            #[cfg(feature = "db")]
+            Error::NonAtomicSave(e) => write!(f, "lot only partly saved: {e}"),
+            Error::Uuid(e) => write!(f, "uuid: {e}"),
+            Error::Encrypt(e) => write!(f, "encryption: {e}"),
+            #[cfg(feature = "db")]
+            Error::Label(e) => write!(f, "label: {e}"),
+            Error::PasswordTooLong(e) => write!(f, "{e}"),
+            #[cfg(feature = "db")]
+            Error::Json(e) => write!(f, "json: {e}"),
+            #[cfg(feature = "db")]
+            Error::Record(e) => write!(f, "record: {e}"),
+            #[cfg(feature = "db")]
+            Error::Database(e) => write!(f, "database: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "db")]
+            Error::NonAtomicSave(e) => Some(e.as_ref()),
+            Error::Uuid(e) => Some(e),
+            Error::Encrypt(e) => Some(e),
+            #[cfg(feature = "db")]
+            Error::Label(e) => Some(e),
+            #[cfg(feature = "db")]
+            Error::Json(e) => Some(e),
+            #[cfg(feature = "db")]
+            Error::Record(e) => Some(e),
+            #[cfg(feature = "db")]
+            Error::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl From<crate::uuid::Error> for Error {
     fn from(err: crate::uuid::Error) -> Self {
         Error::Uuid(err)
@@ -444,6 +1409,12 @@ impl From<encrypt::Error> for Error {
     }
 }
 
+impl From<crate::password::TooLong> for Error {
+    fn from(err: crate::password::TooLong) -> Self {
+        Error::PasswordTooLong(err)
+    }
+}
+
 #[cfg(feature = "db")]
 impl From<db::Error> for Error {
     fn from(err: db::Error) -> Self {
@@ -458,6 +1429,13 @@ impl From<sea_orm::DbErr> for Error {
     }
 }
 
+#[cfg(feature = "db")]
+impl From<label::Error> for Error {
+    fn from(err: label::Error) -> Self {
+        Error::Label(err)
+    }
+}
+
 #[cfg(feature = "db")]
 impl From<record::Error> for Error {
     fn from(err: record::Error) -> Self {
@@ -465,6 +1443,18 @@ impl From<record::Error> for Error {
     }
 }
 
+#[cfg(feature = "db")]
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+mod escrow;
+#[cfg(feature = "db")]
+mod export;
+pub use self::escrow::RECOVERY_KEY_SIZE;
+
 #[cfg(all(feature = "db", feature = "orm"))]
 pub mod orm;
 #[cfg(all(feature = "db", not(feature = "orm")))]
@@ -485,6 +1475,124 @@ mod tests {
         assert_eq!(36, lot.uuid.to_string().len());
     }
 
+    #[cfg(feature = "db")]
+    #[test]
+    fn error_display() {
+        use std::error::Error as _;
+
+        let err = Error::Record(record::Error::Encryption(encrypt::Error::MalformedEncoding));
+        assert_eq!("record: encryption: malformed encoding", err.to_string());
+        let source = err.source().expect("no source");
+        assert_eq!("encryption: malformed encoding", source.to_string());
+        let root = source.source().expect("no source");
+        assert_eq!("malformed encoding", root.to_string());
+        assert!(root.source().is_none());
+    }
+
+    #[cfg(feature = "db")]
+    #[test]
+    fn contains_labels() {
+        let mut lot = Lot::new("lot");
+        assert!(!lot.contains("github"));
+        assert_eq!(0, lot.labels().count());
+
+        for label in ["github", "nix@example.com"] {
+            lot.index_mut()
+                .insert(label.parse::<Label>().unwrap(), Uuid::now());
+        }
+        assert!(lot.contains("github"));
+        assert!(lot.contains("nix@example.com"));
+        assert!(!lot.contains("example.com"));
+        assert!(!lot.contains(""));
+        let labels: Vec<String> = lot.labels().map(ToString::to_string).collect();
+        // Domain names sort first, see `LabelName: Ord`.
+        assert_eq!(vec!["nix@example.com", "github"], labels);
+    }
+
+    #[cfg(feature = "db")]
+    #[test]
+    fn records_sorted() {
+        let mut lot = Lot::new("lot");
+        // Created in this order, so UUIDs sort the same way.
+        let uuids: Vec<Uuid<Record>> = (0..3).map(|_| Uuid::now()).collect();
+        for (label, uuid) in ["nix@example.com", "github", "bank"].iter().zip(&uuids) {
+            lot.index_mut()
+                .insert(label.parse::<Label>().unwrap(), uuid.clone());
+        }
+
+        let names = |by| -> Vec<String> {
+            lot.records_sorted(by)
+                .expect("failed to sort")
+                .into_iter()
+                .map(|(label, _)| label.name().to_string())
+                .collect()
+        };
+        assert_eq!(
+            vec!["bank", "github", "nix@example.com"],
+            names(SortKey::Label)
+        );
+        assert_eq!(
+            vec!["nix@example.com", "github", "bank"],
+            names(SortKey::Created)
+        );
+        let created: Vec<_> = lot
+            .records_sorted(SortKey::Created)
+            .expect("failed to sort")
+            .into_iter()
+            .map(|(_, uuid)| uuid.clone())
+            .collect();
+        assert_eq!(uuids, created);
+        // The index keeps its own order.
+        let labels: Vec<String> = lot.labels().map(ToString::to_string).collect();
+        assert_eq!(vec!["nix@example.com", "bank", "github"], labels);
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn records_sorted_updated() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("lot");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let a = Record::new(
+            &lot,
+            "a".parse::<Label>().unwrap(),
+            Data::new("1".try_into().unwrap()),
+        );
+        a.save(&db, &mut lot).await.expect("failed to save record");
+        let b = Record::new(
+            &lot,
+            "b".parse::<Label>().unwrap(),
+            Data::new("1".try_into().unwrap()),
+        );
+        b.save(&db, &mut lot).await.expect("failed to save record");
+        // Commit times have second resolution.
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        Record::with_uuid(
+            a.uuid().clone(),
+            &lot,
+            "a".parse::<Label>().unwrap(),
+            Data::new("2".try_into().unwrap()),
+        )
+        .save(&db, &mut lot)
+        .await
+        .expect("failed to save record");
+
+        let updated: Vec<_> = lot
+            .records_sorted(SortKey::Updated)
+            .expect("failed to sort")
+            .into_iter()
+            .map(|(_, uuid)| uuid.clone())
+            .collect();
+        assert_eq!(vec![b.uuid().clone(), a.uuid().clone()], updated);
+    }
+
     #[cfg(feature = "db")]
     #[tokio::test(flavor = "multi_thread")]
     async fn create_load() {
@@ -562,6 +1670,14 @@ mod tests {
             .await
             .expect("failed to load lots");
         assert_eq!(lots, vec![lot_a, lot_b]);
+
+        let streamed: Vec<Lot> = Lot::stream_all(&db, &user)
+            .try_collect()
+            .await
+            .expect("failed to stream lots");
+        assert_eq!(lots, streamed);
+        let names: Vec<&str> = streamed.iter().map(Lot::name).collect();
+        assert_eq!(vec!["lot a", "lot b"], names);
     }
 
     #[cfg(feature = "db")]
@@ -615,6 +1731,248 @@ mod tests {
         assert!(user_lot.is_none());
     }
 
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn load_nested_path() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut work = Lot::new("work");
+        work.save(&db, &user).await.expect("failed to save lot");
+        let mut aws = Lot::new_child("aws", &work);
+        aws.save(&db, &user).await.expect("failed to save lot");
+        let mut home = Lot::new("home");
+        home.save(&db, &user).await.expect("failed to save lot");
+
+        let loaded = Lot::load(&db, "work::aws", &user)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        assert_eq!(loaded, aws);
+        assert_eq!(loaded.parent(), Some(work.uuid()));
+
+        // The chain must line up with the stored parent links.
+        assert!(
+            Lot::load(&db, "home::aws", &user)
+                .await
+                .expect("failed to load lot")
+                .is_none()
+        );
+        // Nor is a child a top level lot.
+        assert!(
+            Lot::load(&db, "aws", &user)
+                .await
+                .expect("failed to load lot")
+                .is_none()
+        );
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sibling_names() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut work = Lot::new("work");
+        work.save(&db, &user).await.expect("failed to save lot");
+        let mut home = Lot::new("home");
+        home.save(&db, &user).await.expect("failed to save lot");
+        let mut work_aws = Lot::new_child("aws", &work);
+        work_aws.save(&db, &user).await.expect("failed to save lot");
+        let mut home_aws = Lot::new_child("aws", &home);
+        home_aws.save(&db, &user).await.expect("failed to save lot");
+        let mut aws = Lot::new("aws");
+        aws.save(&db, &user).await.expect("failed to save lot");
+
+        for (path, lot) in [
+            ("work::aws", &work_aws),
+            ("home::aws", &home_aws),
+            ("aws", &aws),
+        ] {
+            let loaded = Lot::load(&db, path, &user)
+                .await
+                .expect("failed to load lot")
+                .expect("no lot");
+            assert_eq!(lot.uuid(), loaded.uuid(), "{path}");
+        }
+
+        // Siblings still can't share a name.
+        assert!(Lot::new_child("aws", &work).save(&db, &user).await.is_err());
+        assert!(Lot::new("work").save(&db, &user).await.is_err());
+    }
+
+    #[test]
+    fn tree() {
+        let work = Lot::new("work");
+        let aws = Lot::new_child("aws", &work);
+        let home = Lot::new("home");
+        let (work_uuid, aws_uuid, home_uuid) =
+            (work.uuid().clone(), aws.uuid().clone(), home.uuid().clone());
+
+        let tree = LotTree::build(vec![aws, work, home]);
+        assert_eq!(2, tree.len());
+        assert_eq!(&work_uuid, tree[0].lot.uuid());
+        assert_eq!(1, tree[0].children.len());
+        assert_eq!(&aws_uuid, tree[0].children[0].lot.uuid());
+        assert_eq!(&home_uuid, tree[1].lot.uuid());
+        assert!(tree[1].children.is_empty());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn share() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let alice = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let bob = User::new("bob", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("shared");
+        lot.save(&db, &alice).await.expect("failed to save lot");
+        lot.share(&db, &bob, Permission::Write)
+            .await
+            .expect("failed to share lot");
+
+        let loaded = Lot::load(&db, "shared", &bob)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        assert_eq!(lot, loaded);
+        let log = db
+            .audit_log(SystemTime::UNIX_EPOCH)
+            .await
+            .expect("failed to read audit log");
+        assert_eq!(
+            Some(&VaultEvent::LotGranted {
+                lot: lot.uuid().clone()
+            }),
+            log.last().map(|entry| &entry.event)
+        );
+
+        // Bob can't be given a second lot called `shared` beside his.
+        let carol = User::new("carol", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut other = Lot::new("shared");
+        other.save(&db, &carol).await.expect("failed to save lot");
+        assert!(matches!(
+            other.share(&db, &bob, Permission::Write).await,
+            Err(Error::NameTaken(_))
+        ));
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn read_only_share() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let mut users = Vec::new();
+        for name in ["alice", "bob", "carol"] {
+            users.push(
+                User::new(name, "password".try_into().unwrap())
+                    .expect("failed to make user")
+                    .register(&db)
+                    .await
+                    .expect("failed to register user"),
+            );
+        }
+        let (alice, bob, carol) = (&users[0], &users[1], &users[2]);
+        let mut lot = Lot::new("shared");
+        lot.save(&db, alice).await.expect("failed to save lot");
+        Record::new(
+            &lot,
+            "a".parse::<Label>().unwrap(),
+            Data::new("secret".try_into().unwrap()),
+        )
+        .save(&db, &mut lot)
+        .await
+        .expect("failed to save record");
+        lot.share(&db, bob, Permission::Read)
+            .await
+            .expect("failed to share lot");
+        lot.share(&db, carol, Permission::Write)
+            .await
+            .expect("failed to share lot");
+
+        // Bob can read, but not write.
+        let mut bobs = Lot::load(&db, "shared", bob)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        assert!(bobs.is_read_only());
+        let uuid = bobs.index().iter().next().expect("no record").1.clone();
+        let record = Record::show(&db, &bobs, &uuid)
+            .await
+            .expect("failed to show record")
+            .expect("no record");
+        assert_eq!("secret", record.password().expose());
+        assert!(matches!(bobs.save(&db, bob).await, Err(Error::ReadOnly)));
+        let edit = Record::with_uuid(
+            uuid.clone(),
+            &bobs,
+            "a".parse::<Label>().unwrap(),
+            Data::new("changed".try_into().unwrap()),
+        );
+        assert!(matches!(
+            edit.save(&db, &mut bobs).await,
+            Err(record::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            record.delete(&db, &mut bobs).await,
+            Err(record::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            bobs.share(&db, carol, Permission::Write).await,
+            Err(Error::ReadOnly)
+        ));
+
+        // Carol can do both.
+        let mut carols = Lot::load(&db, "shared", carol)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        assert_eq!(Permission::Write, carols.permission());
+        let edit = Record::with_uuid(
+            uuid.clone(),
+            &carols,
+            "a".parse::<Label>().unwrap(),
+            Data::new("changed".try_into().unwrap()),
+        );
+        edit.save(&db, &mut carols)
+            .await
+            .expect("failed to save record");
+        carols.save(&db, carol).await.expect("failed to save lot");
+        let bobs = Lot::load(&db, "shared", bob)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        let record = Record::show(&db, &bobs, &uuid)
+            .await
+            .expect("failed to show record")
+            .expect("no record");
+        assert_eq!("changed", record.password().expose());
+    }
+
     /// Returns the lot key for a given user/lot as decrypted from the
     /// user_lots table.
     #[cfg(feature = "db")]
@@ -639,4 +1997,445 @@ mod tests {
                 .expect("failed to decrypted lot key"),
         )
     }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn non_atomic_save() {
+        // Never registered, so binding a lot to them fails the
+        // `user_lots` foreign key after the `lots` row is written.
+        let ghost =
+            User::new("ghost", "password".try_into().unwrap()).expect("failed to make user");
+
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let mut lot = Lot::new("atomic");
+        assert!(matches!(
+            lot.save(&db, &ghost).await,
+            Err(Error::Database(_))
+        ));
+        let row = self::orm::Entity::find_by_id(lot.uuid().to_string())
+            .one(db.connection())
+            .await
+            .expect("failed to query lots");
+        assert!(row.is_none());
+
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database")
+            .without_transactions();
+        let mut lot = Lot::new("partial");
+        assert!(matches!(
+            lot.save(&db, &ghost).await,
+            Err(Error::NonAtomicSave(_))
+        ));
+        let row = self::orm::Entity::find_by_id(lot.uuid().to_string())
+            .one(db.connection())
+            .await
+            .expect("failed to query lots");
+        assert!(row.is_some());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn transaction_rollback() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let mut lot = Lot::new("half");
+        let result = db
+            .transaction(async |txn| -> Result<(), Error> {
+                let store = lot.snapshot_store().expect("failed to snapshot store");
+                let saved = lot
+                    .save_store(txn, SystemTime::now(), store.as_deref())
+                    .await;
+                assert!(saved.expect("failed to save store"));
+                Err(Error::ReadOnly)
+            })
+            .await;
+        assert!(matches!(result, Err(Error::ReadOnly)));
+        let row = self::orm::Entity::find_by_id(lot.uuid().to_string())
+            .one(db.connection())
+            .await
+            .expect("failed to query lots");
+        assert!(row.is_none());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn timestamps() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("main");
+        assert_eq!(None, lot.created_at());
+        lot.save(&db, &user).await.expect("failed to save lot");
+        assert!(lot.created_at().is_some());
+        assert_eq!(lot.created_at(), lot.updated_at());
+
+        // Backdate the row rather than wait, timestamps being whole
+        // seconds.
+        let backdated = unix_seconds(SystemTime::now()) - 60;
+        let created = from_unix_seconds(backdated);
+        self::orm::Entity::update_many()
+            .col_expr(self::orm::Column::CreatedAt, Expr::value(backdated))
+            .col_expr(self::orm::Column::UpdatedAt, Expr::value(backdated))
+            .filter(self::orm::Column::Uuid.eq(lot.uuid().to_string()))
+            .exec(db.connection())
+            .await
+            .expect("failed to backdate lot");
+        let mut lot = Lot::load(&db, "main", &user)
+            .await
+            .expect("failed to load lot")
+            .expect("missing lot");
+        assert_eq!(Some(created), lot.created_at());
+        assert_eq!(Some(created), lot.updated_at());
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let updated = lot.updated_at().expect("no updated_at");
+        assert!(updated > created);
+
+        let lot = Lot::load(&db, "main", &user)
+            .await
+            .expect("failed to load lot")
+            .expect("missing lot");
+        assert_eq!(Some(created), lot.created_at());
+        assert_eq!(Some(updated), lot.updated_at());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn case_insensitive_labels() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let name = |name: &str| name.parse::<LabelName>().unwrap();
+        let record = |lot: &Lot, label: &str| {
+            Record::new(
+                lot,
+                label.parse::<Label>().unwrap(),
+                Data::new("secret".try_into().unwrap()),
+            )
+        };
+
+        let mut exact = Lot::new("exact");
+        exact.save(&db, &user).await.expect("failed to save lot");
+        assert!(!exact.case_insensitive_labels());
+        for label in ["Email", "email"] {
+            record(&exact, label)
+                .save(&db, &mut exact)
+                .await
+                .expect("failed to save record");
+        }
+        assert_eq!(2, exact.index().len());
+        assert!(exact.index().find_by_name(&name("EMAIL")).is_none());
+        assert!(matches!(
+            exact.set_case_insensitive_labels(true),
+            Err(Error::Record(record::Error::LabelCollision { .. }))
+        ));
+        assert!(!exact.case_insensitive_labels());
+
+        let mut folded = Lot::new("folded");
+        folded
+            .set_case_insensitive_labels(true)
+            .expect("failed to set case insensitive");
+        folded.save(&db, &user).await.expect("failed to save lot");
+        let uuid = record(&folded, "Ünïcode")
+            .save(&db, &mut folded)
+            .await
+            .expect("failed to save record");
+        assert!(matches!(
+            record(&folded, "üNÏCODE").save(&db, &mut folded).await,
+            Err(record::Error::LabelCollision { .. })
+        ));
+
+        let folded = Lot::load(&db, "folded", &user)
+            .await
+            .expect("failed to load lot")
+            .expect("missing lot");
+        assert!(folded.case_insensitive_labels());
+        assert_eq!(Some(&uuid), folded.index().find_by_name(&name("ÜNÏCODE")));
+        assert!(folded.contains("ünïcode"));
+    }
+
+    #[test]
+    fn with_key() {
+        let lot = Lot::new("original");
+        let copy = Lot::with_key("copy", Key::from_bytes(lot.key().as_bytes()));
+        assert!(lot.key() == copy.key());
+        assert_ne!(lot.uuid(), copy.uuid());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn restore() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (alice, mut lot) = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        let record = Record::new(
+            &lot,
+            "github".parse::<Label>().unwrap(),
+            Data::new("secret".try_into().unwrap()),
+        );
+        record
+            .save(&db, &mut lot)
+            .await
+            .expect("failed to save record");
+        let key_bytes = lot.key().expose_bytes();
+
+        // Alice lost her password, recovery hands the lot to her new account.
+        let recovered = User::new("alice2", "new password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        assert!(matches!(
+            Lot::restore(&db, &recovered, lot.uuid(), DEFAULT_LOT, Key::generate()).await,
+            Err(Error::Encrypt(encrypt::Error::Decryption(_)))
+        ));
+        let key = Key::from_bytes(&key_bytes);
+        let restored = Lot::restore(&db, &recovered, lot.uuid(), DEFAULT_LOT, key)
+            .await
+            .expect("failed to restore lot");
+        assert_eq!(lot, restored);
+        let log = db
+            .audit_log(SystemTime::UNIX_EPOCH)
+            .await
+            .expect("failed to read audit log");
+        assert_eq!(
+            Some(&VaultEvent::LotGranted {
+                lot: lot.uuid().clone()
+            }),
+            log.last().map(|entry| &entry.event)
+        );
+        assert!(matches!(
+            Lot::restore(
+                &db,
+                &recovered,
+                lot.uuid(),
+                DEFAULT_LOT,
+                Key::from_bytes(&key_bytes)
+            )
+            .await,
+            Err(Error::NameTaken(_))
+        ));
+
+        let reloaded = Lot::load(&db, DEFAULT_LOT, &recovered)
+            .await
+            .expect("failed to load lot")
+            .expect("missing lot");
+        let shown = Record::show(&db, &reloaded, record.uuid())
+            .await
+            .expect("failed to show record")
+            .expect("missing record");
+        assert_eq!("secret", shown.password().expose());
+        assert_eq!(1, alice.lots(&db).await.expect("failed to list lots").len());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn short_nonce() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("truncated");
+        lot.save(&db, &user).await.expect("failed to save lot");
+
+        self::orm::user_lots::Entity::update_many()
+            .col_expr(
+                self::orm::user_lots::Column::Nonce,
+                sea_orm::sea_query::Expr::value(vec![0u8; 4]),
+            )
+            .exec(db.connection())
+            .await
+            .expect("failed to truncate nonce");
+        assert!(matches!(
+            Lot::load(&db, "truncated", &user).await,
+            Err(Error::Encrypt(encrypt::Error::CorruptCiphertext))
+        ));
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rename_field() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("imported");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let records = [
+            Record::new(
+                &lot,
+                "a.example"
+                    .parse::<Label>()
+                    .unwrap()
+                    .add_extra("login", "alice")
+                    .unwrap(),
+                Data::new("one".try_into().unwrap()),
+            ),
+            Record::new(
+                &lot,
+                "b.example".parse::<Label>().unwrap(),
+                Data::new("two".try_into().unwrap()).add_extra("login".into(), "bob".into()),
+            ),
+            Record::new(
+                &lot,
+                "c.example"
+                    .parse::<Label>()
+                    .unwrap()
+                    .add_extra("url", "c")
+                    .unwrap(),
+                Data::new("three".try_into().unwrap()),
+            ),
+        ];
+        Record::save_many(&db, &mut lot, &records, |_| {})
+            .await
+            .expect("failed to save records");
+
+        assert_eq!(
+            2,
+            lot.rename_field(&db, "login", "username")
+                .await
+                .expect("failed to rename")
+        );
+
+        let lot = Lot::load(&db, "imported", &user)
+            .await
+            .expect("failed to load lot")
+            .expect("missing lot");
+        let a = Record::show(&db, &lot, records[0].uuid())
+            .await
+            .expect("failed to show")
+            .expect("missing record");
+        assert_eq!(
+            Some("alice"),
+            a.label().extra().get("username").map(String::as_str)
+        );
+        assert!(!a.label().extra().contains_key("login"));
+        assert_eq!("one", a.password().expose());
+        let b = Record::show(&db, &lot, records[1].uuid())
+            .await
+            .expect("failed to show")
+            .expect("missing record");
+        assert_eq!(Some(&"bob".to_owned()), b.data().extra().get("username"));
+        assert!(!b.data().extra().contains_key("login"));
+        let c = Record::show(&db, &lot, records[2].uuid())
+            .await
+            .expect("failed to show")
+            .expect("missing record");
+        assert_eq!(records[2].label(), c.label());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reorder() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("lot a");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        for name in ["a", "b", "c", "d"] {
+            Record::new(
+                &lot,
+                name.parse::<Label>().unwrap(),
+                Data::new("secret".try_into().unwrap()),
+            )
+            .save(&db, &mut lot)
+            .await
+            .expect("failed to save record");
+        }
+        let names = |lot: &Lot| -> Vec<String> {
+            lot.index()
+                .sorted()
+                .into_iter()
+                .map(|(label, _)| label.name().to_string())
+                .collect()
+        };
+        assert_eq!(vec!["a", "b", "c", "d"], names(&lot));
+
+        lot.reorder(&db, &["c", "a"])
+            .await
+            .expect("failed to reorder");
+        assert_eq!(vec!["c", "a", "b", "d"], names(&lot));
+
+        let loaded = Lot::load(&db, "lot a", &user)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        assert_eq!(vec!["c", "a", "b", "d"], names(&loaded));
+
+        assert!(matches!(
+            lot.reorder(&db, &["nope"]).await,
+            Err(Error::NoSuchRecord(name)) if name == "nope"
+        ));
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn load_same_name_per_user() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let alice = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let bob = User::new("bob", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut alice_main = Lot::new(DEFAULT_LOT);
+        alice_main
+            .save(&db, &alice)
+            .await
+            .expect("failed to save lot");
+        let mut bob_main = Lot::new(DEFAULT_LOT);
+        bob_main.save(&db, &bob).await.expect("failed to save lot");
+
+        for (user, expected) in [(&alice, &alice_main), (&bob, &bob_main)] {
+            let loaded = Lot::load(&db, DEFAULT_LOT, user)
+                .await
+                .expect("failed to load lot")
+                .expect("no lot");
+            assert_eq!(&loaded, expected);
+        }
+
+        let mut work = Lot::new("work");
+        work.save(&db, &alice).await.expect("failed to save lot");
+        assert!(matches!(
+            Lot::load(&db, "work", &bob).await,
+            Err(Error::NotFound(name)) if name == "work"
+        ));
+    }
 }
@@ -7,6 +7,14 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub uuid: String,
     pub store: Vec<u8>,
+    #[sea_orm(column_name = "parent")]
+    pub parent_uuid: Option<String>,
+    /// Unix seconds, see [`Lot::created_at`](crate::lot::Lot::created_at).
+    pub created_at: Option<i64>,
+    /// Unix seconds, see [`Lot::updated_at`](crate::lot::Lot::updated_at).
+    pub updated_at: Option<i64>,
+    /// See [`Lot::case_insensitive_labels`](crate::lot::Lot::case_insensitive_labels).
+    pub case_insensitive_labels: bool,
     #[sea_orm(has_many, relation_enum = "Records")]
     pub records: HasMany<crate::record::orm::Entity>,
     #[sea_orm(has_many, relation_enum = "UserLot")]
@@ -29,6 +37,8 @@ pub mod user_lots {
         pub name: String,
         pub data: Vec<u8>,
         pub nonce: Vec<u8>,
+        /// See [`Permission`](crate::lot::Permission).
+        pub permission: String,
         #[sea_orm(belongs_to, relation_enum = "User", from = "username", to = "username")]
         pub user: HasOne<crate::user::orm::Entity>,
         #[sea_orm(belongs_to, relation_enum = "Lot", from = "lot_uuid", to = "uuid")]
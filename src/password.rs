@@ -1,16 +1,45 @@
 use bitcode::{Decode, Encode};
+use serde::{Deserialize, Deserializer};
 use std::fmt;
 use std::pin::Pin;
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 pub const MIN_LENGTH: usize = 8;
-pub const MAX_LENGTH: usize = 255;
+/// Longest password, in bytes, a [`Password`] holds. Also bounds the
+/// input argon2 hashes for a master password.
+///
+/// The buffer is this size, so changing it changes how a [`Password`]
+/// encodes; bump [`Data`]'s schema with it.
+///
+/// [`Data`]: crate::record::Data
+pub const MAX_LENGTH: usize = 1024;
+/// [`MAX_LENGTH`] before record data schema 3.
+const LEGACY_LENGTH: usize = 255;
+
+/// A password longer than [`MAX_LENGTH`], rejected rather than truncated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TooLong {
+    /// The rejected password's length in bytes.
+    pub len: usize,
+}
+
+impl fmt::Display for TooLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "password is {} bytes, longer than {MAX_LENGTH}",
+            self.len
+        )
+    }
+}
+
+impl std::error::Error for TooLong {}
 
 /// A safe wrapper for plaintext password strings.
 ///
 /// This structure zeros it's memory on drop. It also prevents moving the
 /// underlying heap allocated string buffer. To do this it's a fixed allocation
-/// of 255 bytes.
+/// of [`MAX_LENGTH`] bytes.
 //
 // TODO: Implement Secret for unpinned larger secrets.
 //
@@ -59,10 +88,19 @@ impl Password {
         &mut self.0[0..null_pos]
     }
 
-    pub fn as_str(&self) -> &str {
+    /// The plaintext password.
+    ///
+    /// There is deliberately no `Display` impl, revealing a password is
+    /// always an explicit call to this method.
+    pub fn expose(&self) -> &str {
         unsafe { str::from_utf8_unchecked(self.as_bytes()) }
     }
 
+    /// This password drawn with `style`, see [`MaskStyle::mask`].
+    pub fn masked(&self, style: MaskStyle) -> String {
+        style.mask(self.expose())
+    }
+
     /// # Safety
     ///
     /// The caller must ensure that any writes through the returned
@@ -72,6 +110,57 @@ impl Password {
     }
 }
 
+/// How a secret is drawn when it isn't being revealed.
+///
+/// A fixed-width mask doesn't leak the secret's length, and is the only
+/// option when the secret hasn't been decrypted at all (e.g. a collapsed
+/// GUI row). Length-preserving masks are friendlier for editing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaskStyle {
+    /// Character repeated to draw the mask.
+    pub ch: char,
+    /// Draw one `ch` per character of the secret, instead of
+    /// [`MaskStyle::FIXED_WIDTH`] regardless of the secret.
+    pub preserve_length: bool,
+}
+
+impl MaskStyle {
+    /// Width of every mask when [`preserve_length`](Self::preserve_length)
+    /// is off.
+    pub const FIXED_WIDTH: usize = 8;
+
+    pub const BULLET: MaskStyle = MaskStyle {
+        ch: '•',
+        preserve_length: false,
+    };
+
+    pub const ASTERISK: MaskStyle = MaskStyle {
+        ch: '*',
+        preserve_length: false,
+    };
+
+    pub fn preserve_length(mut self, preserve_length: bool) -> Self {
+        self.preserve_length = preserve_length;
+        self
+    }
+
+    /// Draw the mask for `secret`.
+    pub fn mask(&self, secret: &str) -> String {
+        let width = if self.preserve_length {
+            secret.chars().count()
+        } else {
+            Self::FIXED_WIDTH
+        };
+        std::iter::repeat_n(self.ch, width).collect()
+    }
+}
+
+impl Default for MaskStyle {
+    fn default() -> Self {
+        MaskStyle::BULLET
+    }
+}
+
 // TODO: We shouldn't even allow empty passwords at all, or provide a
 // Password::valid method.
 impl Default for Password {
@@ -81,11 +170,11 @@ impl Default for Password {
 }
 
 impl TryFrom<&str> for Password {
-    type Error = ();
+    type Error = TooLong;
 
     fn try_from(str: &str) -> Result<Self, Self::Error> {
         if str.len() > MAX_LENGTH {
-            return Err(());
+            return Err(TooLong { len: str.len() });
         }
         let mut buf = [0; MAX_LENGTH];
         for (d, s) in buf.iter_mut().zip(str.bytes()) {
@@ -95,15 +184,30 @@ impl TryFrom<&str> for Password {
     }
 }
 
-// TODO: Consider a more explicit way to reveal passwords.
-impl fmt::Display for Password {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Ok(utf8) = str::from_utf8(&*self.0) {
-            let null_pos = utf8.find('\0').unwrap_or(utf8.len());
-            write!(f, "{}", &utf8[0..null_pos])
-        } else {
-            write!(f, "<invalid utf8>")
+/// A [`Password`] as written in record data before schema 3, in a
+/// [`LEGACY_LENGTH`] byte buffer.
+#[derive(Encode, Decode, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct LegacyPassword(Pin<Box<[u8; LEGACY_LENGTH]>>);
+
+impl From<LegacyPassword> for Password {
+    fn from(legacy: LegacyPassword) -> Self {
+        let mut password = Password::default();
+        password.0[..LEGACY_LENGTH].copy_from_slice(&legacy.0[..]);
+        password
+    }
+}
+
+#[cfg(test)]
+impl TryFrom<&str> for LegacyPassword {
+    type Error = TooLong;
+
+    fn try_from(str: &str) -> Result<Self, Self::Error> {
+        if str.len() > LEGACY_LENGTH {
+            return Err(TooLong { len: str.len() });
         }
+        let mut buf = [0; LEGACY_LENGTH];
+        buf[..str.len()].copy_from_slice(str.as_bytes());
+        Ok(LegacyPassword(Box::pin(buf)))
     }
 }
 
@@ -113,6 +217,13 @@ impl fmt::Debug for Password {
     }
 }
 
+impl<'de> Deserialize<'de> for Password {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = Zeroizing::new(String::deserialize(deserializer)?);
+        Password::try_from(s.as_str()).map_err(serde::de::Error::custom)
+    }
+}
+
 #[cfg(feature = "gui")]
 use eframe::egui::TextBuffer;
 #[cfg(feature = "gui")]
@@ -125,7 +236,7 @@ impl TextBuffer for Password {
     }
 
     fn as_str(&self) -> &str {
-        self.as_str()
+        self.expose()
     }
 
     fn insert_text(&mut self, text: &str, char_index: usize) -> usize {
@@ -168,6 +279,31 @@ mod tests {
         assert!(!invalid.is_valid());
     }
 
+    #[test]
+    fn max_length() {
+        let max = "a".repeat(MAX_LENGTH);
+        let password = Password::try_from(max.as_str()).expect("max length rejected");
+        assert_eq!(max, password.expose());
+
+        let over = "a".repeat(MAX_LENGTH + 1);
+        assert_eq!(
+            Err(TooLong {
+                len: MAX_LENGTH + 1
+            }),
+            Password::try_from(over.as_str()).map(|_| ())
+        );
+        assert!(serde_json::from_str::<Password>(&format!("\"{over}\"")).is_err());
+    }
+
+    #[test]
+    fn legacy_upgrade() {
+        let max = "a".repeat(LEGACY_LENGTH);
+        let legacy = LegacyPassword::try_from(max.as_str()).expect("failed to build");
+        let decoded: LegacyPassword =
+            bitcode::decode(&bitcode::encode(&legacy)).expect("failed to decode");
+        assert_eq!(max, Password::from(decoded).expose());
+    }
+
     #[test]
     fn from_str() {
         let password_string = String::from("password");
@@ -180,6 +316,31 @@ mod tests {
         let password: Password = "hunter2hunter2".try_into().unwrap();
         let rendered = format!("{password:?}");
         assert!(!rendered.contains("hunter2"));
+        assert_eq!("Password(***)", rendered);
+        assert_eq!("hunter2hunter2", password.expose());
+    }
+
+    #[test]
+    fn mask_fixed_width() {
+        let password: Password = "hunter2".try_into().unwrap();
+        assert_eq!("••••••••", password.masked(MaskStyle::BULLET));
+        assert_eq!("********", password.masked(MaskStyle::ASTERISK));
+        assert_eq!("••••••••", MaskStyle::default().mask(""));
+    }
+
+    #[test]
+    fn mask_preserve_length() {
+        let password: Password = "hunter2".try_into().unwrap();
+        assert_eq!(
+            "•••••••",
+            password.masked(MaskStyle::BULLET.preserve_length(true))
+        );
+        assert_eq!(
+            "*******",
+            password.masked(MaskStyle::ASTERISK.preserve_length(true))
+        );
+        // Counts characters, not bytes.
+        assert_eq!("**", MaskStyle::ASTERISK.preserve_length(true).mask("é€"));
     }
 
     #[test]
@@ -2,5 +2,7 @@
 pub use crate::db::Database;
 pub use crate::lot::{DEFAULT_LOT, Lot};
 pub use crate::password::Password;
-pub use crate::record::{Data, Label, Record};
+pub use crate::record::{Data, Label, Path, Record};
 pub use crate::user::User;
+#[cfg(feature = "db")]
+pub use crate::vault::Vault;
@@ -228,7 +228,7 @@ mod tests {
         match got {
             Request::Unlock { username, password } => {
                 assert_eq!(username, "alice");
-                assert_eq!(password.to_string(), "s3cret!!");
+                assert_eq!(password.expose(), "s3cret!!");
             }
             _ => panic!("wrong variant"),
         }
@@ -267,7 +267,7 @@ mod tests {
         match got {
             Response::Record(record) => {
                 assert_eq!(record.uuid().to_uuid(), uuid.to_uuid());
-                assert_eq!(record.password().to_string(), "hunter22");
+                assert_eq!(record.password().expose(), "hunter22");
             }
             _ => panic!("wrong variant"),
         }
@@ -11,7 +11,8 @@ use crate::protocol::message::{Request, Response, RevisionEntry};
 use crate::{
     Lot, Record,
     db::Database,
-    lot::DEFAULT_LOT,
+    encrypt::{self, KdfParams},
+    lot::{PATH_SEPARATOR, SortKey},
     password::Password,
     record::{Data, Label},
     user::User,
@@ -81,6 +82,9 @@ pub struct State {
     /// Set whenever there are unlocked users; cleared when state is
     /// dropped.
     pub last_activity: Option<Instant>,
+    /// Key derivation cost for new users, calibrated on the first
+    /// register and reused after, since the hardware doesn't change.
+    pub kdf: Option<KdfParams>,
 }
 
 impl State {
@@ -91,6 +95,7 @@ impl State {
             lots: HashMap::new(),
             user_lots: HashMap::new(),
             last_activity: None,
+            kdf: None,
         }
     }
 
@@ -215,6 +220,45 @@ async fn reap_if_idle(state: &Arc<Mutex<State>>, idle_timeout: Duration) -> bool
     }
 }
 
+/// Drop every unlocked user whose key has been replaced by a password
+/// change in another session, see [`User::is_stale`]. Returns who was
+/// dropped.
+async fn reap_stale(state: &Arc<Mutex<State>>) -> Vec<String> {
+    // Only the snapshot is taken under the lock; the queries run without
+    // it so requests don't queue behind the reaper.
+    let (db, versions) = {
+        let st = state.lock().await;
+        let versions: Vec<(String, i64)> = st
+            .users
+            .iter()
+            .map(|(username, user)| (username.clone(), user.key_version()))
+            .collect();
+        (st.db.clone(), versions)
+    };
+    let mut stale = Vec::new();
+    for (username, version) in versions {
+        match User::current_key_version(&db, &username).await {
+            Ok(current) if current != Some(version) => stale.push((username, version)),
+            Ok(_) => {}
+            Err(e) => warn!(user = %username, "failed to check key version: {e:?}"),
+        }
+    }
+    let mut st = state.lock().await;
+    // A user unlocked again in the meantime has a fresh key; keep it.
+    stale.retain(|(username, version)| {
+        st.users
+            .get(username)
+            .is_some_and(|user| user.key_version() == *version)
+    });
+    stale
+        .into_iter()
+        .map(|(username, _)| {
+            st.drop_user(&username);
+            username
+        })
+        .collect()
+}
+
 fn spawn_reaper(
     rt: &tokio::runtime::Handle,
     state: Arc<Mutex<State>>,
@@ -232,6 +276,9 @@ fn spawn_reaper(
             if reap_if_idle(&state, idle_timeout).await {
                 info!("idle timeout, locked all users");
             }
+            for username in reap_stale(&state).await {
+                info!(user = %username, "password changed elsewhere, locked");
+            }
         }
     });
 }
@@ -315,14 +362,24 @@ async fn dispatch(state: &Arc<Mutex<State>>, req: Request) -> Result<Response, S
             create_record(state, username, lot, label, password, extra).await
         }
         Request::Register { username, password } => {
+            // Calibrating and deriving the key both take a while, so
+            // neither holds the lock. Calibration is a blocking timing
+            // loop, so it gets a blocking thread too.
+            let cached = state.lock().await.kdf;
+            let kdf = match cached {
+                Some(kdf) => kdf,
+                None => {
+                    let kdf = tokio::task::spawn_blocking(|| {
+                        KdfParams::calibrate(encrypt::DEFAULT_TARGET)
+                    })
+                    .await
+                    .map_err(err)?;
+                    *state.lock().await.kdf.get_or_insert(kdf)
+                }
+            };
+            let user = User::with_kdf(&username, password, kdf).map_err(err)?;
             let mut st = state.lock().await;
-            let user = User::new(&username, password)
-                .map_err(err)?
-                .register(&st.db)
-                .await
-                .map_err(err)?;
-            let mut lot = Lot::new(DEFAULT_LOT);
-            lot.save(&st.db, &user).await.map_err(err)?;
+            let (user, lot) = user.register_with_default_lot(&st.db).await.map_err(err)?;
             // Leave the newly-registered user unlocked. The caller has
             // just proved the password; forcing a follow-up Unlock to
             // re-derive the key is redundant Argon2 work.
@@ -356,6 +413,7 @@ async fn dispatch(state: &Arc<Mutex<State>>, req: Request) -> Result<Response, S
             lot,
             uuid,
         } => history(state, &username, &lot, &uuid).await,
+        Request::ListByLot { username, queries } => list_by_lot(state, &username, &queries).await,
     }
 }
 
@@ -364,30 +422,59 @@ async fn list(
     username: &str,
     queries: &[String],
 ) -> Result<Response, String> {
-    use crate::record::Query;
-    let parsed = queries
-        .iter()
-        .map(|s| s.parse::<Query>())
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("invalid query: {e}"))?;
-
+    let parsed = parse_queries(queries)?;
     let st = state.lock().await;
-    let lot_uuids = user_lot_uuids(&st, username)?;
-
     let mut out: Vec<(Uuid<Record>, Label)> = Vec::new();
-    for lot_uuid in lot_uuids {
+    for lot_uuid in user_lot_uuids(&st, username)? {
+        out.extend(matching_records(st.get_lot(lot_uuid)?, &parsed)?);
+    }
+    Ok(Response::Index(out))
+}
+
+async fn list_by_lot(
+    state: &Arc<Mutex<State>>,
+    username: &str,
+    queries: &[String],
+) -> Result<Response, String> {
+    let parsed = parse_queries(queries)?;
+    let st = state.lock().await;
+    let mut out = Vec::new();
+    for lot_uuid in user_lot_uuids(&st, username)? {
         let lot = st.get_lot(lot_uuid)?;
-        for (label, uuid) in lot.index().iter() {
-            let keep = parsed.is_empty()
-                || parsed
-                    .iter()
-                    .any(|q| q.matches_lot(lot.name()) && q.matches_label(label));
-            if keep {
-                out.push((uuid.clone(), label.clone()));
-            }
+        let entries = matching_records(lot, &parsed)?;
+        if !entries.is_empty() {
+            out.push((lot.name().to_owned(), entries));
         }
     }
-    Ok(Response::Index(out))
+    Ok(Response::LotIndex(out))
+}
+
+fn parse_queries(queries: &[String]) -> Result<Vec<crate::record::Query>, String> {
+    queries
+        .iter()
+        .map(|s| s.parse())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid query: {e}"))
+}
+
+/// `lot`'s records matching any of `queries`, or all of them if there
+/// are none, sorted by label.
+fn matching_records(
+    lot: &Lot,
+    queries: &[crate::record::Query],
+) -> Result<Vec<(Uuid<Record>, Label)>, String> {
+    Ok(lot
+        .records_sorted(SortKey::Label)
+        .map_err(err)?
+        .into_iter()
+        .filter(|(label, _)| {
+            queries.is_empty()
+                || queries
+                    .iter()
+                    .any(|q| q.matches_lot(lot.name()) && q.matches_label(label))
+        })
+        .map(|(label, uuid)| (uuid.clone(), label.clone()))
+        .collect())
 }
 
 async fn fetch_any_lot(
@@ -448,7 +535,13 @@ async fn create_record(
     // storgit extends that submodule's history, rather than minting a
     // fresh uuid on every save.
     let record = match l.index().find_by_name(label.name()).cloned() {
-        Some(existing) => Record::with_uuid(existing, l, label, data),
+        Some(existing) => {
+            // The request carries no tags, so keep the record's own.
+            if let Some(prior) = Record::show(db, l, &existing).await.map_err(err)? {
+                data = data.with_tags(prior.data().tags());
+            }
+            Record::with_uuid(existing, l, label, data)
+        }
         None => Record::new(l, label, data),
     };
     record.save(db, l).await.map_err(err)?;
@@ -458,12 +551,19 @@ async fn create_record(
 
 async fn list_lots(state: &Arc<Mutex<State>>, username: &str) -> Result<Response, String> {
     let st = state.lock().await;
-    let lot_uuids = user_lot_uuids(&st, username)?;
-    let mut entries: Vec<(Uuid<Lot>, String)> = lot_uuids
-        .iter()
-        .map(|u| st.get_lot(u).map(|l| (u.clone(), l.name().to_owned())))
-        .collect::<Result<_, _>>()?;
-    entries.sort_by(|a, b| a.1.cmp(&b.1));
+    let user = st
+        .users
+        .get(username)
+        .ok_or_else(|| format!("user '{username}' is locked"))?;
+    // Summaries come from SQLite without touching the cached stores,
+    // already sorted by name.
+    let entries = user
+        .lots_summary(&st.db)
+        .await
+        .map_err(err)?
+        .into_iter()
+        .map(|summary| (summary.uuid, summary.name))
+        .collect();
     Ok(Response::Lots(entries))
 }
 
@@ -473,11 +573,18 @@ async fn create_lot(
     lot_name: &str,
 ) -> Result<Response, String> {
     let mut st = state.lock().await;
+    // A path like `work::aws` nests the new lot under an existing one.
+    let mut lot = match lot_name.rsplit_once(PATH_SEPARATOR) {
+        Some((parent_path, leaf)) => {
+            let parent_uuid = lookup_lot_uuid(&st, username, parent_path)?;
+            Lot::new_child(leaf, st.get_lot(&parent_uuid)?)
+        }
+        None => Lot::new(lot_name),
+    };
     let user = st
         .users
         .get(username)
         .ok_or_else(|| format!("user '{username}' is locked"))?;
-    let mut lot = Lot::new(lot_name);
     lot.save(&st.db, user).await.map_err(err)?;
     info!(user = %username, lot = %lot_name, "lot created");
     st.insert_lot(username, lot);
@@ -564,16 +671,30 @@ async fn sync_user_lots(st: &mut State, username: &str) -> Result<(), String> {
 }
 
 /// Look up the uuid of `lot_name` for `username`, reading only from
-/// cache (no DB). Errors if the user is locked or the lot isn't in
-/// their access list.
+/// cache (no DB). `lot_name` may be a nested path like `work::aws`, in
+/// which case the first segment must be one of the user's top level
+/// lots and each one after it a child of the one before it, as with
+/// [`Lot::load`]. Errors if the user is locked or the lot isn't in their
+/// access list.
 fn lookup_lot_uuid(st: &State, username: &str, lot_name: &str) -> Result<Uuid<Lot>, String> {
     let uuids = user_lot_uuids(st, username)?;
-    for uuid in uuids {
-        if st.get_lot(uuid)?.name() == lot_name {
-            return Ok(uuid.clone());
+    let mut found: Option<Uuid<Lot>> = None;
+    for segment in lot_name.split(PATH_SEPARATOR) {
+        let mut next = None;
+        for uuid in uuids {
+            let lot = st.get_lot(uuid)?;
+            let parent_matches = match &found {
+                Some(found) => lot.parent() == Some(found),
+                None => lot.parent().is_none_or(|p| !uuids.contains(p)),
+            };
+            if lot.name() == segment && parent_matches {
+                next = Some(uuid.clone());
+                break;
+            }
         }
+        found = Some(next.ok_or_else(|| format!("lot '{lot_name}' not found"))?);
     }
-    Err(format!("lot '{lot_name}' not found"))
+    found.ok_or_else(|| format!("lot '{lot_name}' not found"))
 }
 
 /// Borrow the per-user uuid list. Errors if the user isn't unlocked.
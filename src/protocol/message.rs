@@ -109,6 +109,12 @@ pub enum Request {
         lot: String,
         uuid: Uuid<Record>,
     },
+    /// [`Request::List`], grouped by the name of the lot each record is
+    /// in. Answered with [`Response::LotIndex`].
+    ListByLot {
+        username: String,
+        queries: Vec<String>,
+    },
 }
 
 /// One historical revision of a record, as carried in
@@ -151,6 +157,9 @@ pub enum Response {
     /// when the handler cannot satisfy the request.
     // TODO: Make a proper Error enum for this too
     Error(String),
+    /// Lot names, each with its matching label-and-uuid pairs, leaving
+    /// out lots with none (ListByLot).
+    LotIndex(Vec<(String, Vec<(Uuid<Record>, Label)>)>),
 }
 
 // TODO: goes away with the `Request::FindRecords` fold (see the TODO on
@@ -292,6 +301,24 @@ impl Call for List {
     }
 }
 
+/// Payload for [`Request::ListByLot`].
+pub struct ListByLot {
+    pub username: String,
+    pub queries: Vec<String>,
+}
+impl Call for ListByLot {
+    type Response = Vec<(String, Vec<(Uuid<Record>, Label)>)>;
+    fn into_request(self) -> Request {
+        Request::ListByLot {
+            username: self.username,
+            queries: self.queries,
+        }
+    }
+    fn from_response(r: Response) -> Result<Self::Response, ResponseError> {
+        r.expect_lot_index()
+    }
+}
+
 /// Payload for [`Request::Fetch`].
 pub struct Fetch {
     pub username: String,
@@ -533,6 +560,18 @@ impl Response {
         }
     }
 
+    /// Extract [`Response::LotIndex`]. Folds [`Response::Error`] and any
+    /// other variant into [`ResponseError`].
+    pub(crate) fn expect_lot_index(
+        self,
+    ) -> Result<Vec<(String, Vec<(Uuid<Record>, Label)>)>, ResponseError> {
+        match self {
+            Response::LotIndex(v) => Ok(v),
+            Response::Error(msg) => Err(ResponseError::Remote(msg)),
+            _ => Err(ResponseError::UnexpectedResponse),
+        }
+    }
+
     /// Extract [`Response::Record`]. Folds [`Response::Error`] and any
     /// other variant into [`ResponseError`].
     pub(crate) fn expect_record(self) -> Result<Record, ResponseError> {
@@ -0,0 +1,141 @@
+use super::{PASSWORD_KEY, Record, USERNAME_KEY};
+use crate::totp::{OTP_KEY, Totp};
+use std::{convert::Infallible, fmt, str::FromStr};
+
+/// Which value of a record a copy (e.g. to the clipboard) takes, see
+/// [`Record::copy_value`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum CopyField {
+    #[default]
+    Password,
+    /// See [`Label::username`](crate::record::Label::username).
+    Username,
+    /// The current code for the record's `otp` secret.
+    Otp,
+    /// A named field, looked up in the [`Data`] extras then the
+    /// [`Label`] extras.
+    ///
+    /// [`Data`]: crate::record::Data
+    /// [`Label`]: crate::record::Label
+    Field(String),
+}
+
+/// `password`, `username` and `otp` name their variants, anything else is
+/// a [`CopyField::Field`].
+impl FromStr for CopyField {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            PASSWORD_KEY => CopyField::Password,
+            USERNAME_KEY => CopyField::Username,
+            OTP_KEY => CopyField::Otp,
+            field => CopyField::Field(field.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for CopyField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CopyField::Password => write!(f, "{PASSWORD_KEY}"),
+            CopyField::Username => write!(f, "{USERNAME_KEY}"),
+            CopyField::Otp => write!(f, "{OTP_KEY}"),
+            CopyField::Field(field) => write!(f, "{field}"),
+        }
+    }
+}
+
+impl Record {
+    /// The value `field` picks out of this record, `None` if it has no
+    /// such field (or an `otp` secret that doesn't parse).
+    pub fn copy_value(&self, field: &CopyField) -> Option<String> {
+        match field {
+            CopyField::Password => Some(self.password().expose().to_owned()),
+            CopyField::Username => self.username().map(str::to_owned),
+            CopyField::Otp => Totp::parse(self.otp()?).ok().map(|totp| totp.code()),
+            CopyField::Field(field) => self
+                .data()
+                .extra()
+                .get(field)
+                .or_else(|| self.label().extra().get(field))
+                .cloned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lot::Lot,
+        record::{Data, Label},
+    };
+
+    fn record(label: &str, data: Data) -> Record {
+        let lot = Lot::new("lot");
+        Record::new(&lot, label.parse().unwrap(), data)
+    }
+
+    #[test]
+    fn domain_fields() {
+        let lot = Lot::new("lot");
+        let record = Record::new(
+            &lot,
+            "nix@example.com"
+                .parse::<Label>()
+                .unwrap()
+                .add_extra("url", "https://example.com")
+                .unwrap(),
+            Data::new("secret".try_into().unwrap())
+                .add_extra("notes".into(), "hi".into())
+                .add_extra(OTP_KEY.into(), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".into()),
+        );
+        assert_eq!(
+            Some("secret".into()),
+            record.copy_value(&CopyField::default())
+        );
+        assert_eq!(Some("nix".into()), record.copy_value(&CopyField::Username));
+        let code = record.copy_value(&CopyField::Otp).expect("no otp code");
+        assert_eq!(6, code.len());
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(
+            Some("hi".into()),
+            record.copy_value(&"notes".parse().unwrap())
+        );
+        assert_eq!(
+            Some("https://example.com".into()),
+            record.copy_value(&"url".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn missing_fields() {
+        let simple = record("github", Data::new("secret".try_into().unwrap()));
+        assert_eq!(None, simple.copy_value(&CopyField::Username));
+        assert_eq!(None, simple.copy_value(&CopyField::Otp));
+        assert_eq!(None, simple.copy_value(&"notes".parse().unwrap()));
+        assert_eq!(
+            Some("secret".into()),
+            simple.copy_value(&CopyField::Password)
+        );
+
+        let bad_otp = record(
+            "nix@example.com",
+            Data::new("secret".try_into().unwrap()).add_extra(OTP_KEY.into(), "!!".into()),
+        );
+        assert_eq!(None, bad_otp.copy_value(&CopyField::Otp));
+    }
+
+    #[test]
+    fn parse() {
+        for field in [
+            CopyField::Password,
+            CopyField::Username,
+            CopyField::Otp,
+            CopyField::Field("notes".into()),
+        ] {
+            assert_eq!(field, field.to_string().parse().unwrap());
+        }
+    }
+}
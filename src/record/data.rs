@@ -1,6 +1,34 @@
-use crate::{encrypt::Stash, lot::Lot, password::Password};
+use crate::{
+    encrypt::Stash,
+    lot::Lot,
+    password::{LegacyPassword, MaskStyle, Password},
+    record::Error,
+    totp::{OTP_KEY, Totp},
+};
 use bitcode::{Decode, Encode};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+/// The name the password is displayed and exported under. It isn't a
+/// [`Data::extra`] key, the password has its own field.
+pub const PASSWORD_KEY: &str = "password";
+
+/// The [`Data::extra`] key for free-form notes, see [`Data::notes`].
+pub const NOTES_KEY: &str = "notes";
+
+/// Field names [`Data::display_masked`] redacts by default. The password
+/// itself is always displayed as `password`.
+pub const SECRET_KEYS: &[&str] = &[PASSWORD_KEY, OTP_KEY, "cvv"];
+
+/// Largest [`Data::extra`] value, in bytes, [`Data::check_values`] allows.
+/// Anything bigger is a file, not a field.
+pub const MAX_VALUE_SIZE: usize = 1024 * 1024;
+
+/// The name tags are displayed under. Schema 1 stored them in
+/// [`Data::extra`] under this key, comma separated, where an imported
+/// field of the same name would clobber them; they have their own field
+/// now, see [`Data::tags`].
+pub const TAGS_KEY: &str = "tags";
 
 /// A record's secret payload: the password plus any attributes that are only
 /// meaningful once the record is opened.
@@ -13,25 +41,97 @@ use std::collections::HashMap;
 /// `url`), put it on [`Label::extra`]. If it is opaque supplementary content
 /// (e.g. `notes`, recovery codes, TOTP secrets), put it on [`Data::extra`].
 ///
+/// Stored with bitcode. The serde impls are for interchange, as
+/// `{"password":"...","extra":{...},"tags":[...]}` with the password in the
+/// clear; `tags` is left out when there are none.
+///
 /// [`Label::extra`]: crate::record::Label::extra
 /// [`RecordIndex`]: crate::record::RecordIndex
-#[derive(Encode, Decode, Debug, Eq, PartialEq)]
+#[derive(Encode, Decode, Serialize, Deserialize, Eq, PartialEq)]
 pub struct Data {
+    #[serde(serialize_with = "serialize_password")]
     password: Password,
     /// Opaque supplementary attributes. Encrypted as part of the enclosing
     /// [`Data`] under the lot key (see [`Stash<Lot>`]); not visible to
     /// [`RecordIndex`](crate::record::RecordIndex). See the [`Data`] type
     /// docs for when to use this vs. [`Label::extra`](crate::record::Label::extra).
+    #[serde(default)]
+    extra: HashMap<String, String>,
+    /// See [`Data::with_tags`]. Kept apart from `extra` so no field name
+    /// is reserved.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
+
+/// [`Data`] as written at schema 1, with tags in the extras.
+#[derive(Encode, Decode)]
+struct DataV1 {
+    password: LegacyPassword,
     extra: HashMap<String, String>,
 }
 
-impl Stash<Lot> for Data {}
+/// [`Data`] as written at schema 2, with a shorter password buffer.
+#[derive(Encode, Decode)]
+struct DataV2 {
+    password: LegacyPassword,
+    extra: HashMap<String, String>,
+    tags: Vec<String>,
+}
+
+/// [`Password`] deliberately isn't `Serialize`, so nothing holding one
+/// writes it out by accident. Exported data is the exception.
+fn serialize_password<S: serde::Serializer>(
+    password: &Password,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(password.expose())
+}
+
+/// Bump [`Stash::SCHEMA_VERSION`] with any change to `Data`'s fields, and
+/// add an arm to `decode_version` upgrading the old layout, so records
+/// already in a vault keep opening.
+impl Stash<Lot> for Data {
+    const SCHEMA_VERSION: u16 = 3;
+
+    fn decode_version(version: u16, buf: &[u8]) -> Result<Self, crate::encrypt::Error> {
+        match version {
+            1 => {
+                let DataV1 {
+                    password,
+                    mut extra,
+                } = bitcode::decode(buf).map_err(crate::encrypt::Error::Decoding)?;
+                let tags = extra.remove(TAGS_KEY);
+                Ok(Data {
+                    password: password.into(),
+                    extra,
+                    tags: Vec::new(),
+                }
+                .with_tags(tags.as_deref().unwrap_or_default().split(',')))
+            }
+            2 => {
+                let DataV2 {
+                    password,
+                    extra,
+                    tags,
+                } = bitcode::decode(buf).map_err(crate::encrypt::Error::Decoding)?;
+                Ok(Data {
+                    password: password.into(),
+                    extra,
+                    tags,
+                })
+            }
+            3 => Self::decode(buf),
+            _ => Err(crate::encrypt::Error::UnknownSchema(version)),
+        }
+    }
+}
 
 impl Data {
     pub fn new(password: Password) -> Self {
         Data {
             password,
             extra: HashMap::new(),
+            tags: Vec::new(),
         }
     }
 
@@ -49,15 +149,226 @@ impl Data {
         &self.password
     }
 
+    /// Check that fields with a known format are well formed: currently
+    /// that an [`otp`](OTP_KEY) field parses into a working TOTP generator.
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(otp) = self.extra.get(OTP_KEY) {
+            Totp::parse(otp)?.code();
+        }
+        Ok(())
+    }
+
+    /// Check that every [`Data::extra`] value fits in [`MAX_VALUE_SIZE`]
+    /// and has no NUL bytes, which editors and terminals mangle. Checked
+    /// by [`Record::save`](crate::record::Record::save) and
+    /// [`Record::save_many`](crate::record::Record::save_many).
+    pub fn check_values(&self) -> Result<(), Error> {
+        for (key, value) in &self.extra {
+            if value.len() > MAX_VALUE_SIZE {
+                return Err(Error::ValueTooLarge {
+                    key: key.clone(),
+                    len: value.len(),
+                });
+            }
+            if value.contains('\0') {
+                return Err(Error::NulInValue { key: key.clone() });
+            }
+        }
+        Ok(())
+    }
+
     pub fn extra(&self) -> &HashMap<String, String> {
         &self.extra
     }
+
+    /// The [`NOTES_KEY`] extra.
+    pub fn notes(&self) -> Option<&str> {
+        self.extra.get(NOTES_KEY).map(String::as_str)
+    }
+
+    /// The [`OTP_KEY`] extra, as stored: an `otpauth://` URI or a bare
+    /// secret, see [`Totp::parse`].
+    pub fn otp(&self) -> Option<&str> {
+        self.extra.get(OTP_KEY).map(String::as_str)
+    }
+
+    /// Replace this record's tags. Tags are trimmed, and empty or repeated
+    /// ones dropped; commas can't appear in a tag, so they split it.
+    pub fn with_tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.tags.clear();
+        for tag in tags {
+            for tag in tag.as_ref().split(',').map(str::trim) {
+                if !tag.is_empty() && !self.tags.iter().any(|t| t == tag) {
+                    self.tags.push(tag.to_owned());
+                }
+            }
+        }
+        self
+    }
+
+    /// This record's tags, in the order they were added.
+    pub fn tags(&self) -> Vec<&str> {
+        self.tags.iter().map(String::as_str).collect()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().contains(&tag.trim())
+    }
+
+    /// Render like [`Display`](fmt::Display), but with the [`SECRET_KEYS`]
+    /// fields masked.
+    pub fn display_masked(&self) -> String {
+        self.display_masked_with(SECRET_KEYS, MaskStyle::default())
+    }
+
+    /// Render like [`Display`](fmt::Display), masking every field named in
+    /// `secret_keys` with `style`.
+    pub fn display_masked_with(&self, secret_keys: &[&str], style: MaskStyle) -> String {
+        let mut out = String::new();
+        self.write_fields(&mut out, |key, value| {
+            if secret_keys.iter().any(|k| *k == key) {
+                style.mask(value)
+            } else {
+                value.to_owned()
+            }
+        })
+        .expect("writing to a String cannot fail");
+        out
+    }
+
+    /// One `key: value` line per field, password first and extras sorted
+    /// by key, with each value passed through `render`.
+    fn write_fields(
+        &self,
+        f: &mut impl fmt::Write,
+        render: impl Fn(&str, &str) -> String,
+    ) -> fmt::Result {
+        write!(
+            f,
+            "{PASSWORD_KEY}: {}",
+            render(PASSWORD_KEY, self.password.expose())
+        )?;
+        if !self.tags.is_empty() {
+            write!(f, "\n{TAGS_KEY}: {}", self.tags.join(","))?;
+        }
+        let mut extra: Vec<_> = self.extra.iter().collect();
+        extra.sort();
+        for (key, value) in extra {
+            write!(f, "\n{key}: {}", render(key, value))?;
+        }
+        Ok(())
+    }
+
+    /// Combine this data with a newer copy of the same record, e.g. when an
+    /// import overlaps records already in the lot.
+    ///
+    /// The password is taken from `other`. Extras are unioned; on a shared
+    /// key `other`'s value wins unless it is empty, so a sparse re-import
+    /// doesn't blank out attributes it simply didn't carry. Tags are
+    /// unioned too, this data's first.
+    pub fn merge(&self, other: &Data) -> Data {
+        let mut extra = self.extra.clone();
+        for (key, value) in &other.extra {
+            if !value.is_empty() || !extra.contains_key(key) {
+                extra.insert(key.clone(), value.clone());
+            }
+        }
+        Data {
+            password: other.password.clone(),
+            extra,
+            tags: Vec::new(),
+        }
+        .with_tags(self.tags.iter().chain(&other.tags))
+    }
+}
+
+/// Every field in full, secrets included. See [`Data::display_masked`] for
+/// a redacted rendering.
+impl fmt::Display for Data {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_fields(f, |_, value| value.to_owned())
+    }
+}
+
+/// Field names only, every value is redacted so `dbg!` and logs can't leak
+/// one. [`Data::debug_full`] shows them.
+impl fmt::Debug for Data {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut keys: Vec<_> = self.extra.keys().collect();
+        keys.sort();
+        f.debug_struct("Data")
+            .field("password", &self.password)
+            .field(
+                "extra",
+                &DebugMap(keys.into_iter().map(|key| (key, Redacted)).collect()),
+            )
+            .field("tags", &self.tags)
+            .finish()
+    }
+}
+
+/// Debugs as `<redacted>`, in place of a secret value.
+struct Redacted;
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+/// Sorted map entries for a [`fmt::Debug`] impl.
+struct DebugMap<K, V>(Vec<(K, V)>);
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for DebugMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(k, v)| (k, v)))
+            .finish()
+    }
+}
+
+impl Data {
+    /// `{:?}` with the password and every extra in the clear, for tests
+    /// that need to see them; the [`Debug`](fmt::Debug) impl redacts them.
+    pub fn debug_full(&self) -> String {
+        let mut extra: Vec<_> = self.extra.iter().collect();
+        extra.sort();
+        format!(
+            "Data {{ password: {:?}, extra: {:?}, tags: {:?} }}",
+            self.password.expose(),
+            DebugMap(extra),
+            self.tags
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{encrypt::Key, lot::Lot};
+    use crate::{
+        encrypt::{self, COMPRESSION_THRESHOLD, Compression, HEADER_VERSION, Key},
+        lot::Lot,
+    };
+
+    #[test]
+    fn check_values() {
+        let data = |value: String| {
+            Data::new("secret".try_into().unwrap()).add_extra(NOTES_KEY.into(), value)
+        };
+        assert!(data("a".repeat(MAX_VALUE_SIZE)).check_values().is_ok());
+        assert!(matches!(
+            data("a".repeat(MAX_VALUE_SIZE + 1)).check_values(),
+            Err(Error::ValueTooLarge { key, len }) if key == NOTES_KEY && len == MAX_VALUE_SIZE + 1
+        ));
+        assert!(matches!(
+            data("a\0b".into()).check_values(),
+            Err(Error::NulInValue { key }) if key == NOTES_KEY
+        ));
+    }
 
     #[test]
     fn extra() {
@@ -68,6 +379,138 @@ mod tests {
         assert_eq!(data.extra["foo"], "bar");
     }
 
+    #[test]
+    fn tags() {
+        let untagged = Data::new("secret".try_into().unwrap());
+        assert!(untagged.tags().is_empty());
+        assert!(!untagged.has_tag("work"));
+
+        let data = untagged.with_tags(["work", " email ", "", "work", "a,b"]);
+        assert_eq!(vec!["work", "email", "a", "b"], data.tags());
+        assert!(data.has_tag("work"));
+        assert!(!data.has_tag("home"));
+        assert!(data.extra.is_empty());
+        assert_eq!("password: secret\ntags: work,email,a,b", data.to_string());
+
+        let decoded = Data::decode(&data.encode()).expect("failed to decode");
+        assert_eq!(data.tags(), decoded.tags());
+
+        let cleared = decoded.with_tags(Vec::<String>::new());
+        assert!(cleared.tags().is_empty());
+    }
+
+    #[test]
+    fn tags_apart_from_extra() {
+        // An imported field named `tags` is just another extra.
+        let data = Data::new("secret".try_into().unwrap())
+            .add_extra(TAGS_KEY.into(), "from a csv".into())
+            .with_tags(["work"]);
+        assert_eq!(vec!["work"], data.tags());
+        assert_eq!("from a csv", data.extra[TAGS_KEY]);
+        let merged = data.merge(&Data::new("new".try_into().unwrap()).with_tags(["home"]));
+        assert_eq!(vec!["work", "home"], merged.tags());
+        assert_eq!("from a csv", merged.extra[TAGS_KEY]);
+
+        // Schema 1 kept tags in the extras; they move to their own field.
+        let v1 = DataV1 {
+            password: "secret".try_into().unwrap(),
+            extra: HashMap::from([
+                (TAGS_KEY.into(), "work,email".into()),
+                (NOTES_KEY.into(), "hi".into()),
+            ]),
+        };
+        let upgraded = Data::decode_version(1, &bitcode::encode(&v1)).expect("failed to upgrade");
+        assert_eq!(vec!["work", "email"], upgraded.tags());
+        assert_eq!(
+            HashMap::from([(NOTES_KEY.into(), "hi".into())]),
+            upgraded.extra
+        );
+
+        // Schema 2 had a shorter password buffer.
+        let v2 = DataV2 {
+            password: "secret".try_into().unwrap(),
+            extra: HashMap::from([(NOTES_KEY.into(), "hi".into())]),
+            tags: vec!["work".into()],
+        };
+        let upgraded = Data::decode_version(2, &bitcode::encode(&v2)).expect("failed to upgrade");
+        assert_eq!("secret", upgraded.password().expose());
+        assert_eq!(vec!["work"], upgraded.tags());
+        assert_eq!("hi", upgraded.extra[NOTES_KEY]);
+    }
+
+    #[test]
+    fn merge_unions_extra() {
+        let existing = Data::new("old".try_into().unwrap())
+            .add_extra("notes".into(), "keep me".into())
+            .add_extra("otp".into(), "old-otp".into());
+        let incoming = Data::new("new".try_into().unwrap())
+            .add_extra("otp".into(), "new-otp".into())
+            .add_extra("notes".into(), "".into())
+            .add_extra("recovery".into(), "codes".into());
+        let merged = existing.merge(&incoming);
+        assert_eq!(merged.password(), incoming.password());
+        assert_eq!(merged.extra.len(), 3);
+        assert_eq!(merged.extra["notes"], "keep me");
+        assert_eq!(merged.extra["otp"], "new-otp");
+        assert_eq!(merged.extra["recovery"], "codes");
+    }
+
+    #[test]
+    fn merge_keeps_empty_when_new() {
+        let existing = Data::new("old".try_into().unwrap());
+        let incoming = Data::new("new".try_into().unwrap()).add_extra("notes".into(), "".into());
+        let merged = existing.merge(&incoming);
+        assert_eq!(merged.extra["notes"], "");
+    }
+
+    #[test]
+    fn debug_redacts() {
+        let data = Data::new("hunter22".try_into().unwrap())
+            .add_extra(NOTES_KEY.into(), "the vault code is 1234".into());
+        let debug = format!("{data:?}");
+        assert!(!debug.contains("hunter22"), "{debug}");
+        assert!(!debug.contains("1234"), "{debug}");
+        assert_eq!(
+            r#"Data { password: Password(***), extra: {"notes": <redacted>}, tags: [] }"#,
+            debug
+        );
+        assert_eq!(
+            r#"Data { password: "hunter22", extra: {"notes": "the vault code is 1234"}, tags: [] }"#,
+            data.debug_full()
+        );
+    }
+
+    #[test]
+    fn display_masked() {
+        let data = Data::new("hunter22".try_into().unwrap())
+            .add_extra("username".into(), "nixpulvis".into())
+            .add_extra("otp".into(), "123456".into());
+        assert_eq!(
+            "password: hunter22\notp: 123456\nusername: nixpulvis",
+            data.to_string()
+        );
+        let masked = data.display_masked();
+        assert_eq!(
+            "password: ••••••••\notp: ••••••••\nusername: nixpulvis",
+            masked
+        );
+        assert!(!masked.contains("hunter22"));
+        assert!(!masked.contains("123456"));
+    }
+
+    #[test]
+    fn display_masked_with() {
+        let data = Data::new("hunter22".try_into().unwrap())
+            .add_extra("username".into(), "nixpulvis".into());
+        assert_eq!(
+            "password: ********\nusername: *********",
+            data.display_masked_with(
+                &["password", "username"],
+                MaskStyle::ASTERISK.preserve_length(true)
+            )
+        );
+    }
+
     #[test]
     fn encode_decode() {
         let data = Data::new("secret".try_into().unwrap());
@@ -84,6 +527,85 @@ mod tests {
         assert_eq!(data, decompressed);
     }
 
+    #[test]
+    fn compression_header() {
+        let small = Data::new("secret".try_into().unwrap());
+        let compressed = small.compress().expect("failed to compress");
+        let [lo, hi] = Data::SCHEMA_VERSION.to_le_bytes();
+        assert_eq!(
+            [HEADER_VERSION, Compression::None as u8, lo, hi],
+            compressed[..4]
+        );
+        assert_eq!(small.encode(), compressed[4..]);
+        assert_eq!(
+            small,
+            Data::decompress(&compressed).expect("failed to decompress")
+        );
+
+        let large = Data::new("secret".try_into().unwrap())
+            .add_extra("notes".into(), "a".repeat(COMPRESSION_THRESHOLD * 4));
+        let compressed = large.compress().expect("failed to compress");
+        let [lo, hi] = Data::SCHEMA_VERSION.to_le_bytes();
+        assert_eq!(
+            [HEADER_VERSION, Compression::Snappy as u8, lo, hi],
+            compressed[..4]
+        );
+        assert!(compressed.len() < large.encode().len());
+        assert_eq!(
+            large,
+            Data::decompress(&compressed).expect("failed to decompress")
+        );
+
+        let forced = small
+            .compress_with(Compression::Snappy)
+            .expect("failed to compress");
+        assert_eq!(
+            small,
+            Data::decompress(&forced).expect("failed to decompress")
+        );
+    }
+
+    #[test]
+    fn decompress_headerless() {
+        // Written before the header, as a bare snappy frame stream, so at
+        // schema 1.
+        let data = Data::new("secret".try_into().unwrap());
+        let encoded = bitcode::encode(&DataV1 {
+            password: "secret".try_into().unwrap(),
+            extra: HashMap::new(),
+        });
+        let mut legacy = Vec::new();
+        std::io::copy(
+            &mut snap::read::FrameEncoder::new(encoded.as_slice()),
+            &mut legacy,
+        )
+        .expect("failed to compress");
+        assert_eq!(
+            data,
+            Data::decompress(&legacy).expect("failed to decompress")
+        );
+
+        // Written with a version 1 header, before schema versions.
+        let v1 = [&[1, Compression::None as u8][..], &encoded].concat();
+        assert_eq!(data, Data::decompress(&v1).expect("failed to decompress"));
+
+        let [lo, hi] = (Data::SCHEMA_VERSION + 1).to_le_bytes();
+        let future = [
+            &[HEADER_VERSION, Compression::None as u8, lo, hi][..],
+            &data.encode(),
+        ]
+        .concat();
+        assert!(matches!(
+            Data::decompress(&future),
+            Err(encrypt::Error::UnknownSchema(v)) if v == Data::SCHEMA_VERSION + 1
+        ));
+        assert!(matches!(
+            Data::decompress(&[HEADER_VERSION + 1, 0, 1, 2]),
+            Err(encrypt::Error::UnknownHeader(_))
+        ));
+        assert!(Data::decompress(&[]).is_err());
+    }
+
     #[test]
     fn encrypt_decrypt() {
         let key = Key::<Lot>::generate();
@@ -104,4 +626,19 @@ mod tests {
         let decrypted = Data::decrypt_with_aad(&encrypted, &key, &aad).expect("failed to decrypt");
         assert_eq!(data, decrypted);
     }
+
+    #[test]
+    fn serde_roundtrip() {
+        let data = Data::new("secret".try_into().unwrap()).add_extra("notes".into(), "hi".into());
+        let json = serde_json::to_value(&data).unwrap();
+        assert_eq!(
+            serde_json::json!({"password": "secret", "extra": {"notes": "hi"}}),
+            json
+        );
+        assert_eq!(data, serde_json::from_value(json).unwrap());
+        let bare: Data = serde_json::from_value(serde_json::json!({"password": "pw"})).unwrap();
+        assert!(bare.extra().is_empty());
+        let long = "x".repeat(crate::password::MAX_LENGTH + 1);
+        assert!(serde_json::from_value::<Data>(serde_json::json!({"password": long})).is_err());
+    }
 }
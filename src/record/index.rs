@@ -3,7 +3,7 @@ use crate::{
     record::{Error, Label, Query, Record},
     uuid::Uuid,
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 /// An in-memory map from `Label` to `Uuid<Record>` for a single lot.
 ///
@@ -22,19 +22,110 @@ use std::collections::BTreeMap;
 #[derive(Default)]
 pub struct RecordIndex {
     entries: BTreeMap<Label, Uuid<Record>>,
+    /// `records.sort_index` for records with a custom position, see
+    /// [`Lot::reorder`](crate::lot::Lot::reorder).
+    order: HashMap<Uuid<Record>, i64>,
+    /// Every label under its [`RecordIndex::name_key`], so
+    /// [`RecordIndex::find_by_name`] folds only the name it's given.
+    names: HashMap<String, BTreeSet<Label>>,
+    /// See [`Lot::case_insensitive_labels`](crate::lot::Lot::case_insensitive_labels).
+    case_insensitive: bool,
 }
 
 impl RecordIndex {
     /// Build an index from a live storgit store's label cache.
     #[cfg(feature = "db")]
-    pub(crate) fn from_store(store: &storgit::Store) -> Result<Self, Error> {
+    pub(crate) fn from_store(
+        store: &storgit::Store,
+        case_insensitive: bool,
+    ) -> Result<Self, Error> {
         let mut entries = BTreeMap::new();
         for (id, label_bytes) in store.list_labels() {
             let uuid = Uuid::<Record>::parse(id.as_str())?;
             let label = Label::decode(&label_bytes)?;
             entries.insert(label, uuid);
         }
-        Ok(RecordIndex { entries })
+        let mut index = RecordIndex {
+            entries,
+            order: HashMap::new(),
+            names: HashMap::new(),
+            case_insensitive,
+        };
+        index.index_names();
+        Ok(index)
+    }
+
+    /// Whether names match ignoring case, see
+    /// [`Lot::case_insensitive_labels`](crate::lot::Lot::case_insensitive_labels).
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    /// Turn case-insensitive names on or off. Turning them on fails with
+    /// [`Error::LabelCollision`] if two records' names already fold to
+    /// the same thing.
+    pub(crate) fn set_case_insensitive(&mut self, case_insensitive: bool) -> Result<(), Error> {
+        if case_insensitive && !self.case_insensitive {
+            let mut folded: HashMap<String, &Uuid<Record>> = HashMap::new();
+            for (label, uuid) in &self.entries {
+                if let Some(existing) = folded.insert(fold(label.name()), uuid)
+                    && existing != uuid
+                {
+                    return Err(Error::LabelCollision {
+                        name: label.name().clone(),
+                        existing: existing.clone(),
+                        attempted: uuid.clone(),
+                    });
+                }
+            }
+        }
+        if case_insensitive != self.case_insensitive {
+            self.case_insensitive = case_insensitive;
+            self.index_names();
+        }
+        Ok(())
+    }
+
+    /// Rebuild [`RecordIndex::names`] from the entries.
+    fn index_names(&mut self) {
+        let mut names: HashMap<String, BTreeSet<Label>> = HashMap::new();
+        for label in self.entries.keys() {
+            names
+                .entry(self.name_key(label.name()))
+                .or_default()
+                .insert(label.clone());
+        }
+        self.names = names;
+    }
+
+    /// Drop every entry for `uuid`.
+    fn unlink(&mut self, uuid: &Uuid<Record>) {
+        let labels: Vec<Label> = self
+            .entries
+            .iter()
+            .filter(|(_, v)| *v == uuid)
+            .map(|(label, _)| label.clone())
+            .collect();
+        for label in labels {
+            self.entries.remove(&label);
+            let key = self.name_key(label.name());
+            if let Some(names) = self.names.get_mut(&key) {
+                names.remove(&label);
+                if names.is_empty() {
+                    self.names.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// `name` as this index compares it: case folded if names are case
+    /// insensitive, as displayed otherwise.
+    pub(crate) fn name_key(&self, name: &super::LabelName) -> String {
+        if self.case_insensitive {
+            fold(name)
+        } else {
+            name.to_string()
+        }
     }
 
     /// Insert or replace an entry for `uuid`. Called by
@@ -44,14 +135,42 @@ impl RecordIndex {
         // Record identity is the label name; clear any prior entry
         // under this uuid so a subsequent `find_by_name` returns only
         // the current revision even when extras changed.
-        self.entries.retain(|_, v| v != &uuid);
+        self.unlink(&uuid);
+        self.names
+            .entry(self.name_key(label.name()))
+            .or_default()
+            .insert(label.clone());
         self.entries.insert(label, uuid);
     }
 
     /// Remove any entry mapped to `uuid`. Called by
     /// [`Record::delete`](crate::record::Record::delete).
     pub(crate) fn remove(&mut self, uuid: &Uuid<Record>) {
-        self.entries.retain(|_, v| v != uuid);
+        self.unlink(uuid);
+        self.order.remove(uuid);
+    }
+
+    /// Replace the custom positions, as loaded from or just written to
+    /// `records.sort_index`.
+    pub(crate) fn set_order(&mut self, order: HashMap<Uuid<Record>, i64>) {
+        self.order = order;
+    }
+
+    /// Every `(label, uuid)` pair in the lot's custom order: records with
+    /// a position first, by position, then the rest by UUID (which, being
+    /// v7, is creation order).
+    pub fn sorted(&self) -> Vec<(&Label, &Uuid<Record>)> {
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by_key(|(_, uuid)| {
+            let position = self.order.get(*uuid).copied().unwrap_or(i64::MAX);
+            (position, uuid.to_uuid())
+        });
+        entries
+    }
+
+    /// True if a record with this uuid is in the index.
+    pub(crate) fn contains_uuid(&self, uuid: &Uuid<Record>) -> bool {
+        self.entries.values().any(|v| v == uuid)
     }
 
     /// Look up the UUID of the record with the given label, if one exists.
@@ -90,11 +209,12 @@ impl RecordIndex {
     /// revisions of the same record. Returns the first match in
     /// [`Label: Ord`] order if the caller has historically stored multiple
     /// rows with the same name.
+    ///
+    /// In a [case-insensitive](RecordIndex::is_case_insensitive) index
+    /// names match after Unicode case folding.
     pub fn find_by_name(&self, name: &super::LabelName) -> Option<&Uuid<Record>> {
-        self.entries
-            .iter()
-            .find(|(label, _)| label.name() == name)
-            .map(|(_, uuid)| uuid)
+        let label = self.names.get(&self.name_key(name))?.first()?;
+        self.entries.get(label)
     }
 
     /// Return an iterator over every label in the index.
@@ -158,13 +278,21 @@ impl IntoIterator for RecordIndex {
     }
 }
 
+/// `name` under Unicode default case folding, so `Email`, `EMAIL` and
+/// `email` are all `email`.
+fn fold(name: &super::LabelName) -> String {
+    caseless::default_case_fold_str(&name.to_string())
+}
+
 #[cfg(test)]
 mod tests {
+    use super::RecordIndex;
     use crate::{
         db::Database,
         lot::Lot,
         record::{Data, Label, Query, Record},
         user::User,
+        uuid::Uuid,
     };
     use std::str::FromStr;
 
@@ -182,6 +310,29 @@ mod tests {
         (db, user, lot)
     }
 
+    #[test]
+    fn find_by_name_follows_changes() {
+        let mut index = RecordIndex::default();
+        index.set_case_insensitive(true).unwrap();
+        let (a, b) = (Uuid::<Record>::now(), Uuid::<Record>::now());
+        index.insert("Email".parse().unwrap(), a.clone());
+        index.insert("bank".parse().unwrap(), b.clone());
+        let name = |s: &str| s.parse::<Label>().unwrap().name().clone();
+        assert_eq!(Some(&a), index.find_by_name(&name("EMAIL")));
+
+        // Renamed, the old name is free.
+        index.insert("mail".parse().unwrap(), a.clone());
+        assert_eq!(None, index.find_by_name(&name("email")));
+        assert_eq!(Some(&a), index.find_by_name(&name("MAIL")));
+
+        index.remove(&b);
+        assert_eq!(None, index.find_by_name(&name("bank")));
+
+        index.set_case_insensitive(false).unwrap();
+        assert_eq!(None, index.find_by_name(&name("MAIL")));
+        assert_eq!(Some(&a), index.find_by_name(&name("mail")));
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn empty_index() {
         let (_db, _user, lot) = setup().await;
@@ -260,7 +411,7 @@ mod tests {
         assert_eq!(2, revisions.len());
         let passwords: Vec<String> = revisions
             .iter()
-            .map(|r| r.data.password().to_string())
+            .map(|r| r.data.password().expose().to_owned())
             .collect();
         assert!(passwords.contains(&"pw1".to_string()));
         assert!(passwords.contains(&"pw2".to_string()));
@@ -287,7 +438,7 @@ mod tests {
             .await
             .unwrap()
             .expect("record exists");
-        assert_eq!("s3cret", record.password().to_string());
+        assert_eq!("s3cret", record.password().expose());
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -1,5 +1,6 @@
 use crate::{encrypt::Stash, lot::Lot};
 use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
     collections::BTreeMap,
@@ -8,6 +9,27 @@ use std::{
     str::FromStr,
 };
 
+/// The [`Label::extra`] key for an explicit username, see
+/// [`Label::username`].
+pub const USERNAME_KEY: &str = "username";
+
+/// The [`Label::extra`] key for the site a record is for, see
+/// [`Label::url`].
+pub const URL_KEY: &str = "url";
+
+/// Which sites [`Label::matches_url`] counts as a record's own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UrlMatch {
+    /// The same host, ignoring a leading `www.`.
+    #[default]
+    Host,
+    /// The same host or any subdomain of it, so a stored `example.com`
+    /// matches `login.example.com` too. There's no public suffix list
+    /// behind this, so a record stored for `co.uk` would match every
+    /// site under it.
+    Subdomains,
+}
+
 /// A record's primary identifier plus optional searchable metadata.
 ///
 /// Equality and hashing are **name-only**: two labels with the same
@@ -15,7 +37,11 @@ use std::{
 /// [`extra`](Label::extra) contents, so a single `(id, domain)` pair
 /// identifies one logical record. [`Ord`] still breaks ties on extras so
 /// [`Label`] remains a well-behaved [`BTreeMap`] key.
-#[derive(Encode, Decode, Debug, Clone)]
+///
+/// Stored with bitcode; the serde impls are only for interchange, and
+/// check extras like [`Label::with_extra`] does.
+#[derive(Encode, Decode, Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "LabelRepr")]
 pub struct Label {
     /// The record's primary, exact identifier (e.g. [`LabelName::Simple`] for
     /// `"github"` or [`LabelName::Domain`] for `"nix@example.com"`). Literal
@@ -47,12 +73,69 @@ pub struct Label {
 }
 
 /// The primary, exact-identifying part of a [`Label`].
-#[derive(Encode, Decode, Debug, Eq, PartialEq, Hash, Clone)]
+///
+/// Serialized with a `type` tag, as `{"type":"simple","name":"github"}`
+/// or `{"type":"domain","id":"nix","domain":"example.com"}`, and
+/// validated like [`LabelName::from_str`] on the way back in.
+#[derive(Encode, Decode, Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Clone)]
+#[serde(into = "LabelNameRepr", try_from = "LabelNameRepr")]
 pub enum LabelName {
     Simple(String),
     Domain { id: String, domain: String },
 }
 
+/// Serde shape of a [`Label`], checked through [`Label::with_extra`].
+#[derive(Deserialize)]
+struct LabelRepr {
+    name: LabelName,
+    #[serde(default)]
+    extra: BTreeMap<String, String>,
+}
+
+impl TryFrom<LabelRepr> for Label {
+    type Error = Error;
+
+    fn try_from(repr: LabelRepr) -> Result<Self, Self::Error> {
+        Label::from(repr.name).with_extra(repr.extra)
+    }
+}
+
+/// Serde shape of a [`LabelName`]. Internal tagging needs struct
+/// variants, hence `Simple { name }`.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LabelNameRepr {
+    Simple { name: String },
+    Domain { id: String, domain: String },
+}
+
+impl From<LabelName> for LabelNameRepr {
+    fn from(name: LabelName) -> Self {
+        match name {
+            LabelName::Simple(name) => LabelNameRepr::Simple { name },
+            LabelName::Domain { id, domain } => LabelNameRepr::Domain { id, domain },
+        }
+    }
+}
+
+impl TryFrom<LabelNameRepr> for LabelName {
+    type Error = Error;
+
+    /// Only names that parse back to themselves are accepted, so e.g. a
+    /// simple name containing `@` is refused.
+    fn try_from(repr: LabelNameRepr) -> Result<Self, Self::Error> {
+        let name = match repr {
+            LabelNameRepr::Simple { name } => LabelName::Simple(name),
+            LabelNameRepr::Domain { id, domain } => LabelName::Domain { id, domain },
+        };
+        if name.to_string().parse::<LabelName>()? == name {
+            Ok(name)
+        } else {
+            Err(Error::InvalidName)
+        }
+    }
+}
+
 impl Stash<Lot> for Label {}
 
 impl From<LabelName> for Label {
@@ -107,7 +190,7 @@ impl Label {
     /// Returns `None` for [`LabelName::Simple`] labels without an explicit
     /// username extra, since there is no principled way to derive one.
     pub fn username(&self) -> Option<&str> {
-        if let Some(v) = self.extra.get("username") {
+        if let Some(v) = self.extra.get(USERNAME_KEY) {
             return Some(v.as_str());
         }
         match &self.name {
@@ -115,6 +198,94 @@ impl Label {
             LabelName::Simple(_) => None,
         }
     }
+
+    /// The [`URL_KEY`] extra.
+    pub fn url(&self) -> Option<&str> {
+        self.extra.get(URL_KEY).map(String::as_str)
+    }
+
+    /// True if this label is for the site at `url`, e.g. the page a
+    /// browser wants to autofill. The site is the [`URL_KEY`] extra,
+    /// falling back to the domain of a [`LabelName::Domain`].
+    ///
+    /// Only hosts are compared, by `policy`, lowercased and without any
+    /// leading `www.`. Scheme, port, path and query are ignored, and
+    /// either side may leave the scheme off, as in `example.com/login`.
+    pub fn matches_url(&self, url: &str, policy: UrlMatch) -> bool {
+        let Some(visited) = url_host(url) else {
+            return false;
+        };
+        let stored = match (self.url(), &self.name) {
+            (Some(url), _) => url_host(url),
+            (None, LabelName::Domain { domain, .. }) => url_host(domain),
+            (None, LabelName::Simple(_)) => None,
+        };
+        let Some(stored) = stored else {
+            return false;
+        };
+        match policy {
+            UrlMatch::Host => visited == stored,
+            UrlMatch::Subdomains => {
+                visited == stored
+                    || visited
+                        .strip_suffix(stored.as_str())
+                        .is_some_and(|sub| sub.ends_with('.'))
+            }
+        }
+    }
+
+    /// Promote a [`LabelName::Simple`] label to a [`LabelName::Domain`]
+    /// one, e.g. once a note turns out to be a login. The simple name
+    /// becomes the domain and the `id_key` extra (usually
+    /// [`USERNAME_KEY`]) becomes the id, moving out of the extras into
+    /// the name. [`Label::into_plain`] with the same key undoes it.
+    ///
+    /// Domain labels are returned as they are. Fails with
+    /// [`Error::MissingId`] if there is no `id_key` extra, or
+    /// [`Error::InvalidId`] if it can't be an id.
+    pub fn into_domain(mut self, id_key: &str) -> Result<Self, Error> {
+        let LabelName::Simple(domain) = &self.name else {
+            return Ok(self);
+        };
+        let id = self.extra.get(id_key).ok_or(Error::MissingId)?;
+        let name = LabelName::Domain {
+            id: id.clone(),
+            domain: domain.clone(),
+        };
+        if name.to_string().parse::<LabelName>().ok().as_ref() != Some(&name) {
+            return Err(Error::InvalidId);
+        }
+        self.extra.remove(id_key);
+        self.name = name;
+        Ok(self)
+    }
+
+    /// Demote a [`LabelName::Domain`] label to a [`LabelName::Simple`]
+    /// one named after the domain, keeping the id in the `id_key` extra.
+    ///
+    /// That's lossless unless `id_key` already holds something else. Then
+    /// the id is joined onto the name instead, as `domain/id`, which
+    /// [`Label::into_domain`] can't split again. Simple labels are
+    /// returned as they are. Fails with [`Error::InvalidName`] if the
+    /// joined name isn't a valid simple name.
+    pub fn into_plain(mut self, id_key: &str) -> Result<Self, Error> {
+        let LabelName::Domain { id, domain } = &self.name else {
+            return Ok(self);
+        };
+        let name = match self.extra.get(id_key) {
+            Some(existing) if existing != id => format!("{domain}/{id}"),
+            _ => {
+                validate_extra(id_key, id)?;
+                self.extra.insert(id_key.to_owned(), id.clone());
+                domain.clone()
+            }
+        };
+        match name.parse::<LabelName>()? {
+            name @ LabelName::Simple(_) => self.name = name,
+            LabelName::Domain { .. } => return Err(Error::InvalidName),
+        }
+        Ok(self)
+    }
 }
 
 impl PartialEq for Label {
@@ -305,6 +476,20 @@ impl fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
+/// The host of `url`, lowercased, without a leading `www.` or trailing
+/// `.`. A URL without a scheme is taken to be `https`.
+fn url_host(url: &str) -> Option<String> {
+    let url = url.trim();
+    let parsed = match url::Url::parse(url) {
+        Err(url::ParseError::RelativeUrlWithoutBase) => url::Url::parse(&format!("https://{url}")),
+        parsed => parsed,
+    }
+    .ok()?;
+    let host = parsed.host_str()?.trim_end_matches('.');
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    (!host.is_empty()).then(|| host.to_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -479,6 +664,111 @@ mod tests {
         assert_eq!(decoded.extra().len(), 2);
     }
 
+    #[test]
+    fn into_domain_and_back() {
+        let note = "github.com"
+            .parse::<Label>()
+            .unwrap()
+            .add_extra(USERNAME_KEY, "nix")
+            .unwrap()
+            .add_extra(URL_KEY, "https://github.com")
+            .unwrap();
+        let login = note
+            .clone()
+            .into_domain(USERNAME_KEY)
+            .expect("failed to convert");
+        assert_eq!("nix@github.com<url=https://github.com>", login.to_string());
+        assert_eq!(Some("nix"), login.username());
+
+        let plain = login
+            .clone()
+            .into_plain(USERNAME_KEY)
+            .expect("failed to convert");
+        assert_eq!(note.to_string(), plain.to_string());
+        assert_eq!(
+            login.to_string(),
+            login.clone().into_domain(USERNAME_KEY).unwrap().to_string()
+        );
+
+        assert!(matches!(
+            "github".parse::<Label>().unwrap().into_domain(USERNAME_KEY),
+            Err(Error::MissingId)
+        ));
+        let separator = "github"
+            .parse::<Label>()
+            .unwrap()
+            .add_extra(USERNAME_KEY, "a::b")
+            .unwrap();
+        assert!(matches!(
+            separator.into_domain(USERNAME_KEY),
+            Err(Error::InvalidId)
+        ));
+    }
+
+    #[test]
+    fn into_plain_lossy() {
+        // The username extra is taken, so the id joins the name.
+        let login = "nix@github.com"
+            .parse::<Label>()
+            .unwrap()
+            .add_extra(USERNAME_KEY, "nixpulvis")
+            .unwrap();
+        let plain = login.into_plain(USERNAME_KEY).expect("failed to convert");
+        assert_eq!(&LabelName::Simple("github.com/nix".into()), plain.name());
+        assert_eq!(Some("nixpulvis"), plain.username());
+
+        // Same id, nothing lost.
+        let login = "nix@github.com"
+            .parse::<Label>()
+            .unwrap()
+            .add_extra(USERNAME_KEY, "nix")
+            .unwrap();
+        let plain = login.into_plain(USERNAME_KEY).expect("failed to convert");
+        assert_eq!("github.com<username=nix>", plain.to_string());
+
+        // An `@` in the id can't survive in a simple name.
+        let login: Label = "a@b@github.com".parse().unwrap();
+        let joined = login
+            .add_extra(USERNAME_KEY, "other")
+            .unwrap()
+            .into_plain(USERNAME_KEY);
+        assert!(matches!(joined, Err(Error::InvalidName)));
+    }
+
+    #[test]
+    fn matches_url() {
+        let login: Label = "nix@example.com".parse().unwrap();
+        for url in [
+            "https://www.example.com/login",
+            "http://example.com:8080/?next=/",
+            "example.com",
+            "HTTPS://EXAMPLE.COM.",
+        ] {
+            assert!(login.matches_url(url, UrlMatch::Host), "{url}");
+        }
+        for url in [
+            "https://login.example.com",
+            "https://example.org",
+            "notexample.com",
+            "",
+        ] {
+            assert!(!login.matches_url(url, UrlMatch::Host), "{url}");
+        }
+        assert!(login.matches_url("https://login.example.com/", UrlMatch::Subdomains));
+        assert!(!login.matches_url("https://notexample.com/", UrlMatch::Subdomains));
+
+        let note: Label = "github".parse().unwrap();
+        assert!(!note.matches_url("https://github.com", UrlMatch::Host));
+        let note = note
+            .add_extra(URL_KEY, "https://www.github.com/login")
+            .unwrap();
+        assert!(note.matches_url("https://github.com/nixpulvis", UrlMatch::Host));
+        // The url extra wins over the domain.
+        let login = login.add_extra(URL_KEY, "example.org").unwrap();
+        assert!(login.matches_url("https://example.org", UrlMatch::Host));
+        assert!(!login.matches_url("https://example.com", UrlMatch::Host));
+    }
+
     #[test]
     fn parse_simple() {
         let label = "label".parse::<Label>().unwrap();
@@ -568,4 +858,66 @@ mod tests {
             Err(Error::InvalidId)
         );
     }
+
+    #[test]
+    fn serde_simple() {
+        let name = LabelName::Simple("github".into());
+        let json = serde_json::to_value(&name).unwrap();
+        assert_eq!(
+            serde_json::json!({"type": "simple", "name": "github"}),
+            json
+        );
+        assert_eq!(name, serde_json::from_value(json).unwrap());
+    }
+
+    #[test]
+    fn serde_domain() {
+        let name = LabelName::Domain {
+            id: "nix".into(),
+            domain: "example.com".into(),
+        };
+        let json = serde_json::to_value(&name).unwrap();
+        assert_eq!(
+            serde_json::json!({"type": "domain", "id": "nix", "domain": "example.com"}),
+            json
+        );
+        assert_eq!(name, serde_json::from_value(json).unwrap());
+    }
+
+    #[test]
+    fn serde_label() {
+        let label = "nix@example.com"
+            .parse::<Label>()
+            .unwrap()
+            .add_extra("url", "https://example.com")
+            .unwrap();
+        let json = serde_json::to_value(&label).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "name": {"type": "domain", "id": "nix", "domain": "example.com"},
+                "extra": {"url": "https://example.com"},
+            }),
+            json
+        );
+        let back: Label = serde_json::from_value(json).unwrap();
+        assert_eq!(label.extra(), back.extra());
+        assert_eq!(label.name(), back.name());
+    }
+
+    #[test]
+    fn serde_rejects_invalid() {
+        for json in [
+            serde_json::json!({"type": "simple", "name": "nix@example.com"}),
+            serde_json::json!({"type": "simple", "name": "a::b"}),
+            serde_json::json!({"type": "domain", "id": "", "domain": "example.com"}),
+            serde_json::json!({"type": "plain", "name": "github"}),
+        ] {
+            assert!(serde_json::from_value::<LabelName>(json).is_err());
+        }
+        let json = serde_json::json!({
+            "name": {"type": "simple", "name": "github"},
+            "extra": {"bad key": "x"},
+        });
+        assert!(serde_json::from_value::<Label>(json).is_err());
+    }
 }
@@ -1,15 +1,35 @@
 #[cfg(feature = "db")]
-use crate::db::{self, Database};
+use crate::db::{self, Database, VaultEvent};
 #[cfg(feature = "db")]
-use crate::encrypt::{Encrypted, Stash};
-use crate::{encrypt, lot::Lot, password::Password, uuid::Uuid};
+use crate::encrypt::{Encrypted, Key, Stash};
+use crate::{encrypt, lot::Lot, password::Password, totp, uuid::Uuid};
 use bitcode::{Decode, Encode};
 #[cfg(feature = "db")]
-use sea_orm::{IntoActiveModel, TransactionTrait, entity::prelude::*, sea_query::OnConflict};
+use sea_orm::{
+    DatabaseTransaction, IntoActiveModel, PaginatorTrait, entity::prelude::*, sea_query::OnConflict,
+};
 use std::fmt;
 #[cfg(feature = "db")]
+use std::num::NonZeroUsize;
+#[cfg(feature = "db")]
 use storgit::layout::submodule::{ModuleChange, Snapshot};
 
+/// Half the machine's cores: enough for [`Record::load_all`] to make
+/// quick work of a big lot while leaving room for a UI thread.
+#[cfg(feature = "db")]
+pub fn default_concurrency() -> NonZeroUsize {
+    std::thread::available_parallelism()
+        .ok()
+        .and_then(|n| NonZeroUsize::new(n.get() / 2))
+        .unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Rows per multi-row INSERT in [`Record::save_many`]. Each row binds one
+/// parameter per `records` column, so this keeps a statement well under
+/// SQLite's 32766 parameter limit.
+#[cfg(feature = "db")]
+const INSERT_CHUNK: usize = 1000;
+
 /// One historical revision of a record, produced by [`Record::history`].
 ///
 /// Each live commit in the record's submodule contributes one entry with
@@ -64,6 +84,18 @@ type SaveBatch = (
     Option<Vec<u8>>,
 );
 
+/// A [`Record::save_many`] batch ready to write, from
+/// [`Record::prepare_many`].
+#[cfg(feature = "db")]
+pub(crate) struct PreparedBatch {
+    active_models: Vec<self::orm::ActiveModel>,
+    /// Whether each record, in order, changed; byte-identical puts
+    /// didn't.
+    changed: Vec<bool>,
+    store_packed: Option<Vec<u8>>,
+    events: Vec<VaultEvent>,
+}
+
 #[derive(Encode, Decode)]
 pub struct Record {
     pub(crate) uuid: Uuid<Self>,
@@ -102,6 +134,18 @@ impl Record {
         &self.data
     }
 
+    /// `{:?}` with every secret in the clear, for tests that really need
+    /// it. Never log this.
+    pub fn debug_full(&self) -> String {
+        format!(
+            "Record {{ uuid: {:?}, lot: {:?}, label: {:?}, data: {} }}",
+            self.uuid,
+            self.lot_uuid,
+            self.label,
+            self.data.debug_full()
+        )
+    }
+
     pub fn label(&self) -> &Label {
         &self.label
     }
@@ -110,6 +154,51 @@ impl Record {
         self.data.password()
     }
 
+    /// The [`USERNAME_KEY`] field, searching the label's extras, then the
+    /// data's, then falling back to a [`LabelName::Domain`]'s `id`.
+    pub fn username(&self) -> Option<&str> {
+        self.label
+            .extra()
+            .get(USERNAME_KEY)
+            .or_else(|| self.data.extra().get(USERNAME_KEY))
+            .map(String::as_str)
+            .or_else(|| self.label.username())
+    }
+
+    /// The [`URL_KEY`] field, from the label's extras or else the data's.
+    pub fn url(&self) -> Option<&str> {
+        self.label
+            .url()
+            .or_else(|| self.data.extra().get(URL_KEY).map(String::as_str))
+    }
+
+    /// See [`Data::notes`].
+    pub fn notes(&self) -> Option<&str> {
+        self.data.notes()
+    }
+
+    /// See [`Data::otp`].
+    pub fn otp(&self) -> Option<&str> {
+        self.data.otp()
+    }
+
+    /// Check that the record's fields are usable, see [`Data::validate`].
+    ///
+    /// Saving only calls this under [`Database::with_record_validation`];
+    /// otherwise importers opt in so that a typo'd or truncated secret is
+    /// caught before it is the only copy.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.data.validate()
+    }
+
+    /// How many times this record's password appears in known breaches,
+    /// see [`hibp::breach_count`](crate::hibp::breach_count). Only a 5
+    /// character prefix of the password's SHA-1 is sent.
+    #[cfg(feature = "hibp")]
+    pub async fn check_breached(&self) -> Result<u64, Error> {
+        Ok(crate::hibp::breach_count(self.password()).await?)
+    }
+
     #[cfg(feature = "db")]
     pub(crate) fn data_aad(record_uuid: &Uuid<Self>, lot_uuid: &Uuid<Lot>) -> Vec<u8> {
         [
@@ -145,8 +234,19 @@ impl Record {
     /// Save this record to the database and return its uuid.
     #[cfg(feature = "db")]
     pub async fn save(&self, db: &Database, lot: &mut Lot) -> Result<Uuid<Self>, Error> {
+        db.check_writable()?;
+        if lot.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
         lot.index()
             .check_name_owner(self.label.name(), &self.uuid)?;
+        self.data.check_values()?;
+        if db.validates_records() {
+            self.validate()?;
+        }
+        Record::check_quota(db, lot, std::slice::from_ref(self))?;
+        #[cfg(feature = "strength")]
+        crate::encrypt::warn_if_weak(self.password(), "record");
 
         // Integrity check: if a row already exists for this uuid
         // under a different lot, the INSERT ... ON CONFLICT below
@@ -209,6 +309,7 @@ impl Record {
             uuid: self.uuid.to_string(),
             lot_uuid: self.lot_uuid.to_string(),
             module: module_packed,
+            sort_index: None,
         };
         let active = model.into_active_model();
         let on_conflict = OnConflict::column(self::orm::Column::Uuid)
@@ -222,27 +323,79 @@ impl Record {
             .as_ref()
             .map(|bytes| lot.encrypt_store(bytes))
             .transpose()?;
-        let txn = db.connection().begin().await?;
-        self::orm::Entity::insert(active)
-            .on_conflict(on_conflict)
-            .exec_with_returning(&txn)
-            .await?;
-        if let Some(store_packed) = store_packed {
-            crate::lot::orm::Entity::update(crate::lot::orm::ActiveModel {
-                uuid: sea_orm::ActiveValue::Unchanged(self.lot_uuid.to_string()),
-                store: sea_orm::ActiveValue::Set(store_packed),
-            })
-            .exec(&txn)
-            .await?;
-        }
-        txn.commit().await?;
+        let events = [Record::save_event(lot, &self.uuid)];
+        db.transaction(async |txn| -> Result<(), Error> {
+            self::orm::Entity::insert(active.clone())
+                .on_conflict(on_conflict.clone())
+                .exec_with_returning(txn)
+                .await?;
+            Record::check_quota_in(db, txn, &self.lot_uuid.to_string()).await?;
+            if let Some(store_packed) = &store_packed {
+                crate::lot::orm::Entity::update(crate::lot::orm::ActiveModel {
+                    uuid: sea_orm::ActiveValue::Unchanged(self.lot_uuid.to_string()),
+                    store: sea_orm::ActiveValue::Set(store_packed.clone()),
+                    ..Default::default()
+                })
+                .exec(txn)
+                .await?;
+            }
+            Ok(Database::log(txn, &events).await?)
+        })
+        .await?;
 
         lot.index_mut()
             .insert(self.label.clone(), self.uuid.clone());
+        db.notify(events);
 
         Ok(self.uuid.clone())
     }
 
+    /// Save this record unless `lot` already has one by the same label
+    /// name, returning the uuid saved or found and whether it was saved.
+    ///
+    /// Unlike [`Record::save`] with a fresh uuid, which fails with
+    /// [`Error::LabelCollision`], running this twice stores one record.
+    #[cfg(feature = "db")]
+    pub async fn insert_or_get(
+        &self,
+        db: &Database,
+        lot: &mut Lot,
+    ) -> Result<(Uuid<Self>, bool), Error> {
+        db.check_writable()?;
+        if let Some(existing) = lot.index().find_by_name(self.label.name()) {
+            return Ok((existing.clone(), false));
+        }
+        Ok((self.save(db, lot).await?, true))
+    }
+
+    /// Save `records` into `lot` one at a time with [`Record::save`],
+    /// carrying on past any that fail.
+    ///
+    /// Where [`Record::save_many`] saves all or nothing, this saves what
+    /// it can: if any record fails, [`Error::PartialSave`] lists which
+    /// ones were saved and why the rest weren't.
+    #[cfg(feature = "db")]
+    pub async fn save_each(
+        db: &Database,
+        lot: &mut Lot,
+        records: &[Record],
+    ) -> Result<Vec<Uuid<Self>>, Error> {
+        db.check_writable()?;
+        let mut saved = Vec::with_capacity(records.len());
+        let mut failed = Vec::new();
+        for record in records {
+            match record.save(db, lot).await {
+                Ok(uuid) => saved.push(uuid),
+                Err(e) => failed.push((record.uuid.clone(), e)),
+            }
+        }
+        if failed.is_empty() {
+            Ok(saved)
+        } else {
+            Err(Error::PartialSave { saved, failed })
+        }
+    }
+
     /// Save many records against a single lot with one storgit snapshot and
     /// one database transaction. Much faster than looping [`Record::save`]
     /// (which reopens the store and round-trips the DB per record), which
@@ -262,11 +415,38 @@ impl Record {
         records: &[Record],
         mut on_progress: impl FnMut(SaveProgress<'_>),
     ) -> Result<Vec<Uuid<Self>>, Error> {
-        if records.is_empty() {
+        let Some(batch) = Record::prepare_many(db, lot, records, &mut on_progress).await? else {
             return Ok(Vec::new());
+        };
+        let lot_uuid = lot.uuid().to_string();
+        db.transaction(async |txn| -> Result<(), Error> {
+            Record::write_many(db, txn, &lot_uuid, &batch).await
+        })
+        .await?;
+        Ok(Record::finish_many(db, lot, records, batch, on_progress))
+    }
+
+    /// The checks, storgit work and encryption for [`Record::save_many`],
+    /// up to the point of writing to the database. `None` for an empty
+    /// batch. The batch is written by [`Record::write_many`], inside
+    /// whatever transaction the caller likes, then
+    /// [`Record::finish_many`] once it commits.
+    #[cfg(feature = "db")]
+    pub(crate) async fn prepare_many(
+        db: &Database,
+        lot: &mut Lot,
+        records: &[Record],
+        on_progress: &mut impl FnMut(SaveProgress<'_>),
+    ) -> Result<Option<PreparedBatch>, Error> {
+        db.check_writable()?;
+        if lot.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+        if records.is_empty() {
+            return Ok(None);
         }
 
-        let mut batch_names: std::collections::HashMap<&LabelName, &Uuid<Self>> =
+        let mut batch_names: std::collections::HashMap<String, &Uuid<Self>> =
             std::collections::HashMap::with_capacity(records.len());
         for record in records {
             if record.lot_uuid != *lot.uuid() {
@@ -277,7 +457,8 @@ impl Record {
             }
             lot.index()
                 .check_name_owner(record.label.name(), &record.uuid)?;
-            if let Some(prior) = batch_names.insert(record.label.name(), &record.uuid)
+            let key = lot.index().name_key(record.label.name());
+            if let Some(prior) = batch_names.insert(key, &record.uuid)
                 && prior != &record.uuid
             {
                 return Err(Error::LabelCollision {
@@ -288,6 +469,14 @@ impl Record {
             }
         }
 
+        for record in records {
+            record.data.check_values()?;
+            if db.validates_records() {
+                record.validate()?;
+            }
+        }
+        Record::check_quota(db, lot, records)?;
+
         // Integrity check: no row may claim any of these uuids under
         // a different lot. Scanning up front is cheaper than failing
         // part-way through the storgit work.
@@ -365,6 +554,7 @@ impl Record {
                             uuid: p.uuid.to_string(),
                             lot_uuid: lot.uuid().to_string(),
                             module: encrypted.pack(),
+                            sort_index: None,
                         }
                         .into_active_model(),
                     );
@@ -379,25 +569,79 @@ impl Record {
             .map(|bytes| lot.encrypt_store(bytes))
             .transpose()?;
 
+        // Only records whose put marked the module dirty are saved; a
+        // record repeated in the batch is an update after its first.
+        let mut seen = std::collections::HashSet::with_capacity(changed_ids.len());
+        let events: Vec<VaultEvent> = records
+            .iter()
+            .zip(&prepared)
+            .filter(|(_, p)| changed_ids.contains(&p.storgit_id))
+            .map(|(record, _)| {
+                let first = seen.insert(&record.uuid);
+                match Record::save_event(lot, &record.uuid) {
+                    VaultEvent::RecordInserted { lot, record } if !first => {
+                        VaultEvent::RecordUpdated { lot, record }
+                    }
+                    event => event,
+                }
+            })
+            .collect();
+        let changed = prepared
+            .iter()
+            .map(|p| changed_ids.contains(&p.storgit_id))
+            .collect();
+        Ok(Some(PreparedBatch {
+            active_models,
+            changed,
+            store_packed,
+            events,
+        }))
+    }
+
+    /// Write a [`Record::prepare_many`] batch for the lot `lot_uuid` on
+    /// `txn`.
+    #[cfg(feature = "db")]
+    pub(crate) async fn write_many(
+        db: &Database,
+        txn: &DatabaseTransaction,
+        lot_uuid: &str,
+        batch: &PreparedBatch,
+    ) -> Result<(), Error> {
         let on_conflict = OnConflict::column(self::orm::Column::Uuid)
             .update_columns([self::orm::Column::LotUuid, self::orm::Column::Module])
             .to_owned();
-        let txn = db.connection().begin().await?;
-        if !active_models.is_empty() {
-            self::orm::Entity::insert_many(active_models)
-                .on_conflict(on_conflict)
-                .exec(&txn)
+        // Multi-row INSERTs, chunked to stay under SQLite's bound
+        // parameter limit on very large imports.
+        for chunk in batch.active_models.chunks(INSERT_CHUNK) {
+            self::orm::Entity::insert_many(chunk.iter().cloned())
+                .on_conflict(on_conflict.clone())
+                .exec(txn)
                 .await?;
         }
-        if let Some(store_packed) = store_packed {
+        Record::check_quota_in(db, txn, lot_uuid).await?;
+        if let Some(store_packed) = &batch.store_packed {
             crate::lot::orm::Entity::update(crate::lot::orm::ActiveModel {
-                uuid: sea_orm::ActiveValue::Unchanged(lot.uuid().to_string()),
-                store: sea_orm::ActiveValue::Set(store_packed),
+                uuid: sea_orm::ActiveValue::Unchanged(lot_uuid.to_owned()),
+                store: sea_orm::ActiveValue::Set(store_packed.clone()),
+                ..Default::default()
             })
-            .exec(&txn)
+            .exec(txn)
             .await?;
         }
-        txn.commit().await?;
+        Ok(Database::log(txn, &batch.events).await?)
+    }
+
+    /// Bring `lot`'s index up to date once a [`Record::write_many`] of
+    /// `records` has committed, and announce it. Returns the uuids in the
+    /// same order as `records`.
+    #[cfg(feature = "db")]
+    pub(crate) fn finish_many(
+        db: &Database,
+        lot: &mut Lot,
+        records: &[Record],
+        batch: PreparedBatch,
+        mut on_progress: impl FnMut(SaveProgress<'_>),
+    ) -> Vec<Uuid<Self>> {
         on_progress(SaveProgress::SaveRecord);
 
         // Only records whose put marked the module dirty need an
@@ -405,18 +649,93 @@ impl Record {
         // (label, uuid) in the index from a prior save. Matches the
         // single `Record::save` path, which skips the insert on the
         // byte-identical early return.
-        for (record, p) in records.iter().zip(&prepared) {
-            if changed_ids.contains(&p.storgit_id) {
+        for (record, &changed) in records.iter().zip(&batch.changed) {
+            if changed {
                 lot.index_mut()
                     .insert(record.label.clone(), record.uuid.clone());
             }
         }
+        db.notify(batch.events);
+
+        // One estimate per distinct password and one warning for the
+        // batch, rather than a zxcvbn run and log line per record.
+        #[cfg(feature = "strength")]
+        {
+            let passwords: std::collections::HashSet<&str> = records
+                .iter()
+                .zip(&batch.changed)
+                .filter(|(_, changed)| **changed)
+                .map(|(record, _)| record.password().expose())
+                .collect();
+            let weak = passwords
+                .into_iter()
+                .filter(|password| crate::encrypt::estimate_strength(password).is_weak())
+                .count();
+            if weak > 0 {
+                tracing::warn!("{weak} weak record passwords saved");
+            }
+        }
 
-        if new_parent.is_some() {
+        if batch.store_packed.is_some() {
             on_progress(SaveProgress::SaveLot);
         }
 
-        Ok(records.iter().map(|r| r.uuid.clone()).collect())
+        records.iter().map(|r| r.uuid.clone()).collect()
+    }
+
+    /// The event for saving `uuid` into `lot`, before the index has it.
+    #[cfg(feature = "db")]
+    fn save_event(lot: &Lot, uuid: &Uuid<Self>) -> VaultEvent {
+        let existed = lot.index().contains_uuid(uuid);
+        let (lot, record) = (lot.uuid().clone(), uuid.clone());
+        if existed {
+            VaultEvent::RecordUpdated { lot, record }
+        } else {
+            VaultEvent::RecordInserted { lot, record }
+        }
+    }
+
+    /// Reject if saving `records` would add more new records to `lot`
+    /// than [`Database::record_quota`] allows, going by its index. This
+    /// fails fast before any storgit work; [`Record::check_quota_in`] is
+    /// the check that holds against other writers.
+    #[cfg(feature = "db")]
+    fn check_quota(db: &Database, lot: &Lot, records: &[Record]) -> Result<(), Error> {
+        let Some(quota) = db.record_quota() else {
+            return Ok(());
+        };
+        let new: std::collections::HashSet<&Uuid<Self>> = records
+            .iter()
+            .map(|r| &r.uuid)
+            .filter(|uuid| !lot.index().contains_uuid(uuid))
+            .collect();
+        if lot.index().len() + new.len() > quota {
+            return Err(Error::QuotaExceeded { quota });
+        }
+        Ok(())
+    }
+
+    /// Reject if the `records` rows for `lot_uuid` on `conn` number more
+    /// than [`Database::record_quota`] allows. Run inside the save
+    /// transaction after the inserts, so a handle with a stale index, or
+    /// two writers each under the quota, can't commit past it.
+    #[cfg(feature = "db")]
+    async fn check_quota_in(
+        db: &Database,
+        conn: &impl ConnectionTrait,
+        lot_uuid: &str,
+    ) -> Result<(), Error> {
+        let Some(quota) = db.record_quota() else {
+            return Ok(());
+        };
+        let count = self::orm::Entity::find()
+            .filter(self::orm::Column::LotUuid.eq(lot_uuid))
+            .count(conn)
+            .await?;
+        if count > quota as u64 {
+            return Err(Error::QuotaExceeded { quota });
+        }
+        Ok(())
     }
 
     /// Delete this record from the database.
@@ -426,6 +745,10 @@ impl Record {
     /// parent is refreshed.
     #[cfg(feature = "db")]
     pub async fn delete(&self, db: &Database, lot: &mut Lot) -> Result<(), Error> {
+        db.check_writable()?;
+        if lot.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
         let id = Record::storgit_id(&self.uuid);
 
         // Integrity check before we touch storgit: if the row
@@ -459,25 +782,60 @@ impl Record {
             .as_ref()
             .map(|bytes| lot.encrypt_store(bytes))
             .transpose()?;
-        let txn = db.connection().begin().await?;
-        self::orm::Entity::delete_by_id(self.uuid.to_string())
-            .exec(&txn)
-            .await?;
-        if let Some(store_packed) = store_packed {
-            crate::lot::orm::Entity::update(crate::lot::orm::ActiveModel {
-                uuid: sea_orm::ActiveValue::Unchanged(self.lot_uuid.to_string()),
-                store: sea_orm::ActiveValue::Set(store_packed),
-            })
-            .exec(&txn)
-            .await?;
-        }
-        txn.commit().await?;
+        let events = [VaultEvent::RecordRemoved {
+            lot: self.lot_uuid.clone(),
+            record: self.uuid.clone(),
+        }];
+        db.transaction(async |txn| -> Result<(), Error> {
+            self::orm::Entity::delete_by_id(self.uuid.to_string())
+                .exec(txn)
+                .await?;
+            if let Some(store_packed) = &store_packed {
+                crate::lot::orm::Entity::update(crate::lot::orm::ActiveModel {
+                    uuid: sea_orm::ActiveValue::Unchanged(self.lot_uuid.to_string()),
+                    store: sea_orm::ActiveValue::Set(store_packed.clone()),
+                    ..Default::default()
+                })
+                .exec(txn)
+                .await?;
+            }
+            Ok(Database::log(txn, &events).await?)
+        })
+        .await?;
 
         lot.index_mut().remove(&self.uuid);
+        db.notify(events);
 
         Ok(())
     }
 
+    /// Copy this record into `dest` under a new uuid, leaving the original
+    /// in `source` as it is.
+    ///
+    /// The stored revision is decrypted under `source`'s key and saved
+    /// under `dest`'s, so the copy starts its own history and later edits
+    /// to either record don't touch the other.
+    #[cfg(feature = "db")]
+    pub async fn clone_to_lot(
+        &self,
+        db: &Database,
+        source: &Lot,
+        dest: &mut Lot,
+    ) -> Result<Uuid<Self>, Error> {
+        if &self.lot_uuid != source.uuid() {
+            return Err(Error::LotMismatch {
+                expected: source.uuid().clone(),
+                actual: self.lot_uuid.clone(),
+            });
+        }
+        let stored = Record::show(db, source, &self.uuid)
+            .await?
+            .ok_or_else(|| Error::Storgit(storgit::Error::Other("entry missing".into())))?;
+        Record::new(dest, stored.label, stored.data)
+            .save(db, dest)
+            .await
+    }
+
     /// Load a single record by UUID, decrypting both its label and its
     /// password-bearing data.
     ///
@@ -489,8 +847,12 @@ impl Record {
     pub async fn show(db: &Database, lot: &Lot, uuid: &Uuid<Self>) -> Result<Option<Self>, Error> {
         // Lot check: return None rather than decode a record that
         // doesn't belong to this lot (don't leak cross-lot state).
-        let Some(model) = self::orm::Entity::find_by_id(uuid.to_string())
-            .one(db.connection())
+        let Some(model) = db
+            .retry(async || {
+                self::orm::Entity::find_by_id(uuid.to_string())
+                    .one(db.connection())
+                    .await
+            })
             .await?
         else {
             return Ok(None);
@@ -517,7 +879,7 @@ impl Record {
             .ok_or_else(|| Error::Storgit(storgit::Error::Other("entry has no data".into())))?;
 
         let label = Label::decode(&label_bytes)?;
-        let data_ciphertext = Encrypted::unpack(&data_bytes);
+        let data_ciphertext = Encrypted::unpack_owned(data_bytes)?;
         let data = Data::decrypt_with_aad(
             &data_ciphertext,
             lot.key(),
@@ -532,6 +894,95 @@ impl Record {
         }))
     }
 
+    /// Decrypt every record in `lot`, in [`RecordIndex`] order.
+    ///
+    /// Entries are read from the store one at a time, then decrypted on
+    /// at most `concurrency` threads, so a huge lot can be opened without
+    /// taking every core (see [`default_concurrency`]). The result doesn't
+    /// depend on `concurrency`.
+    ///
+    /// Like [`Record::show`] this exposes every secret in the lot; use it
+    /// for whole-lot work like exports and audits, not listing.
+    #[cfg(feature = "db")]
+    pub async fn load_all(lot: &Lot, concurrency: NonZeroUsize) -> Result<Vec<Self>, Error> {
+        let lot_uuid = lot.uuid().clone();
+        tokio::task::block_in_place(|| {
+            let mut entries = Vec::with_capacity(lot.index().len());
+            for (_, uuid) in lot.index().iter() {
+                let entry = lot
+                    .store()
+                    .get(&Record::storgit_id(uuid))
+                    .map_err(Error::Storgit)?
+                    .ok_or_else(|| Error::Storgit(storgit::Error::Other("entry missing".into())))?;
+                let (Some(label), Some(data)) = (entry.label, entry.data) else {
+                    return Err(Error::Storgit(storgit::Error::Other(
+                        "entry has no label or data".into(),
+                    )));
+                };
+                entries.push((uuid.clone(), label, data));
+            }
+
+            // Only the key and uuid cross threads, the store stays here.
+            let key = lot.key();
+            let decrypt = |(uuid, label, data): (Uuid<Self>, Vec<u8>, Vec<u8>)| {
+                Record::decrypt_entry(key, &lot_uuid, uuid, &label, data)
+            };
+            let decrypt = &decrypt;
+
+            let chunk_size = entries.len().div_ceil(concurrency.get()).max(1);
+            if entries.len() <= chunk_size {
+                return entries.into_iter().map(decrypt).collect();
+            }
+            let mut entries = entries.into_iter();
+            let chunks: Vec<Vec<_>> = std::iter::from_fn(|| {
+                let chunk: Vec<_> = entries.by_ref().take(chunk_size).collect();
+                (!chunk.is_empty()).then_some(chunk)
+            })
+            .collect();
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = chunks
+                    .into_iter()
+                    .map(|chunk| {
+                        scope.spawn(move || {
+                            chunk
+                                .into_iter()
+                                .map(decrypt)
+                                .collect::<Result<Vec<_>, _>>()
+                        })
+                    })
+                    .collect();
+                let mut records = Vec::with_capacity(lot.index().len());
+                for handle in handles {
+                    records.extend(handle.join().expect("decrypt thread panicked")?);
+                }
+                Ok(records)
+            })
+        })
+    }
+
+    /// Rebuild a record from its store entry's label and data bytes.
+    #[cfg(feature = "db")]
+    fn decrypt_entry(
+        key: &Key<Lot>,
+        lot_uuid: &Uuid<Lot>,
+        uuid: Uuid<Self>,
+        label: &[u8],
+        data: Vec<u8>,
+    ) -> Result<Self, Error> {
+        let label = Label::decode(label)?;
+        let data = Data::decrypt_with_aad(
+            &Encrypted::unpack_owned(data)?,
+            key,
+            &Record::data_aad(&uuid, lot_uuid),
+        )?;
+        Ok(Record {
+            uuid,
+            lot_uuid: lot_uuid.clone(),
+            label,
+            data,
+        })
+    }
+
     /// Walk every historical revision of the record identified by `uuid`,
     /// newest commit first. Each live commit is decrypted into a
     /// [`Revision`]; tombstone commits (written by [`Record::delete`]) are
@@ -543,8 +994,12 @@ impl Record {
         lot: &Lot,
         uuid: &Uuid<Self>,
     ) -> Result<Option<Vec<Revision>>, Error> {
-        let Some(model) = self::orm::Entity::find_by_id(uuid.to_string())
-            .one(db.connection())
+        let Some(model) = db
+            .retry(async || {
+                self::orm::Entity::find_by_id(uuid.to_string())
+                    .one(db.connection())
+                    .await
+            })
             .await?
         else {
             return Ok(None);
@@ -566,8 +1021,11 @@ impl Record {
                 continue;
             };
             let label = Label::decode(&label_bytes)?;
-            let data =
-                Data::decrypt_with_aad(&Encrypted::unpack(&data_bytes), lot.key(), &data_aad)?;
+            let data = Data::decrypt_with_aad(
+                &Encrypted::unpack_owned(data_bytes)?,
+                lot.key(),
+                &data_aad,
+            )?;
             revisions.push(Revision {
                 time: entry.time,
                 commit: entry.commit,
@@ -595,6 +1053,8 @@ impl fmt::Display for Record {
     }
 }
 
+/// The label and uuids, with [`Data`]'s values redacted. See
+/// [`Record::debug_full`].
 impl fmt::Debug for Record {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Record")
@@ -606,6 +1066,18 @@ impl fmt::Debug for Record {
     }
 }
 
+/// Just the uuids, for the likes of [`Database::audit_log`]. Never the
+/// label or [`Data`].
+impl serde::Serialize for Record {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut record = serializer.serialize_struct("Record", 2)?;
+        record.serialize_field("uuid", &self.uuid)?;
+        record.serialize_field("lot", &self.lot_uuid)?;
+        record.end()
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     #[cfg(feature = "db")]
@@ -625,12 +1097,119 @@ pub enum Error {
         existing: Uuid<Record>,
         attempted: Uuid<Record>,
     },
+    /// Saving would take the lot past [`Database::with_record_quota`].
+    #[cfg(feature = "db")]
+    QuotaExceeded {
+        quota: usize,
+    },
+    /// The lot is [`Lot::is_read_only`] for the user it was loaded for.
+    #[cfg(feature = "db")]
+    ReadOnly,
+    /// Some records passed to [`Record::save_each`] couldn't be saved;
+    /// the ones in `saved` were.
+    #[cfg(feature = "db")]
+    PartialSave {
+        saved: Vec<Uuid<Record>>,
+        failed: Vec<(Uuid<Record>, Error)>,
+    },
     Uuid(crate::uuid::Error),
     #[cfg(feature = "db")]
     Database(db::Error),
     Encryption(encrypt::Error),
+    /// The record's `otp` field doesn't parse, see [`Record::validate`].
+    Otp(totp::Error),
     #[cfg(feature = "db")]
     Storgit(storgit::Error),
+    #[cfg(feature = "hibp")]
+    Breach(crate::hibp::Error),
+    /// A [`Data::extra`] value longer than [`MAX_VALUE_SIZE`] bytes.
+    ValueTooLarge {
+        key: String,
+        len: usize,
+    },
+    /// A [`Data::extra`] value with a NUL byte in it.
+    NulInValue {
+        key: String,
+    },
+    /// The `otp` URI doesn't fit in a QR code, see [`Record::otp_qr`].
+    #[cfg(feature = "qr")]
+    Qr(qrcode::types::QrError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "db")]
+            Error::LotMismatch { expected, actual } => {
+                write!(f, "record belongs to lot {actual}, not {expected}")
+            }
+            #[cfg(feature = "db")]
+            Error::LabelCollision { name, existing, .. } => {
+                write!(f, "'{name}' is already record {existing}")
+            }
+            #[cfg(feature = "db")]
+            Error::QuotaExceeded { quota } => write!(f, "lot is at its quota of {quota} records"),
+            #[cfg(feature = "db")]
+            Error::ReadOnly => write!(f, "lot is read-only"),
+            #[cfg(feature = "db")]
+            Error::PartialSave { saved, failed } => write!(
+                f,
+                "{} records saved, {} couldn't be",
+                saved.len(),
+                failed.len()
+            ),
+            Error::Uuid(e) => write!(f, "uuid: {e}"),
+            #[cfg(feature = "db")]
+            Error::Database(e) => write!(f, "database: {e}"),
+            Error::Encryption(e) => write!(f, "encryption: {e}"),
+            Error::Otp(e) => write!(f, "otp: {e}"),
+            Error::ValueTooLarge { key, len } => {
+                write!(
+                    f,
+                    "'{key}' is {len} bytes, over the {MAX_VALUE_SIZE} byte limit"
+                )
+            }
+            Error::NulInValue { key } => write!(f, "'{key}' contains a NUL byte"),
+            #[cfg(feature = "db")]
+            Error::Storgit(e) => write!(f, "storgit: {e}"),
+            #[cfg(feature = "hibp")]
+            Error::Breach(e) => write!(f, "{e}"),
+            #[cfg(feature = "qr")]
+            Error::Qr(e) => write!(f, "qr: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Uuid(e) => Some(e),
+            #[cfg(feature = "db")]
+            Error::Database(e) => Some(e),
+            Error::Encryption(e) => Some(e),
+            Error::Otp(e) => Some(e),
+            #[cfg(feature = "db")]
+            Error::Storgit(e) => Some(e),
+            #[cfg(feature = "hibp")]
+            Error::Breach(e) => Some(e),
+            #[cfg(feature = "qr")]
+            Error::Qr(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "hibp")]
+impl From<crate::hibp::Error> for Error {
+    fn from(err: crate::hibp::Error) -> Self {
+        Error::Breach(err)
+    }
+}
+
+impl From<totp::Error> for Error {
+    fn from(err: totp::Error) -> Self {
+        Error::Otp(err)
+    }
 }
 
 impl From<encrypt::Error> for Error {
@@ -659,11 +1238,14 @@ impl From<sea_orm::DbErr> for Error {
     }
 }
 
+mod copy;
+pub use self::copy::CopyField;
+
 mod data;
-pub use self::data::Data;
+pub use self::data::{Data, MAX_VALUE_SIZE, NOTES_KEY, PASSWORD_KEY, SECRET_KEYS, TAGS_KEY};
 
 pub(crate) mod label;
-pub use self::label::{Label, LabelName};
+pub use self::label::{Label, LabelName, URL_KEY, USERNAME_KEY, UrlMatch};
 
 #[cfg(feature = "db")]
 mod index;
@@ -673,6 +1255,9 @@ pub use self::index::RecordIndex;
 pub mod query;
 pub use self::query::{Path, Query};
 
+#[cfg(feature = "qr")]
+mod qr;
+
 #[cfg(all(feature = "db", feature = "orm"))]
 pub mod orm;
 #[cfg(all(feature = "db", not(feature = "orm")))]
@@ -696,7 +1281,215 @@ mod tests {
         assert_eq!(lot.uuid(), &record.lot_uuid);
         assert_eq!(36, record.uuid.to_string().len());
         assert_eq!(record.label(), &"foo".parse::<Label>().unwrap());
-        assert_eq!(record.password().to_string(), "bar");
+        assert_eq!(record.password().expose(), "bar");
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn value_too_large() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (_, mut lot) = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        let record = |size: usize| {
+            Record::new(
+                &lot,
+                "big".parse::<Label>().unwrap(),
+                Data::new("secret".try_into().unwrap())
+                    .add_extra(NOTES_KEY.into(), "a".repeat(size)),
+            )
+        };
+
+        let over = record(MAX_VALUE_SIZE + 1);
+        assert!(matches!(
+            over.save(&db, &mut lot).await,
+            Err(Error::ValueTooLarge { .. })
+        ));
+        assert!(matches!(
+            Record::save_many(&db, &mut lot, std::slice::from_ref(&over), |_| {}).await,
+            Err(Error::ValueTooLarge { .. })
+        ));
+        assert!(lot.index().is_empty());
+        record(MAX_VALUE_SIZE)
+            .save(&db, &mut lot)
+            .await
+            .expect("failed to save at the limit");
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn insert_or_get() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (_, mut lot) = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        let record = |password: &str| {
+            Record::new(
+                &lot,
+                "github".parse::<Label>().unwrap(),
+                Data::new(password.try_into().unwrap()),
+            )
+        };
+
+        let first = record("first");
+        let (uuid, inserted) = first
+            .insert_or_get(&db, &mut lot)
+            .await
+            .expect("failed to insert");
+        assert!(inserted);
+        assert_eq!(first.uuid, uuid);
+
+        let (uuid, inserted) = record("second")
+            .insert_or_get(&db, &mut lot)
+            .await
+            .expect("failed to get");
+        assert!(!inserted);
+        assert_eq!(first.uuid, uuid);
+        assert_eq!(1, lot.index().len());
+        let stored = Record::show(&db, &lot, &uuid)
+            .await
+            .expect("failed to show")
+            .expect("missing record");
+        assert_eq!("first", stored.password().expose());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn save_each() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (_, mut lot) = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        let record = |name: &str, notes: String| {
+            Record::new(
+                &lot,
+                name.parse::<Label>().unwrap(),
+                Data::new("secret".try_into().unwrap()).add_extra(NOTES_KEY.into(), notes),
+            )
+        };
+        let records = [
+            record("a", "fine".into()),
+            record("b", "a".repeat(MAX_VALUE_SIZE + 1)),
+            record("c", "fine".into()),
+        ];
+
+        match Record::save_each(&db, &mut lot, &records).await {
+            Err(Error::PartialSave { saved, failed }) => {
+                assert_eq!(
+                    vec![records[0].uuid.clone(), records[2].uuid.clone()],
+                    saved
+                );
+                assert_eq!(1, failed.len());
+                assert_eq!(records[1].uuid, failed[0].0);
+                assert!(matches!(failed[0].1, Error::ValueTooLarge { .. }));
+            }
+            other => panic!("expected a partial save, got {other:?}"),
+        }
+        assert_eq!(2, lot.index().len());
+        for saved in [&records[0], &records[2]] {
+            assert!(
+                Record::show(&db, &lot, saved.uuid())
+                    .await
+                    .expect("failed to show")
+                    .is_some()
+            );
+        }
+
+        let uuids = Record::save_each(&db, &mut lot, &records[..1])
+            .await
+            .expect("failed to save");
+        assert_eq!(vec![records[0].uuid.clone()], uuids);
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn quota() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database")
+            .with_record_quota(3);
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("lot a");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let record = |lot: &Lot, name: &str| {
+            Record::new(
+                lot,
+                name.parse::<Label>().unwrap(),
+                Data::new("secret".try_into().unwrap()),
+            )
+        };
+
+        let batch = [record(&lot, "a"), record(&lot, "b")];
+        Record::save_many(&db, &mut lot, &batch, |_| {})
+            .await
+            .expect("failed to save under quota");
+        let c = record(&lot, "c");
+        c.save(&db, &mut lot)
+            .await
+            .expect("failed to save at quota");
+
+        assert!(matches!(
+            record(&lot, "d").save(&db, &mut lot).await,
+            Err(Error::QuotaExceeded { quota: 3 })
+        ));
+        let over = [record(&lot, "d")];
+        assert!(matches!(
+            Record::save_many(&db, &mut lot, &over, |_| {}).await,
+            Err(Error::QuotaExceeded { quota: 3 })
+        ));
+        // Updating an existing record doesn't count against the quota.
+        Record::with_uuid(
+            c.uuid().clone(),
+            &lot,
+            "c".parse::<Label>().unwrap(),
+            Data::new("changed".try_into().unwrap()),
+        )
+        .save(&db, &mut lot)
+        .await
+        .expect("failed to update at quota");
+        assert_eq!(3, lot.index().len());
+    }
+
+    #[test]
+    fn validate_otp() {
+        let lot = Lot::new("test");
+        let record = |otp: &str| {
+            Record::new(
+                &lot,
+                "foo".parse::<Label>().unwrap(),
+                Data::new("bar".try_into().unwrap()).add_extra(totp::OTP_KEY.into(), otp.into()),
+            )
+        };
+        record("otpauth://totp/foo?secret=JBSWY3DPEHPK3PXP&issuer=foo")
+            .validate()
+            .expect("valid otp rejected");
+        assert!(matches!(
+            record("otpauth://totp/foo?secret=JBSWY3DP!!").validate(),
+            Err(Error::Otp(totp::Error::InvalidSecret))
+        ));
+        Record::new(
+            &lot,
+            "foo".parse::<Label>().unwrap(),
+            Data::new("bar".try_into().unwrap()),
+        )
+        .validate()
+        .expect("record without otp rejected");
     }
 
     #[cfg(feature = "db")]
@@ -712,6 +1505,228 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn swapped_ciphertext_fails() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("lot a");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let a = Record::new(
+            &lot,
+            "a".parse::<Label>().unwrap(),
+            Data::new("1".try_into().unwrap()),
+        );
+        a.save(&db, &mut lot).await.expect("failed to save record");
+        let b = Record::new(
+            &lot,
+            "b".parse::<Label>().unwrap(),
+            Data::new("2".try_into().unwrap()),
+        );
+        b.save(&db, &mut lot).await.expect("failed to save record");
+
+        // Same lot key, but the AAD binds each blob to its own uuid.
+        let module = self::orm::Entity::find_by_id(a.uuid().to_string())
+            .one(db.connection())
+            .await
+            .expect("failed to find row")
+            .expect("no row")
+            .module;
+        self::orm::Entity::update(self::orm::ActiveModel {
+            uuid: sea_orm::ActiveValue::Unchanged(b.uuid().to_string()),
+            module: sea_orm::ActiveValue::Set(module),
+            ..Default::default()
+        })
+        .exec(db.connection())
+        .await
+        .expect("failed to swap module");
+
+        let lot = Lot::load(&db, "lot a", &user)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        assert!(Record::show(&db, &lot, b.uuid()).await.is_err());
+        let shown = Record::show(&db, &lot, a.uuid())
+            .await
+            .expect("failed to show record")
+            .expect("no record");
+        assert_eq!("1", shown.password().expose());
+    }
+
+    #[test]
+    fn canonical_fields() {
+        let lot = Lot::new("lot");
+        let record = Record::new(
+            &lot,
+            "nix@example.com"
+                .parse::<Label>()
+                .unwrap()
+                .add_extra(URL_KEY, "https://example.com")
+                .unwrap(),
+            Data::new("secret".try_into().unwrap())
+                .add_extra(NOTES_KEY.into(), "hi".into())
+                .add_extra(totp::OTP_KEY.into(), "JBSWY3DPEHPK3PXP".into()),
+        );
+        assert_eq!("secret", record.password().expose());
+        assert_eq!(Some("nix"), record.username());
+        assert_eq!(Some("https://example.com"), record.url());
+        assert_eq!(Some("hi"), record.notes());
+        assert_eq!(Some("JBSWY3DPEHPK3PXP"), record.otp());
+
+        // Explicit fields win over the domain id, wherever they're kept.
+        let record = Record::new(
+            &lot,
+            "nix@example.com".parse::<Label>().unwrap(),
+            Data::new("secret".try_into().unwrap())
+                .add_extra(USERNAME_KEY.into(), "nixpulvis".into())
+                .add_extra(URL_KEY.into(), "https://example.com/login".into()),
+        );
+        assert_eq!(Some("nixpulvis"), record.username());
+        assert_eq!(Some("https://example.com/login"), record.url());
+        assert_eq!(None, record.notes());
+        assert_eq!(None, record.otp());
+
+        let simple = Record::new(
+            &lot,
+            "github".parse::<Label>().unwrap(),
+            Data::new("secret".try_into().unwrap()),
+        );
+        assert_eq!(None, simple.username());
+        assert_eq!(None, simple.url());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn mixed_compression() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("lot a");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let notes = "a".repeat(encrypt::COMPRESSION_THRESHOLD * 4);
+        let small = Record::new(
+            &lot,
+            "small".parse::<Label>().unwrap(),
+            Data::new("1".try_into().unwrap()),
+        );
+        small
+            .save(&db, &mut lot)
+            .await
+            .expect("failed to save record");
+        let large = Record::new(
+            &lot,
+            "large".parse::<Label>().unwrap(),
+            Data::new("2".try_into().unwrap()).add_extra("notes".into(), notes.clone()),
+        );
+        large
+            .save(&db, &mut lot)
+            .await
+            .expect("failed to save record");
+
+        let lot = Lot::load(&db, "lot a", &user)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        let shown = Record::show(&db, &lot, small.uuid())
+            .await
+            .expect("failed to show record")
+            .expect("no record");
+        assert_eq!("1", shown.password().expose());
+        let shown = Record::show(&db, &lot, large.uuid())
+            .await
+            .expect("failed to show record")
+            .expect("no record");
+        assert_eq!(Some(&notes), shown.data().extra().get("notes"));
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn events() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut rx = db.subscribe();
+        let mut lot = Lot::new("lot a");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let lot_uuid = lot.uuid().clone();
+        assert_eq!(
+            VaultEvent::LotCreated {
+                lot: lot_uuid.clone()
+            },
+            rx.try_recv().expect("no event")
+        );
+
+        let record = Record::new(
+            &lot,
+            "a".parse::<Label>().unwrap(),
+            Data::new("1".try_into().unwrap()),
+        );
+        record
+            .save(&db, &mut lot)
+            .await
+            .expect("failed to save record");
+        assert_eq!(
+            VaultEvent::RecordInserted {
+                lot: lot_uuid.clone(),
+                record: record.uuid().clone(),
+            },
+            rx.try_recv().expect("no event")
+        );
+
+        let updated = Record::with_uuid(
+            record.uuid().clone(),
+            &lot,
+            "a".parse::<Label>().unwrap(),
+            Data::new("2".try_into().unwrap()),
+        );
+        updated
+            .save(&db, &mut lot)
+            .await
+            .expect("failed to save record");
+        assert_eq!(
+            VaultEvent::RecordUpdated {
+                lot: lot_uuid.clone(),
+                record: record.uuid().clone(),
+            },
+            rx.try_recv().expect("no event")
+        );
+
+        updated
+            .delete(&db, &mut lot)
+            .await
+            .expect("failed to delete record");
+        assert_eq!(
+            VaultEvent::RecordRemoved {
+                lot: lot_uuid.clone(),
+                record: record.uuid().clone(),
+            },
+            rx.try_recv().expect("no event")
+        );
+
+        lot.delete(&db).await.expect("failed to delete lot");
+        assert_eq!(
+            VaultEvent::LotDeleted { lot: lot_uuid },
+            rx.try_recv().expect("no event")
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
     #[cfg(feature = "db")]
     #[tokio::test(flavor = "multi_thread")]
     async fn show_roundtrip() {
@@ -879,7 +1894,7 @@ mod tests {
             .await
             .expect("failed to show record")
             .expect("record missing");
-        assert_eq!(loaded.password().to_string(), "p1-new");
+        assert_eq!(loaded.password().expose(), "p1-new");
         let history = Record::history(&db, &lot, records[0].uuid())
             .await
             .expect("failed to read history")
@@ -1047,4 +2062,207 @@ mod tests {
             .expect_err("expected LabelCollision");
         assert!(matches!(err, Error::LabelCollision { .. }));
     }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn load_all_concurrency() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("lot");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let records: Vec<Record> = (0..25)
+            .map(|i| {
+                Record::new(
+                    &lot,
+                    format!("record{i:02}").parse::<Label>().unwrap(),
+                    Data::new(format!("secret{i}").as_str().try_into().unwrap()),
+                )
+            })
+            .collect();
+        Record::save_many(&db, &mut lot, &records, |_| {})
+            .await
+            .expect("failed to save records");
+
+        let expected: Vec<(Uuid<Record>, String)> = lot
+            .index()
+            .iter()
+            .map(|(label, uuid)| (uuid.clone(), label.to_string()))
+            .collect();
+        assert_eq!(25, expected.len());
+        for concurrency in [1, 2, 4, 7, 64] {
+            let loaded = Record::load_all(&lot, NonZeroUsize::new(concurrency).unwrap())
+                .await
+                .expect("failed to load records");
+            let got: Vec<(Uuid<Record>, String)> = loaded
+                .iter()
+                .map(|r| (r.uuid().clone(), r.label().to_string()))
+                .collect();
+            assert_eq!(expected, got, "concurrency {concurrency}");
+            for record in &loaded {
+                let original = records.iter().find(|r| r.uuid() == record.uuid()).unwrap();
+                assert_eq!(original.password(), record.password());
+            }
+        }
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn clone_to_lot() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut personal = Lot::new("personal");
+        personal.save(&db, &user).await.expect("failed to save lot");
+        let mut shared = Lot::new("shared");
+        shared.save(&db, &user).await.expect("failed to save lot");
+        let record = Record::new(
+            &personal,
+            "alice@example.com".parse::<Label>().unwrap(),
+            Data::new("secret".try_into().unwrap()).add_extra("notes".into(), "hi".into()),
+        );
+        record
+            .save(&db, &mut personal)
+            .await
+            .expect("failed to save record");
+
+        let uuid = record
+            .clone_to_lot(&db, &personal, &mut shared)
+            .await
+            .expect("failed to clone record");
+        assert_ne!(record.uuid(), &uuid);
+        assert!(matches!(
+            record.clone_to_lot(&db, &shared, &mut personal).await,
+            Err(Error::LotMismatch { .. })
+        ));
+
+        // Each copy decrypts on its own, from a fresh load of its lot.
+        for (name, uuid) in [("personal", record.uuid()), ("shared", &uuid)] {
+            let lot = Lot::load(&db, name, &user)
+                .await
+                .expect("failed to load lot")
+                .expect("no lot");
+            let copy = Record::show(&db, &lot, uuid)
+                .await
+                .expect("failed to show record")
+                .expect("no record");
+            assert_eq!("secret", copy.password().expose());
+            assert_eq!(Some(&"hi".to_owned()), copy.data().extra().get("notes"));
+            assert_eq!(record.label(), copy.label());
+        }
+    }
+
+    #[test]
+    fn debug_redacts() {
+        let lot = Lot::new("lot");
+        let record = Record::new(
+            &lot,
+            "nix@example.com".parse::<Label>().unwrap(),
+            Data::new("hunter22".try_into().unwrap())
+                .add_extra(crate::totp::OTP_KEY.into(), "GEZDGNBVGY3TQOJQ".into()),
+        );
+        let debug = format!("{record:?}");
+        assert!(debug.contains("nix@example.com"), "{debug}");
+        assert!(!debug.contains("hunter22"), "{debug}");
+        assert!(!debug.contains("GEZDGNBVGY3TQOJQ"), "{debug}");
+        let full = record.debug_full();
+        assert!(full.contains("hunter22"), "{full}");
+        assert!(full.contains("GEZDGNBVGY3TQOJQ"), "{full}");
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn save_validates_when_asked() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database")
+            .with_record_validation();
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("lot a");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let record = |lot: &Lot, name: &str, otp: &str| {
+            Record::new(
+                lot,
+                name.parse::<Label>().unwrap(),
+                Data::new("secret".try_into().unwrap()).add_extra(totp::OTP_KEY.into(), otp.into()),
+            )
+        };
+
+        record(&lot, "good", "otpauth://totp/foo?secret=JBSWY3DPEHPK3PXP")
+            .save(&db, &mut lot)
+            .await
+            .expect("valid otp rejected");
+        let broken = record(&lot, "broken", "otpauth://totp/foo?secret=JBSWY3DP!!");
+        assert!(matches!(
+            broken.save(&db, &mut lot).await,
+            Err(Error::Otp(totp::Error::InvalidSecret))
+        ));
+        assert!(matches!(
+            Record::save_many(&db, &mut lot, std::slice::from_ref(&broken), |_| {}).await,
+            Err(Error::Otp(totp::Error::InvalidSecret))
+        ));
+        assert_eq!(1, lot.index().len());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn quota_counts_the_database() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database")
+            .with_record_quota(2);
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("lot a");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let record = |lot: &Lot, name: &str| {
+            Record::new(
+                lot,
+                name.parse::<Label>().unwrap(),
+                Data::new("secret".try_into().unwrap()),
+            )
+        };
+        // A second handle whose index never sees the first's saves.
+        let mut stale = Lot::load(&db, "lot a", &user)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+
+        let batch = [record(&lot, "a"), record(&lot, "b")];
+        Record::save_many(&db, &mut lot, &batch, |_| {})
+            .await
+            .expect("failed to save at quota");
+        assert!(matches!(
+            record(&stale, "c").save(&db, &mut stale).await,
+            Err(Error::QuotaExceeded { quota: 2 })
+        ));
+        let over = [record(&stale, "d")];
+        assert!(matches!(
+            Record::save_many(&db, &mut stale, &over, |_| {}).await,
+            Err(Error::QuotaExceeded { quota: 2 })
+        ));
+        let rows = self::orm::Entity::find()
+            .filter(self::orm::Column::LotUuid.eq(lot.uuid().to_string()))
+            .count(db.connection())
+            .await
+            .expect("failed to count records");
+        assert_eq!(2, rows);
+    }
 }
@@ -9,6 +9,8 @@ pub struct Model {
     #[sea_orm(column_name = "lot")]
     pub lot_uuid: String,
     pub module: Vec<u8>,
+    /// Position set by [`Lot::reorder`](crate::lot::Lot::reorder), if any.
+    pub sort_index: Option<i64>,
     #[sea_orm(belongs_to, from = "lot_uuid", to = "uuid")]
     pub lot: HasOne<crate::lot::orm::Entity>,
 }
@@ -0,0 +1,96 @@
+use super::{Error, LabelName, Record};
+use crate::totp::Totp;
+use qrcode::{QrCode, render::unicode::Dense1x2};
+
+impl Record {
+    /// The `otpauth://` URI for this record's `otp` secret, as a QR code
+    /// drawn with Unicode half blocks for scanning off a terminal into an
+    /// authenticator app.
+    ///
+    /// `None` if the record has no `otp` field. Domain records are
+    /// labelled `domain:id`, the issuer and account authenticator apps
+    /// display.
+    pub fn otp_qr(&self) -> Option<Result<String, Error>> {
+        let uri = self.otp_uri()?;
+        Some(uri.and_then(|uri| {
+            let code = QrCode::new(uri.as_bytes()).map_err(Error::Qr)?;
+            Ok(code.render::<Dense1x2>().build())
+        }))
+    }
+
+    fn otp_uri(&self) -> Option<Result<String, Error>> {
+        let secret = self.otp()?;
+        let label = match self.label().name() {
+            LabelName::Domain { id, domain } => format!("{domain}:{id}"),
+            LabelName::Simple(name) => name.clone(),
+        };
+        Some(
+            Totp::parse(secret)
+                .map(|totp| totp.to_uri(&label))
+                .map_err(Error::from),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lot::Lot, record::Data, totp::OTP_KEY};
+
+    fn record(label: &str, data: Data) -> Record {
+        let lot = Lot::new("lot");
+        Record::new(&lot, label.parse().unwrap(), data)
+    }
+
+    /// Read the dark modules back out of [`Dense1x2`]'s half blocks.
+    fn decode(qr: &str) -> String {
+        let rows: Vec<Vec<char>> = qr.lines().map(|l| l.chars().collect()).collect();
+        let width = rows[0].len();
+        let mut pixels = vec![false; width * rows.len() * 2];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                let (top, bottom) = match c {
+                    '█' => (true, true),
+                    '▀' => (true, false),
+                    '▄' => (false, true),
+                    _ => (false, false),
+                };
+                pixels[2 * y * width + x] = top;
+                pixels[(2 * y + 1) * width + x] = bottom;
+            }
+        }
+        let mut image =
+            rqrr::PreparedImage::prepare_from_greyscale(width, rows.len() * 2, |x, y| {
+                if pixels[y * width + x] { 0 } else { 255 }
+            });
+        let grids = image.detect_grids();
+        assert_eq!(1, grids.len());
+        grids[0].decode().expect("failed to decode qr").1
+    }
+
+    #[test]
+    fn otp_qr() {
+        let record = record(
+            "nix@example.com",
+            Data::new("secret".try_into().unwrap())
+                .add_extra(OTP_KEY.into(), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".into()),
+        );
+        let qr = record.otp_qr().expect("no qr").expect("failed to render");
+        assert_eq!(
+            "otpauth://totp/example.com:nix?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&digits=6&period=30",
+            decode(&qr)
+        );
+    }
+
+    #[test]
+    fn otp_qr_missing() {
+        let plain = record("github", Data::new("secret".try_into().unwrap()));
+        assert!(plain.otp_qr().is_none());
+
+        let bad = record(
+            "github",
+            Data::new("secret".try_into().unwrap()).add_extra(OTP_KEY.into(), "!!".into()),
+        );
+        assert!(matches!(bad.otp_qr(), Some(Err(Error::Otp(_)))));
+    }
+}
@@ -193,6 +193,18 @@ impl fmt::Display for Path {
     }
 }
 
+/// Parse a `lot::label` path with the [`Query`] grammar, then
+/// [`Query::into_path`]. A missing lot falls back to [`DEFAULT_LOT`], and
+/// since the lot is split at the *last* `::`, `work::aws::key` targets the
+/// nested lot `work::aws`.
+impl FromStr for Path {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Query>()?.into_path()
+    }
+}
+
 impl fmt::Display for Query {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.lot {
@@ -434,6 +446,16 @@ impl fmt::Display for Error {
     }
 }
 
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Name(e) => Some(e),
+            Error::Regex(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -779,6 +801,18 @@ mod tests {
         assert!(matches!(err, Error::DuplicateKey(k) if k == "k"));
     }
 
+    #[test]
+    fn path_parse() {
+        let path: Path = "label".parse().unwrap();
+        assert_eq!(path, Path::new(DEFAULT_LOT, "label".parse().unwrap()));
+        let path: Path = "lot::label".parse().unwrap();
+        assert_eq!(path, Path::new("lot", "label".parse().unwrap()));
+        let path: Path = "lot::sublot::label".parse().unwrap();
+        assert_eq!(path, Path::new("lot::sublot", "label".parse().unwrap()));
+        assert_eq!("lot::sublot::label", path.to_string());
+        assert!("~lot::label".parse::<Path>().is_err());
+    }
+
     #[test]
     fn into_path_builds_literal() {
         let path = p("work::nix@example.com<username=nix url=github.com>")
@@ -0,0 +1,321 @@
+//! Time-based one-time passwords ([RFC 6238]).
+//!
+//! Records carry TOTP secrets in their `otp` data field, usually as the
+//! `otpauth://totp/...` URIs authenticator apps export. [`Totp::parse`]
+//! accepts either that URI form or a bare base32 secret.
+//!
+//! [RFC 6238]: https://www.rfc-editor.org/rfc/rfc6238
+
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use sha1::Sha1;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// The [`Data::extra`](crate::record::Data::extra) key TOTP secrets are
+/// stored under.
+pub const OTP_KEY: &str = "otp";
+
+const DEFAULT_DIGITS: u32 = 6;
+const DEFAULT_PERIOD: u64 = 30;
+/// RFC 4226 recommends (and authenticator apps expect) 160 bit secrets.
+const SECRET_SIZE: usize = 20;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// A TOTP generator: HMAC-SHA1 over the current 30 second (by default)
+/// time step.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct Totp {
+    secret: Vec<u8>,
+    digits: u32,
+    period: u64,
+}
+
+impl Totp {
+    /// A generator with the usual 6 digits and 30 second period.
+    pub fn new(secret: Vec<u8>) -> Self {
+        Totp {
+            secret,
+            digits: DEFAULT_DIGITS,
+            period: DEFAULT_PERIOD,
+        }
+    }
+
+    /// A generator with a fresh random secret and the default parameters.
+    pub fn generate() -> Self {
+        let mut secret = vec![0; SECRET_SIZE];
+        OsRng.fill_bytes(&mut secret);
+        Totp::new(secret)
+    }
+
+    /// The raw shared secret.
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+
+    /// An `otpauth://totp/` URI for this generator, as authenticator apps
+    /// import (usually from a QR code). `label` is used verbatim.
+    pub fn to_uri(&self, label: &str) -> String {
+        format!(
+            "otpauth://totp/{label}?secret={}&digits={}&period={}",
+            encode_base32(&self.secret),
+            self.digits,
+            self.period
+        )
+    }
+
+    /// Parse an `otpauth://totp/<label>?secret=...` URI, honouring its
+    /// `digits` and `period` parameters, or a bare base32 secret.
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let s = s.trim();
+        let Some(rest) = s.strip_prefix("otpauth://") else {
+            return Ok(Totp::new(decode_base32(s)?));
+        };
+        let (kind, rest) = rest.split_once('/').ok_or(Error::InvalidUri)?;
+        if !kind.eq_ignore_ascii_case("totp") {
+            return Err(Error::UnsupportedKind(kind.into()));
+        }
+        let (_, query) = rest.split_once('?').ok_or(Error::MissingSecret)?;
+
+        let mut secret = None;
+        let mut digits = DEFAULT_DIGITS;
+        let mut period = DEFAULT_PERIOD;
+        for pair in query.split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key.to_ascii_lowercase().as_str() {
+                "secret" => secret = Some(decode_base32(value)?),
+                "digits" => {
+                    digits = value
+                        .parse()
+                        .ok()
+                        .filter(|d| (1..=9).contains(d))
+                        .ok_or_else(|| Error::InvalidParameter(key.into()))?
+                }
+                "period" => {
+                    period = value
+                        .parse()
+                        .ok()
+                        .filter(|p| *p > 0)
+                        .ok_or_else(|| Error::InvalidParameter(key.into()))?
+                }
+                "algorithm" if !value.eq_ignore_ascii_case("sha1") => {
+                    return Err(Error::UnsupportedAlgorithm(value.into()));
+                }
+                _ => {}
+            }
+        }
+        Ok(Totp {
+            secret: secret.ok_or(Error::MissingSecret)?,
+            digits,
+            period,
+        })
+    }
+
+    /// The code for the current time step.
+    pub fn code(&self) -> String {
+        self.code_at(SystemTime::now())
+    }
+
+    /// The code for the time step containing `time`.
+    pub fn code_at(&self, time: SystemTime) -> String {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.hotp(secs / self.period)
+    }
+
+    /// Check `code` against the current time step, see [`Totp::verify_at`].
+    pub fn verify(&self, code: &str) -> bool {
+        self.verify_at(code, SystemTime::now())
+    }
+
+    /// Check `code` against the time step containing `time`, also
+    /// accepting the steps either side of it to allow for clock skew and
+    /// a code entered just as it rolled over.
+    ///
+    /// Every step is compared in constant time, so timing doesn't say
+    /// how many digits matched, or which step did.
+    pub fn verify_at(&self, code: &str, time: SystemTime) -> bool {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let step = secs / self.period;
+        let code = code.trim().as_bytes();
+        [step.saturating_sub(1), step, step.saturating_add(1)]
+            .into_iter()
+            .fold(Choice::from(0), |matched, s| {
+                matched | self.hotp(s).as_bytes().ct_eq(code)
+            })
+            .into()
+    }
+
+    /// [RFC 4226] HOTP of `counter`, zero padded to `digits`.
+    ///
+    /// [RFC 4226]: https://www.rfc-editor.org/rfc/rfc4226
+    fn hotp(&self, counter: u64) -> String {
+        let mut mac =
+            Hmac::<Sha1>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(&counter.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let truncated = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        let code = truncated % 10u32.pow(self.digits);
+        format!("{code:0width$}", width = self.digits as usize)
+    }
+}
+
+impl fmt::Debug for Totp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Totp")
+            .field("digits", &self.digits)
+            .field("period", &self.period)
+            .finish_non_exhaustive()
+    }
+}
+
+/// RFC 4648 base32, unpadded as `otpauth` URIs expect.
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// RFC 4648 base32, case-insensitive, ignoring padding and the spaces
+/// or dashes some services group secrets with.
+fn decode_base32(s: &str) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    for c in s.chars().filter(|c| !matches!(c, '=' | ' ' | '-')) {
+        let value = match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u32 - 'A' as u32,
+            c @ '2'..='7' => c as u32 - '2' as u32 + 26,
+            _ => return Err(Error::InvalidSecret),
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    if out.is_empty() {
+        Err(Error::MissingSecret)
+    } else {
+        Ok(out)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    InvalidUri,
+    UnsupportedKind(String),
+    UnsupportedAlgorithm(String),
+    InvalidParameter(String),
+    MissingSecret,
+    InvalidSecret,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidUri => write!(f, "invalid otpauth uri"),
+            Error::UnsupportedKind(k) => write!(f, "unsupported otp kind '{k}'"),
+            Error::UnsupportedAlgorithm(a) => write!(f, "unsupported otp algorithm '{a}'"),
+            Error::InvalidParameter(p) => write!(f, "invalid otp parameter '{p}'"),
+            Error::MissingSecret => write!(f, "missing otp secret"),
+            Error::InvalidSecret => write!(f, "otp secret is not base32"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Base32 of the RFC 6238 SHA1 test secret `12345678901234567890`.
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn rfc6238_vectors() {
+        let totp = Totp::parse(&format!("otpauth://totp/test?secret={RFC_SECRET}&digits=8"))
+            .expect("failed to parse");
+        for (secs, code) in [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1234567890, "89005924"),
+            (20000000000, "65353130"),
+        ] {
+            assert_eq!(code, totp.code_at(UNIX_EPOCH + Duration::from_secs(secs)));
+        }
+    }
+
+    #[test]
+    fn bare_secret() {
+        let totp = Totp::parse("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").expect("failed to parse");
+        assert_eq!("287082", totp.code_at(UNIX_EPOCH + Duration::from_secs(59)));
+    }
+
+    #[test]
+    fn generate_roundtrip() {
+        let totp = Totp::generate();
+        let parsed = Totp::parse(&totp.to_uri("valet:alice")).expect("failed to parse");
+        assert_eq!(totp.secret(), parsed.secret());
+        assert_eq!(RFC_SECRET, encode_base32(b"12345678901234567890"));
+    }
+
+    #[test]
+    fn verify_window() {
+        let totp = Totp::parse(RFC_SECRET).expect("failed to parse");
+        let now = UNIX_EPOCH + Duration::from_secs(1111111109);
+        let code = totp.code_at(now);
+        assert!(totp.verify_at(&code, now));
+        assert!(totp.verify_at(&code, now + Duration::from_secs(30)));
+        assert!(!totp.verify_at(&code, now + Duration::from_secs(90)));
+        assert!(!totp.verify_at("000000", now));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(Some(Error::InvalidSecret), Totp::parse("not base32!").err());
+        assert_eq!(Some(Error::MissingSecret), Totp::parse("").err());
+        assert_eq!(
+            Some(Error::MissingSecret),
+            Totp::parse("otpauth://totp/test?issuer=x").err()
+        );
+        assert_eq!(
+            Some(Error::UnsupportedKind("hotp".into())),
+            Totp::parse("otpauth://hotp/test?secret=AAAA").err()
+        );
+        assert_eq!(
+            Some(Error::InvalidParameter("digits".into())),
+            Totp::parse("otpauth://totp/test?secret=AAAA&digits=x").err()
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use super::{Error, User};
+use crate::{
+    db::Database,
+    lot::{self, PATH_SEPARATOR},
+    record::{self, Record},
+};
+
+impl User {
+    /// Find passwords used by more than one record.
+    ///
+    /// Every record in every lot this user can open is decrypted and
+    /// compared in memory. Only the `lot::label` names of each group of
+    /// records sharing a password are returned, never the passwords.
+    /// Groups, and the names within them, are sorted.
+    pub async fn audit_reused_passwords(&self, db: &Database) -> Result<Vec<Vec<String>>, Error> {
+        let mut records = self.decrypt_all(db).await?;
+        records.retain(|(_, record)| !record.password().is_empty());
+        records.sort_by(|(_, a), (_, b)| a.password().as_bytes().cmp(b.password().as_bytes()));
+        let mut groups: Vec<Vec<String>> = records
+            .chunk_by(|(_, a), (_, b)| a.password() == b.password())
+            .filter(|chunk| chunk.len() > 1)
+            .map(|chunk| {
+                let mut names: Vec<String> = chunk.iter().map(|(name, _)| name.clone()).collect();
+                names.sort();
+                names
+            })
+            .collect();
+        groups.sort();
+        Ok(groups)
+    }
+
+    /// The `lot::label` name and breach count of every record whose
+    /// password appears in a known breach, see [`Record::check_breached`].
+    ///
+    /// Stops at the first failed lookup, so when offline this returns
+    /// the [`hibp::Error::Unavailable`](crate::hibp::Error::Unavailable)
+    /// rather than a misleadingly clean result.
+    #[cfg(feature = "hibp")]
+    pub async fn audit_breached(&self, db: &Database) -> Result<Vec<(String, u64)>, Error> {
+        let mut breached = Vec::new();
+        for (name, record) in self.decrypt_all(db).await? {
+            if record.password().is_empty() {
+                continue;
+            }
+            let count = record.check_breached().await.map_err(lot::Error::from)?;
+            if count > 0 {
+                breached.push((name, count));
+            }
+        }
+        Ok(breached)
+    }
+
+    /// Every record this user can open, paired with its `lot::label` name.
+    async fn decrypt_all(&self, db: &Database) -> Result<Vec<(String, Record)>, Error> {
+        let mut records = Vec::new();
+        for lot in self.lots(db).await? {
+            let loaded = Record::load_all(&lot, record::default_concurrency())
+                .await
+                .map_err(lot::Error::from)?;
+            for record in loaded {
+                let name = format!("{}{PATH_SEPARATOR}{}", lot.name(), record.label().name());
+                records.push((name, record));
+            }
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lot::Lot,
+        record::{Data, Label},
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reused_passwords() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let alice = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut main = Lot::new(lot::DEFAULT_LOT);
+        main.save(&db, &alice).await.expect("failed to save lot");
+        let mut work = Lot::new("work");
+        work.save(&db, &alice).await.expect("failed to save lot");
+        save(&db, &mut main, "alice@example.com", "hunter22").await;
+        save(&db, &mut main, "bank", "unique-and-long").await;
+        save(&db, &mut work, "alice@corp.example", "hunter22").await;
+
+        let groups = alice
+            .audit_reused_passwords(&db)
+            .await
+            .expect("failed to audit");
+        assert_eq!(
+            vec![vec![
+                "main::alice@example.com".to_owned(),
+                "work::alice@corp.example".to_owned()
+            ]],
+            groups
+        );
+    }
+
+    async fn save(db: &Database, lot: &mut Lot, label: &str, password: &str) {
+        Record::new(
+            lot,
+            label.parse::<Label>().unwrap(),
+            Data::new(password.try_into().unwrap()),
+        )
+        .save(db, lot)
+        .await
+        .expect("failed to save record");
+    }
+}
@@ -0,0 +1,207 @@
+use super::{Error, User, is_validation};
+#[cfg(feature = "db")]
+use crate::db::Database;
+use crate::{
+    encrypt::{Encrypted, KdfParams, Key, SALT_SIZE},
+    password::Password,
+};
+#[cfg(feature = "db")]
+use sea_orm::EntityTrait;
+use zeroize::Zeroizing;
+
+/// A user key derived once from a password, for clients that check the
+/// password and then go on to use the key.
+///
+/// Deriving the key is the slow (argon2) part of unlocking. A credential
+/// pays for it in [`Credential::derive`], then [`Credential::validate`]s
+/// against a user's validation string and encrypts and decrypts under the
+/// same key, and [`User::from_credential`] turns it into a [`User`]
+/// without deriving again.
+pub struct Credential {
+    username: String,
+    salt: [u8; SALT_SIZE],
+    kdf: KdfParams,
+    key: Key<User>,
+}
+
+impl Credential {
+    /// Derive `username`'s key from `password`, `salt` and `kdf`.
+    pub fn derive(
+        username: &str,
+        password: &Password,
+        salt: [u8; SALT_SIZE],
+        kdf: KdfParams,
+    ) -> Result<Self, Error> {
+        let key = Key::from_password_with(password, &salt, &kdf)?;
+        Ok(Credential {
+            username: username.into(),
+            salt,
+            kdf,
+            key,
+        })
+    }
+
+    /// Derive a key with the salt and [`KdfParams`] `username` was
+    /// registered with.
+    ///
+    /// An unknown user gets a key derived against a throwaway salt with
+    /// the default [`KdfParams`], which never validates, so they look
+    /// like a wrong password: [`User::from_credential`] is
+    /// [`Error::Invalid`] either way.
+    #[cfg(feature = "db")]
+    pub async fn fetch(db: &Database, username: &str, password: &Password) -> Result<Self, Error> {
+        match super::orm::Entity::find_by_id(username.to_owned())
+            .one(db.connection())
+            .await?
+        {
+            Some(model) => Self::from_model(&model, password),
+            None => Self::derive(username, password, [0; SALT_SIZE], KdfParams::default()),
+        }
+    }
+
+    /// A credential for a key derived some time before, e.g. one a
+    /// running agent holds.
+    #[cfg(feature = "db")]
+    pub(crate) fn from_key(model: &super::orm::Model, key: Key<User>) -> Result<Self, Error> {
+        Ok(Credential {
+            username: model.username.clone(),
+            salt: model
+                .salt
+                .as_slice()
+                .try_into()
+                .map_err(|_| Error::SaltError)?,
+            kdf: User::model_kdf(model)?,
+            key,
+        })
+    }
+
+    #[cfg(feature = "db")]
+    pub(crate) fn from_model(
+        model: &super::orm::Model,
+        password: &Password,
+    ) -> Result<Self, Error> {
+        let salt = model
+            .salt
+            .as_slice()
+            .try_into()
+            .map_err(|_| Error::SaltError)?;
+        Self::derive(&model.username, password, salt, User::model_kdf(model)?)
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn key(&self) -> &Key<User> {
+        &self.key
+    }
+
+    /// Whether `validation`, a user's encrypted validation string,
+    /// decrypts under this key. Only true for the right password.
+    pub fn validate(&self, validation: &Encrypted) -> bool {
+        self.key
+            .decrypt_with_aad(validation, User::aad(&self.username))
+            .is_ok_and(|v| is_validation(&v))
+    }
+
+    /// Encrypt under this key, bound to the username so the ciphertext
+    /// doesn't decrypt as anyone else's.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Encrypted, Error> {
+        Ok(self
+            .key
+            .encrypt_with_aad(plaintext, &User::credential_aad(&self.username))?)
+    }
+
+    pub fn decrypt(&self, encrypted: &Encrypted) -> Result<Zeroizing<Vec<u8>>, Error> {
+        Ok(self
+            .key
+            .decrypt_with_aad(encrypted, &User::credential_aad(&self.username))?)
+    }
+
+    /// The user this credential unlocks, once `validation` has passed
+    /// [`Credential::validate`].
+    pub(crate) fn into_user(self, validation: Encrypted, key_version: i64) -> User {
+        User {
+            username: self.username,
+            salt: self.salt,
+            validation,
+            key: self.key,
+            key_version,
+            kdf: self.kdf,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_and_encrypt() {
+        let password: Password = "password".try_into().unwrap();
+        let user = User::new("alice", password.clone()).expect("failed to make user");
+
+        // One derivation, used for both.
+        let credential =
+            Credential::derive("alice", &password, user.salt, user.kdf).expect("failed to derive");
+        assert!(credential.validate(&user.validation));
+        let encrypted = credential.encrypt(b"secret").expect("failed to encrypt");
+        assert_eq!(
+            b"secret",
+            &credential.decrypt(&encrypted).expect("failed to decrypt")[..]
+        );
+        assert_eq!(
+            b"secret",
+            &user
+                .key()
+                .decrypt_with_aad(&encrypted, &User::credential_aad("alice"))
+                .expect("failed to decrypt")[..]
+        );
+        assert!(user.key().decrypt(&encrypted).is_err());
+
+        let wrong = Credential::derive("alice", &"wrong".try_into().unwrap(), user.salt, user.kdf)
+            .expect("failed to derive");
+        assert!(!wrong.validate(&user.validation));
+        let mallory = Credential::derive("mallory", &password, user.salt, user.kdf)
+            .expect("failed to derive");
+        assert!(!mallory.validate(&user.validation));
+        assert!(mallory.decrypt(&encrypted).is_err());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn from_credential() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let password: Password = "password".try_into().unwrap();
+        let user = User::new("alice", password.clone())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+
+        let credential = Credential::fetch(&db, "alice", &password)
+            .await
+            .expect("failed to fetch");
+        let loaded = User::from_credential(&db, credential)
+            .await
+            .expect("failed to load user");
+        assert_eq!(user, loaded);
+
+        let wrong = Credential::fetch(&db, "alice", &"wrong".try_into().unwrap())
+            .await
+            .expect("failed to fetch");
+        assert!(matches!(
+            User::from_credential(&db, wrong).await,
+            Err(Error::Invalid)
+        ));
+        let nobody = Credential::fetch(&db, "nobody", &password)
+            .await
+            .expect("failed to fetch");
+        assert!(matches!(
+            User::from_credential(&db, nobody).await,
+            Err(Error::Invalid)
+        ));
+    }
+}
@@ -0,0 +1,272 @@
+use super::{Error, User};
+use crate::{
+    db::Database,
+    lot::{self, Lot, LotTree, PATH_SEPARATOR},
+    password::Password,
+    record::{Data, Label, LabelName, Record},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Everything a user can decrypt, in plaintext, produced by
+/// [`User::export_all`] and consumed by [`User::import_all`].
+///
+/// This is the "download all my data" format, so every password in it
+/// is in the clear. Handle the serialized form like the secrets it
+/// holds.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct UserExport {
+    /// Parents always come before their children.
+    pub lots: Vec<LotExport>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct LotExport {
+    /// The lot's full [`PATH_SEPARATOR`] delimited path, e.g. `work::aws`.
+    pub name: String,
+    /// Path of the lot this one is nested under, if any.
+    pub parent: Option<String>,
+    pub records: Vec<RecordExport>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct RecordExport {
+    /// The [`LabelName`] in its display form, e.g. `nix@example.com`.
+    pub name: String,
+    pub label_extra: BTreeMap<String, String>,
+    pub password: String,
+    pub extra: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl RecordExport {
+    pub(crate) fn from_record(record: &Record) -> Self {
+        RecordExport {
+            name: record.label().name().to_string(),
+            label_extra: record.label().extra().clone(),
+            password: record.password().expose().to_owned(),
+            extra: record.data().extra().clone(),
+            tags: record.data().tags().into_iter().map(String::from).collect(),
+        }
+    }
+
+    /// A record for this export in `lot`, reusing the UUID of the record
+    /// already there by the same name so saving it overwrites that one.
+    pub(crate) fn to_record(&self, lot: &Lot) -> Result<Record, lot::Error> {
+        let label =
+            Label::from(self.name.parse::<LabelName>()?).with_extra(self.label_extra.clone())?;
+        let password = Password::try_from(self.password.as_str())?;
+        let data = Data::new(password)
+            .with_extra(self.extra.clone())
+            .with_tags(&self.tags);
+        Ok(match lot.index().find_by_name(label.name()).cloned() {
+            Some(uuid) => Record::with_uuid(uuid, lot, label, data),
+            None => Record::new(lot, label, data),
+        })
+    }
+}
+
+impl LotExport {
+    /// This lot's records for `lot`, see [`RecordExport::to_record`].
+    pub(crate) fn to_records(&self, lot: &Lot) -> Result<Vec<Record>, lot::Error> {
+        self.records.iter().map(|r| r.to_record(lot)).collect()
+    }
+}
+
+impl User {
+    /// Decrypt every lot and record this user has access to into a single
+    /// [`UserExport`].
+    pub async fn export_all(&self, db: &Database) -> Result<UserExport, Error> {
+        let mut ordered = Vec::new();
+        flatten(self.lot_tree(db).await?, None, &mut ordered);
+
+        let mut lots = Vec::with_capacity(ordered.len());
+        for (lot, path, parent) in ordered {
+            let records = lot.export_records(db).await?;
+            lots.push(LotExport {
+                name: path,
+                parent,
+                records,
+            });
+        }
+        Ok(UserExport { lots })
+    }
+
+    /// Re-create the contents of a [`UserExport`] under this user,
+    /// re-encrypting everything under fresh lot keys.
+    ///
+    /// Intended for a freshly registered account. A lot this user already
+    /// has at the same path is imported into rather than duplicated, with
+    /// records of the same name overwritten.
+    ///
+    /// Each lot is imported in one transaction, so a lot this creates
+    /// never ends up half filled; lots before a failing one stay imported.
+    pub async fn import_all(&self, db: &Database, export: &UserExport) -> Result<(), Error> {
+        let mut imported: HashMap<&str, Lot> = HashMap::with_capacity(export.lots.len());
+        for lot_export in &export.lots {
+            let path = lot_export.name.as_str();
+            let lot = match Lot::load(db, path, self).await {
+                Ok(Some(mut lot)) => {
+                    let records = lot_export.to_records(&lot)?;
+                    Record::save_many(db, &mut lot, &records, |_| {})
+                        .await
+                        .map_err(lot::Error::from)?;
+                    lot
+                }
+                Ok(None) | Err(lot::Error::NotFound(_)) => {
+                    let leaf = path.rsplit(PATH_SEPARATOR).next().unwrap_or(path);
+                    let mut lot = match &lot_export.parent {
+                        Some(parent) => {
+                            let parent = imported
+                                .get(parent.as_str())
+                                .ok_or_else(|| lot::Error::NotFound(parent.clone()))?;
+                            Lot::new_child(leaf, parent)
+                        }
+                        None => Lot::new(leaf),
+                    };
+                    let records = lot_export.to_records(&lot)?;
+                    lot.save_with_records(db, self, &records).await?;
+                    lot
+                }
+                Err(e) => return Err(e.into()),
+            };
+            imported.insert(path, lot);
+        }
+        Ok(())
+    }
+}
+
+/// Pre-order walk of `trees`, siblings sorted by name, pairing each lot
+/// with its path and its parent's path.
+fn flatten(
+    mut trees: Vec<LotTree>,
+    parent: Option<&str>,
+    out: &mut Vec<(Lot, String, Option<String>)>,
+) {
+    trees.sort_by(|a, b| a.lot.name().cmp(b.lot.name()));
+    for tree in trees {
+        let path = match parent {
+            Some(parent) => format!("{parent}{PATH_SEPARATOR}{}", tree.lot.name()),
+            None => tree.lot.name().to_owned(),
+        };
+        out.push((tree.lot, path.clone(), parent.map(str::to_owned)));
+        flatten(tree.children, Some(&path), out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn export_import_roundtrip() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let alice = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut main = Lot::new(lot::DEFAULT_LOT);
+        main.save(&db, &alice).await.expect("failed to save lot");
+        let mut work = Lot::new("work");
+        work.save(&db, &alice).await.expect("failed to save lot");
+        let mut aws = Lot::new_child("aws", &work);
+        aws.save(&db, &alice).await.expect("failed to save lot");
+        // A top level lot with the same name as the nested one.
+        let mut top_aws = Lot::new("aws");
+        top_aws.save(&db, &alice).await.expect("failed to save lot");
+        Record::new(
+            &top_aws,
+            "top".parse::<Label>().unwrap(),
+            Data::new("toplevel".try_into().unwrap()),
+        )
+        .save(&db, &mut top_aws)
+        .await
+        .expect("failed to save record");
+        Record::new(
+            &main,
+            "alice@example.com"
+                .parse::<Label>()
+                .unwrap()
+                .add_extra("url", "https://example.com")
+                .unwrap(),
+            Data::new("secret".try_into().unwrap()).add_extra("notes".into(), "hi".into()),
+        )
+        .save(&db, &mut main)
+        .await
+        .expect("failed to save record");
+        Record::new(
+            &aws,
+            "root".parse::<Label>().unwrap(),
+            Data::new("hunter22".try_into().unwrap()),
+        )
+        .save(&db, &mut aws)
+        .await
+        .expect("failed to save record");
+
+        let export = alice.export_all(&db).await.expect("failed to export");
+        assert_eq!(4, export.lots.len());
+        assert_eq!("work::aws", export.lots[3].name);
+        assert_eq!(Some("work"), export.lots[3].parent.as_deref());
+
+        let bob = User::new("bob", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        bob.import_all(&db, &export)
+            .await
+            .expect("failed to import");
+
+        let reexport = bob.export_all(&db).await.expect("failed to export");
+        assert_eq!(export, reexport);
+        let bob_aws = Lot::load(&db, "work::aws", &bob)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        assert_ne!(bob_aws.uuid(), aws.uuid());
+        assert_eq!(1, bob_aws.index().len());
+
+        // Importing again merges into the same lots, nested ones included.
+        bob.import_all(&db, &export)
+            .await
+            .expect("failed to import again");
+        let reexport = bob.export_all(&db).await.expect("failed to export");
+        assert_eq!(export, reexport);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn import_failure_leaves_no_lot() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database")
+            .with_record_quota(1);
+        let alice = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let record = |name: &str| RecordExport {
+            name: name.into(),
+            label_extra: BTreeMap::new(),
+            password: "secret".into(),
+            extra: HashMap::new(),
+            tags: Vec::new(),
+        };
+        let export = UserExport {
+            lots: vec![LotExport {
+                name: "work".into(),
+                parent: None,
+                records: vec![record("a"), record("b")],
+            }],
+        };
+        assert!(alice.import_all(&db, &export).await.is_err());
+        assert!(matches!(
+            Lot::load(&db, "work", &alice).await,
+            Ok(None) | Err(lot::Error::NotFound(_))
+        ));
+    }
+}
@@ -1,18 +1,31 @@
 #[cfg(feature = "db")]
 use crate::{
-    db::{self, Database},
-    lot::{self, Lot},
+    db::{self, AdminDatabase, Database},
+    lot::{self, Lot, LotSummary, LotTree, Permission},
+    record::{self, Record},
+    totp::Totp,
+    uuid::Uuid,
 };
 use crate::{
-    encrypt::{self, Encrypted, Key, SALT_SIZE},
+    encrypt::{self, Encrypted, KdfParams, Key, SALT_SIZE},
     password::Password,
+    record::label,
 };
 #[cfg(feature = "db")]
-use sea_orm::{ActiveValue::Set, QuerySelect, entity::prelude::*};
-use std::{fmt::Debug, fmt::Formatter};
+use sea_orm::{ActiveValue::Set, PaginatorTrait, QueryOrder, QuerySelect, entity::prelude::*};
+use std::{fmt::Debug, fmt::Formatter, time::Duration};
+use subtle::ConstantTimeEq;
+#[cfg(feature = "db")]
+use zeroize::Zeroizing;
 
 const VALIDATION: &[u8] = b"VALID";
 
+/// Compare a decrypted validation string without short-circuiting on the
+/// first differing byte.
+fn is_validation(decrypted: &[u8]) -> bool {
+    decrypted.ct_eq(VALIDATION).into()
+}
+
 /// A user of valet, who is uniquely identified by username.
 ///
 /// As is standard practice with password handling, the user's password is never
@@ -39,33 +52,304 @@ pub struct User {
     salt: [u8; SALT_SIZE],
     validation: Encrypted,
     key: Key<Self>,
+    key_version: i64,
+    kdf: KdfParams,
+}
+
+/// Plaintext of everything a user's key protects, see
+/// [`User::unwrap_secrets`].
+#[cfg(feature = "db")]
+struct Secrets {
+    totp: Option<Zeroizing<Vec<u8>>>,
+    grants: Vec<(Uuid<Lot>, String, Key<Lot>, Permission)>,
 }
 
 impl User {
+    /// A new user whose key is derived with the default [`KdfParams`].
     pub fn new(username: &str, password: Password) -> Result<Self, Error> {
+        Self::with_kdf(username, password, KdfParams::default())
+    }
+
+    /// A new user whose key is derived with `kdf`, usually picked by
+    /// [`KdfParams::calibrate`] at registration. The parameters are
+    /// saved with the user and reused for every later unlock.
+    pub fn with_kdf(username: &str, password: Password, kdf: KdfParams) -> Result<Self, Error> {
+        Ok(Self::with_kdf_timed(username, password, kdf)?.0)
+    }
+
+    /// [`User::with_kdf`], also returning how long deriving the key took.
+    /// Unlocking pays about the same, so callers can use it to calibrate a
+    /// progress estimate, see [`Key::from_password_timed`].
+    pub fn with_kdf_timed(
+        username: &str,
+        password: Password,
+        kdf: KdfParams,
+    ) -> Result<(Self, Duration), Error> {
+        #[cfg(feature = "strength")]
+        encrypt::warn_if_weak(&password, "user");
         let salt = encrypt::generate_salt();
-        let key = Key::from_password(&password, &salt)?;
+        let (key, elapsed) = Key::from_password_timed(&password, &salt, &kdf)?;
         let validation = key.encrypt_with_aad(VALIDATION, User::aad(username))?;
-        Ok(User {
+        let user = User {
             username: username.into(),
             salt,
             validation,
             key,
-        })
+            key_version: 0,
+            kdf,
+        };
+        Ok((user, elapsed))
+    }
+
+    /// The key derivation cost this user was registered with.
+    pub fn kdf(&self) -> &KdfParams {
+        &self.kdf
     }
 
     #[cfg(feature = "db")]
     pub async fn register(self, db: &Database) -> Result<Self, Error> {
-        let active = self::orm::ActiveModel {
+        db.check_writable()?;
+        db.retry(async || {
+            self::orm::Entity::insert(self.active_model())
+                .exec(db.connection())
+                .await
+        })
+        .await?;
+        Ok(self)
+    }
+
+    /// [`User::register`] and save an empty [`DEFAULT_LOT`] for the new
+    /// user, so every account starts out with somewhere to put records.
+    ///
+    /// [`DEFAULT_LOT`]: crate::lot::DEFAULT_LOT
+    #[cfg(feature = "db")]
+    pub async fn register_with_default_lot(self, db: &Database) -> Result<(Self, Lot), Error> {
+        let user = self.register(db).await?;
+        let mut lot = Lot::new(lot::DEFAULT_LOT);
+        lot.save(db, &user).await?;
+        Ok((user, lot))
+    }
+
+    #[cfg(feature = "db")]
+    fn active_model(&self) -> self::orm::ActiveModel {
+        self::orm::ActiveModel {
             username: Set(self.username.clone()),
             salt: Set(self.salt.to_vec()),
             validation_data: Set(self.validation.data.clone()),
             validation_nonce: Set(self.validation.nonce.clone()),
+            key_version: Set(self.key_version),
+            kdf_memory: Set(self.kdf.memory_kib.into()),
+            kdf_iterations: Set(self.kdf.iterations.into()),
+            kdf_parallelism: Set(self.kdf.parallelism.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Change this user's username, keeping their password and lots.
+    ///
+    /// The username is bound into the AAD of the validation string, the
+    /// TOTP secret and every `user_lots` grant, so each is re-encrypted
+    /// for the new name. Everything is written in one transaction: a new
+    /// `users` row, the re-wrapped grants, then removal of the old row.
+    /// Returns [`Error::UsernameTaken`] if `new_username` is already
+    /// registered.
+    #[cfg(feature = "db")]
+    pub async fn rename(self, db: &Database, new_username: &str) -> Result<Self, Error> {
+        db.check_writable()?;
+        if self::orm::Entity::find_by_id(new_username.to_owned())
+            .one(db.connection())
+            .await?
+            .is_some()
+        {
+            return Err(Error::UsernameTaken);
+        }
+
+        let secrets = self.unwrap_secrets(db).await?;
+        let old_username = self.username;
+        let validation = self
+            .key
+            .encrypt_with_aad(VALIDATION, User::aad(new_username))?;
+        let renamed = User {
+            username: new_username.into(),
+            salt: self.salt,
+            validation,
+            key: self.key,
+            key_version: self.key_version,
+            kdf: self.kdf,
         };
-        self::orm::Entity::insert(active)
-            .exec(db.connection())
+        let mut active = renamed.active_model();
+        renamed.seal_totp(&mut active, secrets.totp.as_deref())?;
+
+        db.transaction(async |txn| -> Result<(), Error> {
+            self::orm::Entity::insert(active.clone()).exec(txn).await?;
+            for (uuid, name, key, permission) in &secrets.grants {
+                let active = Lot::grant(key, uuid, name, &renamed, *permission)?;
+                lot::orm::user_lots::Entity::insert(active)
+                    .exec(txn)
+                    .await?;
+            }
+            // Cascades to the old user_lots rows.
+            self::orm::Entity::delete_by_id(old_username.clone())
+                .exec(txn)
+                .await?;
+            Ok(())
+        })
+        .await?;
+        Ok(renamed)
+    }
+
+    /// Replace this user's password.
+    ///
+    /// Derives a new user key under a fresh salt and re-wraps the
+    /// validation string, the TOTP secret and every `user_lots` grant
+    /// under it, in one transaction. Lot keys themselves are unchanged,
+    /// so no record is re-encrypted. Bumps [`User::key_version`], which
+    /// is how other sessions still holding the old key find out, see
+    /// [`User::is_stale`].
+    #[cfg(feature = "db")]
+    pub async fn change_password(
+        self,
+        db: &Database,
+        new_password: Password,
+    ) -> Result<Self, Error> {
+        let kdf = self.kdf;
+        self.rekey(db, new_password, kdf).await
+    }
+
+    /// Re-derive this user's key from their unchanged `password` under a
+    /// fresh salt, at no less than the current [`KdfParams::default`]
+    /// cost, and re-wrap everything under it as [`User::change_password`]
+    /// does. For users who [`User::needs_rehash`]. A wrong password is
+    /// [`Error::Invalid`].
+    #[cfg(feature = "db")]
+    pub async fn rehash(self, db: &Database, password: Password) -> Result<Self, Error> {
+        if Key::<Self>::from_password_with(&password, &self.salt, &self.kdf)? != self.key {
+            return Err(Error::Invalid);
+        }
+        let default = KdfParams::default();
+        let kdf = KdfParams {
+            memory_kib: self.kdf.memory_kib.max(default.memory_kib),
+            iterations: self.kdf.iterations.max(default.iterations),
+            parallelism: self.kdf.parallelism,
+        };
+        self.rekey(db, password, kdf).await
+    }
+
+    /// True if this user's key was derived at less than the current
+    /// [`KdfParams::default`] cost, so they should be [`User::rehash`]ed
+    /// while their password is at hand.
+    pub fn needs_rehash(&self) -> bool {
+        let default = KdfParams::default();
+        self.kdf.memory_kib < default.memory_kib || self.kdf.iterations < default.iterations
+    }
+
+    /// Replace the user key with one derived from `password` under `kdf`
+    /// and a fresh salt, for [`User::change_password`] and
+    /// [`User::rehash`].
+    #[cfg(feature = "db")]
+    async fn rekey(self, db: &Database, password: Password, kdf: KdfParams) -> Result<Self, Error> {
+        db.check_writable()?;
+        if self.is_stale(db).await? {
+            return Err(Error::StaleKey);
+        }
+        let secrets = self.unwrap_secrets(db).await?;
+
+        let mut changed = User::with_kdf(&self.username, password, kdf)?;
+        changed.key_version = self.key_version + 1;
+        let mut active = changed.active_model();
+        active.username = sea_orm::ActiveValue::Unchanged(changed.username.clone());
+        changed.seal_totp(&mut active, secrets.totp.as_deref())?;
+
+        db.transaction(async |txn| -> Result<(), Error> {
+            self::orm::Entity::update(active.clone()).exec(txn).await?;
+            for (uuid, name, key, permission) in &secrets.grants {
+                let active = Lot::grant(key, uuid, name, &changed, *permission)?;
+                lot::orm::user_lots::Entity::update(active)
+                    .exec(txn)
+                    .await?;
+            }
+            // Sealed under the old key, see `User::generate_recovery`.
+            self::orm::recovery::Entity::delete_by_id(changed.username.clone())
+                .exec(txn)
+                .await?;
+            Ok(())
+        })
+        .await?;
+        Ok(changed)
+    }
+
+    /// Bumped every time [`User::change_password`] replaces the user key.
+    pub fn key_version(&self) -> i64 {
+        self.key_version
+    }
+
+    /// True if this user's key is no longer the current one: the password
+    /// was changed (possibly by another session) since it was derived, or
+    /// the user is gone. Long-lived sessions poll this and drop the user
+    /// when it flips.
+    #[cfg(feature = "db")]
+    pub async fn is_stale(&self, db: &Database) -> Result<bool, Error> {
+        Ok(User::current_key_version(db, &self.username).await? != Some(self.key_version))
+    }
+
+    /// The stored [`User::key_version`] for `username`, `None` if the
+    /// user is gone. [`User::is_stale`] for when there's no [`User`] at
+    /// hand.
+    #[cfg(feature = "db")]
+    pub async fn current_key_version(db: &Database, username: &str) -> Result<Option<i64>, Error> {
+        Ok(self::orm::Entity::find_by_id(username.to_owned())
+            .select_only()
+            .column(self::orm::Column::KeyVersion)
+            .into_tuple()
+            .one(db.connection())
+            .await?)
+    }
+
+    /// Decrypt everything stored under this user's key, ready to be
+    /// re-encrypted by [`User::rename`] or [`User::change_password`].
+    #[cfg(feature = "db")]
+    async fn unwrap_secrets(&self, db: &Database) -> Result<Secrets, Error> {
+        let model = self::orm::Entity::find_by_id(self.username.clone())
+            .one(db.connection())
+            .await?
+            .ok_or(Error::NotFound)?;
+        let totp = match (model.totp_data, model.totp_nonce) {
+            (Some(data), Some(nonce)) => Some(self.key.decrypt_with_aad(
+                &Encrypted::from_parts(data, nonce)?,
+                &User::totp_aad(&self.username),
+            )?),
+            _ => None,
+        };
+
+        let uls = lot::orm::user_lots::Entity::find()
+            .filter(lot::orm::user_lots::Column::Username.eq(self.username()))
+            .all(db.connection())
             .await?;
-        Ok(self)
+        let mut grants = Vec::with_capacity(uls.len());
+        for ul in uls {
+            let uuid = Uuid::<Lot>::parse(&ul.lot_uuid).map_err(lot::Error::from)?;
+            let key = Lot::unwrap_key(self, &uuid, &ul)?;
+            let permission = Lot::grant_permission(&ul);
+            grants.push((uuid, ul.name, key, permission));
+        }
+        Ok(Secrets { totp, grants })
+    }
+
+    /// Encrypt `secret` (if any) as this user's TOTP secret into `active`.
+    #[cfg(feature = "db")]
+    fn seal_totp(
+        &self,
+        active: &mut self::orm::ActiveModel,
+        secret: Option<&[u8]>,
+    ) -> Result<(), Error> {
+        if let Some(secret) = secret {
+            let encrypted = self
+                .key
+                .encrypt_with_aad(secret, &User::totp_aad(&self.username))?;
+            active.totp_data = Set(Some(encrypted.data));
+            active.totp_nonce = Set(Some(encrypted.nonce));
+        }
+        Ok(())
     }
 
     pub fn username(&self) -> &str {
@@ -81,39 +365,211 @@ impl User {
             .key()
             .decrypt_with_aad(&self.validation, User::aad(&self.username))
         {
-            v == VALIDATION // This should never be false.
+            is_validation(&v) // This should never be false.
         } else {
             false
         }
     }
 
+    /// Unlock a user with their password.
+    ///
+    /// Users who have [enrolled](User::enroll_totp) a TOTP second factor
+    /// must use [`User::load_with_totp`] instead. Here they get
+    /// [`Error::Invalid`] whatever the password, so a right one isn't
+    /// given away without the code.
     #[cfg(feature = "db")]
     pub async fn load<'a>(
         db: &'a Database,
         username: &'a str,
         password: Password,
     ) -> Result<Self, Error> {
+        match Self::unlock(db, username, password).await? {
+            (user, None) => Ok(user),
+            (_, Some(_)) => Err(Error::Invalid),
+        }
+    }
+
+    /// Unlock a user with their password and, if they have enrolled one,
+    /// a current TOTP `code`.
+    ///
+    /// The TOTP secret is encrypted under the user key, so the code can
+    /// only be checked after the (deliberately slow) key derivation. A
+    /// wrong code is [`Error::Invalid`], like a wrong password, so neither
+    /// factor can be guessed on its own. Users without TOTP ignore `code`
+    /// and unlock as with [`User::load`].
+    #[cfg(feature = "db")]
+    pub async fn load_with_totp(
+        db: &Database,
+        username: &str,
+        password: Password,
+        code: &str,
+    ) -> Result<Self, Error> {
+        let (user, totp) = Self::unlock(db, username, password).await?;
+        if let Some(encrypted) = totp {
+            let mut secret = user
+                .key
+                .decrypt_with_aad(&encrypted, &User::totp_aad(&user.username))?;
+            if !Totp::new(std::mem::take(&mut *secret)).verify(code) {
+                return Err(Error::Invalid);
+            }
+        }
+        Ok(user)
+    }
+
+    /// Derive the key and validate it, returning the still encrypted
+    /// TOTP secret if the user has one.
+    ///
+    /// An unknown username is [`Error::Invalid`] too, after deriving a
+    /// throwaway key with the default [`KdfParams`], so neither the error
+    /// nor (for users on the defaults) the time taken tells it apart from
+    /// a wrong password.
+    #[cfg(feature = "db")]
+    async fn unlock(
+        db: &Database,
+        username: &str,
+        password: Password,
+    ) -> Result<(Self, Option<Encrypted>), Error> {
+        let Some(model) = db
+            .retry(async || {
+                self::orm::Entity::find_by_id(username.to_owned())
+                    .one(db.connection())
+                    .await
+            })
+            .await?
+        else {
+            Key::<Self>::from_password_with(&password, &[0; SALT_SIZE], &KdfParams::default())?;
+            return Err(Error::Invalid);
+        };
+
+        let credential = Credential::from_model(&model, &password)?;
+        Self::unlock_with(credential, model)
+    }
+
+    /// Check `credential` against `model`'s validation string, returning
+    /// the user and their still encrypted TOTP secret.
+    #[cfg(feature = "db")]
+    fn unlock_with(
+        credential: Credential,
+        model: self::orm::Model,
+    ) -> Result<(Self, Option<Encrypted>), Error> {
+        let validation = Encrypted::from_parts(model.validation_data, model.validation_nonce)?;
+        if !credential.validate(&validation) {
+            return Err(Error::Invalid);
+        }
+        let user = credential.into_user(validation, model.key_version);
+        let totp = match (model.totp_data, model.totp_nonce) {
+            (Some(data), Some(nonce)) => Some(Encrypted::from_parts(data, nonce)?),
+            _ => None,
+        };
+        Ok((user, totp))
+    }
+
+    /// Unlock a user with a [`Credential`] derived earlier, e.g. one
+    /// already used to check the password, without deriving the key
+    /// again. Otherwise the same as [`User::load`].
+    #[cfg(feature = "db")]
+    pub async fn from_credential(db: &Database, credential: Credential) -> Result<Self, Error> {
+        let model = self::orm::Entity::find_by_id(credential.username().to_owned())
+            .one(db.connection())
+            .await?
+            .ok_or(Error::Invalid)?;
+        match Self::unlock_with(credential, model)? {
+            (user, None) => Ok(user),
+            (_, Some(_)) => Err(Error::Invalid),
+        }
+    }
+
+    /// Rebuild `username` from a `key` derived earlier, e.g. by a running
+    /// agent, checking it against the stored validation string but skipping
+    /// argon2. A wrong or outdated key is [`Error::Invalid`].
+    ///
+    /// Holding the key is as good as having unlocked, so any TOTP code was
+    /// for whoever derived it to check; none is asked for here.
+    #[cfg(feature = "db")]
+    pub async fn resume(db: &Database, username: &str, key: Key<Self>) -> Result<Self, Error> {
         let model = self::orm::Entity::find_by_id(username.to_owned())
             .one(db.connection())
             .await?
-            .ok_or(Error::NotFound)?;
+            .ok_or(Error::Invalid)?;
+        let credential = Credential::from_key(&model, key)?;
+        Ok(Self::unlock_with(credential, model)?.0)
+    }
 
-        let key = Key::from_password(&password, &model.salt[..])?;
-        let validation = Encrypted {
-            data: model.validation_data,
-            nonce: model.validation_nonce,
-        };
-        let user = User {
-            username: model.username,
-            salt: model.salt.try_into().map_err(|_| Error::SaltError)?,
-            validation,
-            key,
+    /// Require a TOTP code on every future unlock, see
+    /// [`User::load_with_totp`].
+    ///
+    /// Generates a fresh secret, replacing any previous enrollment, and
+    /// returns its generator so the caller can hand the secret to the
+    /// user's authenticator app (e.g. via [`Totp::to_uri`]).
+    #[cfg(feature = "db")]
+    pub async fn enroll_totp(&self, db: &Database) -> Result<Totp, Error> {
+        let totp = Totp::generate();
+        let encrypted = self
+            .key
+            .encrypt_with_aad(totp.secret(), &User::totp_aad(&self.username))?;
+        self.set_totp(db, Some(encrypted)).await?;
+        Ok(totp)
+    }
+
+    /// Stop requiring a TOTP code to unlock.
+    #[cfg(feature = "db")]
+    pub async fn disable_totp(&self, db: &Database) -> Result<(), Error> {
+        self.set_totp(db, None).await
+    }
+
+    #[cfg(feature = "db")]
+    async fn set_totp(&self, db: &Database, totp: Option<Encrypted>) -> Result<(), Error> {
+        db.check_writable()?;
+        let (data, nonce) = totp.map(|e| (e.data, e.nonce)).unzip();
+        self::orm::Entity::update(self::orm::ActiveModel {
+            username: sea_orm::ActiveValue::Unchanged(self.username.clone()),
+            totp_data: Set(data),
+            totp_nonce: Set(nonce),
+            ..Default::default()
+        })
+        .exec(db.connection())
+        .await?;
+        Ok(())
+    }
+
+    /// Check `password` against the stored validation string for
+    /// `username` without building a [`User`], e.g. to re-authenticate
+    /// before something destructive like [`User::delete`].
+    ///
+    /// Still pays for the key derivation, but skips everything
+    /// [`User::load`] would go on to do with the key. Returns
+    /// `Ok(false)` for a wrong password, where `load` would fail with
+    /// [`Error::Invalid`]. An unknown user is `Ok(false)` too, after
+    /// deriving a throwaway key as [`User::load`] does.
+    #[cfg(feature = "db")]
+    pub async fn verify_password(
+        db: &Database,
+        username: &str,
+        password: Password,
+    ) -> Result<bool, Error> {
+        let Some(model) = self::orm::Entity::find_by_id(username.to_owned())
+            .one(db.connection())
+            .await?
+        else {
+            Key::<Self>::from_password_with(&password, &[0; SALT_SIZE], &KdfParams::default())?;
+            return Ok(false);
         };
-        if user.validate() {
-            Ok(user)
-        } else {
-            Err(Error::Invalid)
-        }
+        let credential = Credential::from_model(&model, &password)?;
+        let validation = Encrypted::from_parts(model.validation_data, model.validation_nonce)?;
+        Ok(credential.validate(&validation))
+    }
+
+    /// A fast "does this password look right" check, e.g. for the GUI
+    /// before it starts a full session with [`User::load`]. The same
+    /// check as [`User::verify_password`]: only the `users` row is read,
+    /// no lot is loaded or decrypted.
+    #[cfg(feature = "db")]
+    pub async fn quick_validate(
+        db: &Database,
+        username: &str,
+        password: Password,
+    ) -> Result<bool, Error> {
+        User::verify_password(db, username, password).await
     }
 
     /// Load all of this user's lots.
@@ -128,9 +584,206 @@ impl User {
         Ok(Lot::load_all(db, self).await?)
     }
 
-    /// Return the list of registered usernames from the database.
+    /// Load only the lots whose [`LotSummary`] matches `pred`, e.g. just
+    /// "main" at startup.
+    ///
+    /// The predicate sees [`User::lots_summary`], so lots it rejects are
+    /// never unwrapped or decrypted. Sorted by name.
+    #[cfg(feature = "db")]
+    pub async fn lots_where(
+        &self,
+        db: &Database,
+        pred: impl Fn(&LotSummary) -> bool,
+    ) -> Result<Vec<Lot>, Error> {
+        let mut lots = Vec::new();
+        for summary in self.lots_summary(db).await?.iter().filter(|s| pred(s)) {
+            if let Some(lot) = Lot::load(db, &summary.name, self).await? {
+                lots.push(lot);
+            }
+        }
+        Ok(lots)
+    }
+
+    /// Load all of this user's lots arranged by nesting, see
+    /// [`LotTree::build`].
+    #[cfg(feature = "db")]
+    pub async fn lot_tree(&self, db: &Database) -> Result<Vec<LotTree>, Error> {
+        Ok(LotTree::build(self.lots(db).await?))
+    }
+
+    /// Give `grantee` `permission` on each of `lot_uuids`, see
+    /// [`Lot::share`].
+    ///
+    /// Every lot key this user can unwrap is wrapped to `grantee` and the
+    /// grants are written in a single transaction. Lots that can't be
+    /// shared (not this user's, or a name `grantee` already uses) are
+    /// skipped and reported together in [`Error::PartialShare`] after the
+    /// rest are committed.
+    #[cfg(feature = "db")]
+    pub async fn share_lots(
+        &self,
+        db: &Database,
+        grantee: &User,
+        lot_uuids: &[Uuid<Lot>],
+        permission: Permission,
+    ) -> Result<(), Error> {
+        let mut grants = Vec::with_capacity(lot_uuids.len());
+        let mut events = Vec::with_capacity(lot_uuids.len());
+        let mut failures = Vec::new();
+        for uuid in lot_uuids {
+            match Lot::grant_from(db, self, grantee, uuid, permission).await {
+                Ok(active) => {
+                    grants.push(active);
+                    events.push(db::VaultEvent::LotGranted { lot: uuid.clone() });
+                }
+                Err(e) => failures.push((uuid.clone(), e)),
+            }
+        }
+        db.transaction(async |txn| -> Result<(), Error> {
+            for active in &grants {
+                lot::orm::user_lots::Entity::insert(active.clone())
+                    .exec(txn)
+                    .await?;
+            }
+            Ok(Database::log(txn, &events).await?)
+        })
+        .await?;
+        db.notify(events);
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::PartialShare(failures))
+        }
+    }
+
+    /// Every record, across all of this user's lots, tagged `tag`, see
+    /// [`Lot::records_with_tag`].
     #[cfg(feature = "db")]
-    pub async fn list(db: &Database) -> Result<Vec<String>, Error> {
+    pub async fn records_with_tag(&self, db: &Database, tag: &str) -> Result<Vec<Record>, Error> {
+        let mut records = Vec::new();
+        for lot in self.lots(db).await? {
+            records.extend(lot.records_with_tag(tag).await?);
+        }
+        Ok(records)
+    }
+
+    /// Summarise this user's lots without opening any of them.
+    ///
+    /// Names come straight from the `user_lots` table and record counts
+    /// from one grouped `COUNT` over `records`, so no lot key is
+    /// unwrapped and no ciphertext is decrypted. Prefer this over
+    /// [`User::lots`] when only a listing is needed. Sorted by name.
+    #[cfg(feature = "db")]
+    pub async fn lots_summary(&self, db: &Database) -> Result<Vec<LotSummary>, Error> {
+        let uls = lot::orm::user_lots::Entity::find()
+            .filter(lot::orm::user_lots::Column::Username.eq(self.username()))
+            .order_by_asc(lot::orm::user_lots::Column::Name)
+            .all(db.connection())
+            .await?;
+        let counts: std::collections::HashMap<String, i64> = record::orm::Entity::find()
+            .filter(record::orm::Column::LotUuid.is_in(uls.iter().map(|ul| ul.lot_uuid.as_str())))
+            .select_only()
+            .column(record::orm::Column::LotUuid)
+            .column_as(record::orm::Column::Uuid.count(), "record_count")
+            .group_by(record::orm::Column::LotUuid)
+            .into_tuple::<(String, i64)>()
+            .all(db.connection())
+            .await?
+            .into_iter()
+            .collect();
+        let mut summaries = Vec::with_capacity(uls.len());
+        for ul in uls {
+            let record_count = counts.get(&ul.lot_uuid).copied().unwrap_or(0) as u64;
+            summaries.push(LotSummary {
+                uuid: Uuid::parse(&ul.lot_uuid).map_err(lot::Error::from)?,
+                name: ul.name,
+                record_count,
+            });
+        }
+        Ok(summaries)
+    }
+
+    /// Remove this user, once `password` is confirmed with
+    /// [`User::verify_password`], and with them every lot no one else
+    /// has a grant for. Lots shared with other users are left to them.
+    /// A wrong password is [`Error::Invalid`].
+    #[cfg(feature = "db")]
+    pub async fn delete(self, db: &Database, password: Password) -> Result<(), Error> {
+        db.check_writable()?;
+        if !User::verify_password(db, &self.username, password).await? {
+            return Err(Error::Invalid);
+        }
+        let lot_uuids: Vec<String> = lot::orm::user_lots::Entity::find()
+            .filter(lot::orm::user_lots::Column::Username.eq(self.username()))
+            .select_only()
+            .column(lot::orm::user_lots::Column::LotUuid)
+            .into_tuple()
+            .all(db.connection())
+            .await?;
+
+        let events = db
+            .transaction(async |txn| -> Result<Vec<db::VaultEvent>, Error> {
+                // Cascades to this user's user_lots rows.
+                self::orm::Entity::delete_by_id(self.username.clone())
+                    .exec(txn)
+                    .await?;
+                let mut deleted = Vec::new();
+                for uuid in &lot_uuids {
+                    if User::lot_tree_has_members(txn, uuid).await? {
+                        continue;
+                    }
+                    // Cascades to the lot's records and child lots, none of
+                    // which anyone else can open either.
+                    let result = lot::orm::Entity::delete_by_id(uuid.as_str())
+                        .exec(txn)
+                        .await?;
+                    // Already gone if it was a child of a lot deleted before
+                    // it.
+                    if result.rows_affected > 0 {
+                        let lot = Uuid::<Lot>::parse(uuid).map_err(lot::Error::from)?;
+                        deleted.push(db::VaultEvent::LotDeleted { lot });
+                    }
+                }
+                Database::log(txn, &deleted).await?;
+                Ok(deleted)
+            })
+            .await?;
+        db.notify(events);
+        Ok(())
+    }
+
+    /// Whether anyone has a grant for the lot `uuid` or any lot nested
+    /// under it, all of which deleting it would take with it.
+    #[cfg(feature = "db")]
+    async fn lot_tree_has_members(
+        txn: &sea_orm::DatabaseTransaction,
+        uuid: &str,
+    ) -> Result<bool, Error> {
+        let mut pending = vec![uuid.to_owned()];
+        while let Some(uuid) = pending.pop() {
+            let members = lot::orm::user_lots::Entity::find()
+                .filter(lot::orm::user_lots::Column::LotUuid.eq(uuid.as_str()))
+                .count(txn)
+                .await?;
+            if members > 0 {
+                return Ok(true);
+            }
+            let children: Vec<String> = lot::orm::Entity::find()
+                .filter(lot::orm::Column::ParentUuid.eq(uuid.as_str()))
+                .select_only()
+                .column(lot::orm::Column::Uuid)
+                .into_tuple()
+                .all(txn)
+                .await?;
+            pending.extend(children);
+        }
+        Ok(false)
+    }
+
+    /// Return the list of registered usernames from the database. Outside
+    /// the crate, see [`User::all_usernames`].
+    #[cfg(feature = "db")]
+    pub(crate) async fn list(db: &Database) -> Result<Vec<String>, Error> {
         self::orm::Entity::find()
             .select_only()
             .column(self::orm::Column::Username)
@@ -140,9 +793,51 @@ impl User {
             .map_err(Into::into)
     }
 
+    /// Every registered username, in order, for admin tooling over a
+    /// database shared by many users. Nothing but the names is read, no
+    /// keys or salts.
+    #[cfg(feature = "db")]
+    pub async fn all_usernames(db: &AdminDatabase) -> Result<Vec<String>, Error> {
+        self::orm::Entity::find()
+            .select_only()
+            .column(self::orm::Column::Username)
+            .order_by_asc(self::orm::Column::Username)
+            .into_tuple::<String>()
+            .all(db.database().connection())
+            .await
+            .map_err(Into::into)
+    }
+
     fn aad(username: &str) -> &[u8] {
         username.as_bytes()
     }
+
+    #[cfg(feature = "db")]
+    fn model_kdf(model: &self::orm::Model) -> Result<KdfParams, Error> {
+        let param = |value: i64| {
+            u32::try_from(value).map_err(|_| {
+                encrypt::Error::KeyDerivation(format!("invalid kdf parameter {value}"))
+            })
+        };
+        Ok(KdfParams {
+            memory_kib: param(model.kdf_memory)?,
+            iterations: param(model.kdf_iterations)?,
+            parallelism: param(model.kdf_parallelism)?,
+        })
+    }
+
+    /// AAD for the `users.totp_data` ciphertext. The `b"t"` prefix keeps
+    /// it from authenticating as the validation string.
+    #[cfg(feature = "db")]
+    fn totp_aad(username: &str) -> Vec<u8> {
+        [b"t".as_slice(), username.as_bytes()].concat()
+    }
+
+    /// AAD for [`Credential::encrypt`]. The `b"c"` prefix keeps it apart
+    /// from the validation string and TOTP secret.
+    fn credential_aad(username: &str) -> Vec<u8> {
+        [b"c".as_slice(), username.as_bytes()].concat()
+    }
 }
 
 impl Debug for User {
@@ -157,12 +852,77 @@ impl Debug for User {
 pub enum Error {
     NotFound,
     Invalid,
+    /// [`User::rename`] target is already registered.
+    UsernameTaken,
+    /// This user's key was replaced by a [`User::change_password`]
+    /// elsewhere; unlock again.
+    StaleKey,
     SaltError,
+    /// A plaintext password was longer than [`MAX_LENGTH`].
+    ///
+    /// [`MAX_LENGTH`]: crate::password::MAX_LENGTH
+    PasswordTooLong(crate::password::TooLong),
+    /// Not the words of a [`RecoveryPhrase`].
+    #[cfg(feature = "db")]
+    InvalidRecoveryPhrase,
+    Label(label::Error),
     Encrypt(encrypt::Error),
     #[cfg(feature = "db")]
     Database(db::Error),
     #[cfg(feature = "db")]
     Lot(lot::Error),
+    /// Some lots passed to [`User::share_lots`] couldn't be shared; the
+    /// rest were.
+    #[cfg(feature = "db")]
+    PartialShare(Vec<(Uuid<Lot>, lot::Error)>),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NotFound => write!(f, "user not found"),
+            Error::Invalid => write!(f, "invalid username or password"),
+            Error::UsernameTaken => write!(f, "username taken"),
+            Error::StaleKey => write!(f, "user key is stale, unlock again"),
+            Error::SaltError => write!(f, "invalid salt"),
+            Error::PasswordTooLong(e) => write!(f, "{e}"),
+            #[cfg(feature = "db")]
+            Error::InvalidRecoveryPhrase => write!(f, "invalid recovery phrase"),
+            Error::Label(e) => write!(f, "label: {e}"),
+            Error::Encrypt(e) => write!(f, "encryption: {e}"),
+            #[cfg(feature = "db")]
+            Error::Database(e) => write!(f, "database: {e}"),
+            #[cfg(feature = "db")]
+            Error::Lot(e) => write!(f, "lot: {e}"),
+            #[cfg(feature = "db")]
+            Error::PartialShare(failed) => write!(f, "{} lots couldn't be shared", failed.len()),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    /// For [`Error::PartialShare`], the first lot's failure.
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Label(e) => Some(e),
+            Error::Encrypt(e) => Some(e),
+            #[cfg(feature = "db")]
+            Error::Database(e) => Some(e),
+            #[cfg(feature = "db")]
+            Error::Lot(e) => Some(e),
+            #[cfg(feature = "db")]
+            Error::PartialShare(failed) => failed
+                .first()
+                .map(|(_, e)| e as &(dyn std::error::Error + 'static)),
+            _ => None,
+        }
+    }
+}
+
+impl From<crate::password::TooLong> for Error {
+    fn from(err: crate::password::TooLong) -> Self {
+        Error::PasswordTooLong(err)
+    }
 }
 
 impl From<encrypt::Error> for Error {
@@ -171,6 +931,12 @@ impl From<encrypt::Error> for Error {
     }
 }
 
+impl From<label::Error> for Error {
+    fn from(err: label::Error) -> Self {
+        Error::Label(err)
+    }
+}
+
 #[cfg(feature = "db")]
 impl From<db::Error> for Error {
     fn from(err: db::Error) -> Self {
@@ -192,6 +958,19 @@ impl From<lot::Error> for Error {
     }
 }
 
+#[cfg(feature = "db")]
+mod audit;
+mod credential;
+#[cfg(feature = "db")]
+mod export;
+#[cfg(feature = "db")]
+mod recovery;
+pub use self::credential::Credential;
+#[cfg(feature = "db")]
+pub use self::export::{LotExport, RecordExport, UserExport};
+#[cfg(feature = "db")]
+pub use self::recovery::{RecoveryBlob, RecoveryPhrase};
+
 #[cfg(all(feature = "db", feature = "orm"))]
 pub mod orm;
 #[cfg(all(feature = "db", not(feature = "orm")))]
@@ -201,7 +980,10 @@ pub(crate) mod orm;
 mod tests {
     use super::*;
     #[cfg(feature = "db")]
-    use crate::db::Database;
+    use crate::{
+        db::Database,
+        record::{Data, Label, Record},
+    };
     use std::time::{Duration, Instant};
 
     #[test]
@@ -224,6 +1006,36 @@ mod tests {
         assert!(!user.validate());
     }
 
+    #[test]
+    fn is_validation_ct() {
+        assert!(is_validation(VALIDATION));
+        assert!(!is_validation(b"VALIX"));
+        assert!(!is_validation(b"VALI"));
+        assert!(!is_validation(b""));
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn load_uniform_error() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let wrong = User::load(&db, "alice", "hunter22".try_into().unwrap())
+            .await
+            .expect_err("loaded with the wrong password");
+        let unknown = User::load(&db, "bob", "password".try_into().unwrap())
+            .await
+            .expect_err("loaded an unknown user");
+        assert!(matches!(wrong, Error::Invalid));
+        assert!(matches!(unknown, Error::Invalid));
+        assert_eq!(wrong.to_string(), unknown.to_string());
+    }
+
     #[test]
     fn new_is_slow() {
         let start = Instant::now();
@@ -255,24 +1067,246 @@ mod tests {
 
     #[cfg(feature = "db")]
     #[tokio::test(flavor = "multi_thread")]
-    async fn lots() {
+    async fn verify_password() {
         let db = Database::new("sqlite://:memory:")
             .await
             .expect("failed to create database");
-        let user = User::new("nixpulvis", "password".try_into().unwrap())
-            .expect("failed to make user")
+        User::new("alice", "password".try_into().unwrap())
+            .expect("failed to create user")
             .register(&db)
             .await
             .expect("failed to register user");
-        let mut lot_a = Lot::new("lot a");
-        lot_a.save(&db, &user).await.expect("failed to save lot");
-        let mut lot_b = Lot::new("lot b");
-        lot_b.save(&db, &user).await.expect("failed to save lot");
+
+        assert!(
+            User::verify_password(&db, "alice", "password".try_into().unwrap())
+                .await
+                .expect("failed to validate")
+        );
+        assert!(
+            !User::verify_password(&db, "alice", "hunter22".try_into().unwrap())
+                .await
+                .expect("failed to validate")
+        );
+        assert!(
+            !User::verify_password(&db, "bob", "password".try_into().unwrap())
+                .await
+                .expect("failed to validate")
+        );
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn quick_validate() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (user, _) = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to create user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        // Clobber every lot and grant; opening any of them would fail.
+        lot::orm::Entity::update_many()
+            .col_expr(
+                lot::orm::Column::Store,
+                sea_orm::sea_query::Expr::value(vec![0u8; 4]),
+            )
+            .exec(db.connection())
+            .await
+            .expect("failed to clobber lots");
+        lot::orm::user_lots::Entity::update_many()
+            .col_expr(
+                lot::orm::user_lots::Column::Data,
+                sea_orm::sea_query::Expr::value(vec![0u8; 4]),
+            )
+            .exec(db.connection())
+            .await
+            .expect("failed to clobber grants");
+        assert!(user.lots(&db).await.is_err());
+        let mut events = db.subscribe();
+
+        assert!(
+            User::quick_validate(&db, "alice", "password".try_into().unwrap())
+                .await
+                .expect("failed to validate")
+        );
+        assert!(
+            !User::quick_validate(&db, "alice", "hunter22".try_into().unwrap())
+                .await
+                .expect("failed to validate")
+        );
+        assert!(
+            !User::quick_validate(&db, "bob", "password".try_into().unwrap())
+                .await
+                .expect("failed to validate")
+        );
+        assert!(events.try_recv().is_err());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lots() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot_a = Lot::new("lot a");
+        lot_a.save(&db, &user).await.expect("failed to save lot");
+        let mut lot_b = Lot::new("lot b");
+        lot_b.save(&db, &user).await.expect("failed to save lot");
 
         let lots = user.lots(&db).await.expect("failed to load lots");
         assert_eq!(lots, vec![lot_a, lot_b]);
     }
 
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lots_where() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut uuids = Vec::new();
+        for name in ["work a", "home", "work b"] {
+            let mut lot = Lot::new(name);
+            uuids.push(lot.save(&db, &user).await.expect("failed to save lot"));
+        }
+
+        let work = user
+            .lots_where(&db, |s| s.name.starts_with("work"))
+            .await
+            .expect("failed to load lots");
+        let work: Vec<_> = work.iter().map(|lot| lot.uuid().clone()).collect();
+        assert_eq!(work, vec![uuids[0].clone(), uuids[2].clone()]);
+        let none = user
+            .lots_where(&db, |_| false)
+            .await
+            .expect("failed to load lots");
+        assert!(none.is_empty());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lots_summary() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("nixpulvis", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot_a = Lot::new("lot a");
+        lot_a.save(&db, &user).await.expect("failed to save lot");
+        for name in ["a", "b"] {
+            Record::new(
+                &lot_a,
+                name.parse::<Label>().unwrap(),
+                Data::new("password".try_into().unwrap()),
+            )
+            .save(&db, &mut lot_a)
+            .await
+            .expect("failed to save record");
+        }
+        let mut lot_b = Lot::new("lot b");
+        lot_b.save(&db, &user).await.expect("failed to save lot");
+
+        // Clobber every record ciphertext; a summary must not need it.
+        record::orm::Entity::update_many()
+            .col_expr(
+                record::orm::Column::Module,
+                sea_orm::sea_query::Expr::value(vec![0u8; 4]),
+            )
+            .exec(db.connection())
+            .await
+            .expect("failed to clobber records");
+
+        let summaries = user
+            .lots_summary(&db)
+            .await
+            .expect("failed to summarise lots");
+        assert_eq!(
+            summaries,
+            vec![
+                LotSummary {
+                    uuid: lot_a.uuid().clone(),
+                    name: "lot a".into(),
+                    record_count: 2,
+                },
+                LotSummary {
+                    uuid: lot_b.uuid().clone(),
+                    name: "lot b".into(),
+                    record_count: 0,
+                },
+            ]
+        );
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn share_lots() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let alice = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let bob = User::new("bob", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut uuids = Vec::new();
+        for name in ["lot a", "lot b", "lot c"] {
+            let mut lot = Lot::new(name);
+            lot.save(&db, &alice).await.expect("failed to save lot");
+            uuids.push(lot.uuid().clone());
+        }
+
+        alice
+            .share_lots(&db, &bob, &uuids[..2], Permission::Write)
+            .await
+            .expect("failed to share lots");
+        let names: Vec<_> = bob
+            .lots(&db)
+            .await
+            .expect("failed to load lots")
+            .iter()
+            .map(|l| l.name().to_owned())
+            .collect();
+        assert_eq!(names, ["lot a", "lot b"]);
+
+        // One unknown lot and one already shared, alongside one that is fine.
+        let bogus = Uuid::now();
+        let result = alice
+            .share_lots(
+                &db,
+                &bob,
+                &[bogus.clone(), uuids[0].clone(), uuids[2].clone()],
+                Permission::Write,
+            )
+            .await;
+        let failures = match result {
+            Err(Error::PartialShare(failures)) => failures,
+            other => panic!("expected a partial share, got {other:?}"),
+        };
+        assert_eq!(2, failures.len());
+        assert_eq!(bogus, failures[0].0);
+        assert!(matches!(failures[0].1, lot::Error::MissingLotKey));
+        assert!(matches!(failures[1].1, lot::Error::NameTaken(_)));
+        assert_eq!(3, bob.lots(&db).await.expect("failed to load lots").len());
+    }
+
     #[cfg(feature = "db")]
     #[tokio::test(flavor = "multi_thread")]
     async fn list() {
@@ -292,4 +1326,619 @@ mod tests {
         let list = User::list(&db).await.expect("failed to list users");
         assert_eq!(["alice", "bob"], &list[..]);
     }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn resume() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+
+        let key = Key::from_bytes(user.key().as_bytes());
+        let resumed = User::resume(&db, "alice", key)
+            .await
+            .expect("failed to resume");
+        assert_eq!(user, resumed);
+
+        assert!(matches!(
+            User::resume(&db, "alice", Key::generate()).await,
+            Err(Error::Invalid)
+        ));
+        let key = Key::from_bytes(user.key().as_bytes());
+        assert!(matches!(
+            User::resume(&db, "nobody", key).await,
+            Err(Error::Invalid)
+        ));
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn recover() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (user, mut lot) = User::new("alice", "forgotten".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        let uuid = Record::new(
+            &lot,
+            "github".parse::<Label>().unwrap(),
+            Data::new("secret".try_into().unwrap()),
+        )
+        .save(&db, &mut lot)
+        .await
+        .expect("failed to save record");
+        // All zero entropy.
+        let wrong: RecoveryPhrase = format!("{}art", "abandon ".repeat(23))
+            .parse()
+            .expect("failed to parse phrase");
+        assert!(matches!(
+            User::recover(&db, "alice", &wrong, "x".try_into().unwrap()).await,
+            Err(Error::Invalid)
+        ));
+        let (phrase, _) = user
+            .generate_recovery(&db)
+            .await
+            .expect("failed to generate recovery");
+        drop(user);
+        let log = db
+            .audit_log(std::time::UNIX_EPOCH)
+            .await
+            .expect("failed to read log");
+        assert_eq!(
+            Some(&db::VaultEvent::RecoveryGenerated {
+                lot: lot.uuid().clone()
+            }),
+            log.last().map(|entry| &entry.event)
+        );
+
+        let words = phrase.words();
+        assert_eq!(24, words.split(' ').count());
+        assert!(matches!(
+            "not a recovery phrase".parse::<RecoveryPhrase>(),
+            Err(Error::InvalidRecoveryPhrase)
+        ));
+        let parsed: RecoveryPhrase = words
+            .to_uppercase()
+            .parse()
+            .expect("failed to parse phrase");
+        assert!(matches!(
+            User::recover(&db, "alice", &wrong, "x".try_into().unwrap()).await,
+            Err(Error::Invalid)
+        ));
+        let recovered = User::recover(&db, "alice", &parsed, "remembered".try_into().unwrap())
+            .await
+            .expect("failed to recover");
+
+        assert!(matches!(
+            User::load(&db, "alice", "forgotten".try_into().unwrap()).await,
+            Err(Error::Invalid)
+        ));
+        let user = User::load(&db, "alice", "remembered".try_into().unwrap())
+            .await
+            .expect("failed to load with the new password");
+        assert_eq!(recovered, user);
+        let lot = Lot::load(&db, lot::DEFAULT_LOT, &user)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        let record = Record::show(&db, &lot, &uuid)
+            .await
+            .expect("failed to show record")
+            .expect("no record");
+        assert_eq!("secret", record.password().expose());
+
+        // Spent by the password change.
+        assert!(matches!(
+            User::recover(&db, "alice", &parsed, "again".try_into().unwrap()).await,
+            Err(Error::Invalid)
+        ));
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn all_usernames() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        for username in ["bob", "alice"] {
+            User::new(username, "password".try_into().unwrap())
+                .expect("failed to make user")
+                .register(&db)
+                .await
+                .expect("failed to register user");
+        }
+        let admin = AdminDatabase::new(db);
+        let usernames = User::all_usernames(&admin)
+            .await
+            .expect("failed to list users");
+        assert_eq!(["alice", "bob"], &usernames[..]);
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rename() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut main = Lot::new("main");
+        main.save(&db, &user).await.expect("failed to save lot");
+        let mut work = Lot::new("work");
+        work.save(&db, &user).await.expect("failed to save lot");
+        Record::new(
+            &work,
+            "login".parse::<Label>().unwrap(),
+            Data::new("secret".try_into().unwrap()),
+        )
+        .save(&db, &mut work)
+        .await
+        .expect("failed to save record");
+        User::new("bob", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+
+        let user = User::load(&db, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load user");
+        assert!(matches!(
+            user.rename(&db, "bob").await,
+            Err(Error::UsernameTaken)
+        ));
+
+        let user = User::load(&db, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load user");
+        let renamed = user.rename(&db, "carol").await.expect("failed to rename");
+        assert_eq!("carol", renamed.username());
+        assert!(matches!(
+            User::load(&db, "alice", "password".try_into().unwrap()).await,
+            Err(Error::Invalid)
+        ));
+
+        let loaded = User::load(&db, "carol", "password".try_into().unwrap())
+            .await
+            .expect("failed to load renamed user");
+        for lot in [&main, &work] {
+            let reloaded = Lot::load(&db, lot.name(), &loaded)
+                .await
+                .expect("failed to load lot")
+                .expect("no lot");
+            assert_eq!(&reloaded, lot);
+        }
+        let work = Lot::load(&db, "work", &loaded)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        let uuid = work
+            .index()
+            .find_by_name(&"login".parse().unwrap())
+            .cloned()
+            .expect("missing record");
+        let record = Record::show(&db, &work, &uuid)
+            .await
+            .expect("failed to show record")
+            .expect("no record");
+        assert_eq!("secret", record.password().expose());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn totp() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let totp = user.enroll_totp(&db).await.expect("failed to enroll");
+
+        // Without the code even the right password is just invalid.
+        assert!(matches!(
+            User::load(&db, "alice", "password".try_into().unwrap()).await,
+            Err(Error::Invalid)
+        ));
+        let loaded =
+            User::load_with_totp(&db, "alice", "password".try_into().unwrap(), &totp.code())
+                .await
+                .expect("correct code rejected");
+        assert_eq!(user, loaded);
+
+        let expired = totp.code_at(std::time::SystemTime::now() - Duration::from_secs(300));
+        assert!(matches!(
+            User::load_with_totp(&db, "alice", "password".try_into().unwrap(), &expired).await,
+            Err(Error::Invalid)
+        ));
+        assert!(matches!(
+            User::load_with_totp(&db, "alice", "password".try_into().unwrap(), "not a code").await,
+            Err(Error::Invalid)
+        ));
+
+        loaded.disable_totp(&db).await.expect("failed to disable");
+        User::load(&db, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load without totp");
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn kdf_params() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let kdf = KdfParams {
+            memory_kib: 8 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        };
+        let user = User::with_kdf("alice", "password".try_into().unwrap(), kdf)
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        assert_ne!(
+            Key::<User>::from_password(&"password".try_into().unwrap(), &user.salt)
+                .unwrap()
+                .as_bytes(),
+            user.key().as_bytes()
+        );
+
+        let loaded = User::load(&db, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load user");
+        assert_eq!(&kdf, loaded.kdf());
+        assert_eq!(user.key().as_bytes(), loaded.key().as_bytes());
+        assert!(
+            User::verify_password(&db, "alice", "password".try_into().unwrap())
+                .await
+                .expect("failed to validate")
+        );
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn delete() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let alice = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let bob = User::new("bob", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut own = Lot::new("own");
+        own.save(&db, &alice).await.expect("failed to save lot");
+        let mut shared = Lot::new("shared");
+        shared.save(&db, &alice).await.expect("failed to save lot");
+        shared
+            .share(&db, &bob, Permission::Write)
+            .await
+            .expect("failed to share lot");
+
+        let alice = User::load(&db, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load user");
+        let same = User::load(&db, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load user");
+        assert!(matches!(
+            alice.delete(&db, "hunter22".try_into().unwrap()).await,
+            Err(Error::Invalid)
+        ));
+        same.delete(&db, "password".try_into().unwrap())
+            .await
+            .expect("failed to delete user");
+
+        assert_eq!(
+            vec!["bob".to_owned()],
+            User::list(&db).await.expect("failed to list")
+        );
+        let lots: Vec<String> = lot::orm::Entity::find()
+            .all(db.connection())
+            .await
+            .expect("failed to list lots")
+            .into_iter()
+            .map(|m| m.uuid)
+            .collect();
+        assert_eq!(vec![shared.uuid().to_string()], lots);
+        assert!(
+            Lot::load(&db, "shared", &bob)
+                .await
+                .expect("failed to load lot")
+                .is_some()
+        );
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn delete_keeps_shared_child() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let alice = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let bob = User::new("bob", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut work = Lot::new("work");
+        work.save(&db, &alice).await.expect("failed to save lot");
+        let mut aws = Lot::new_child("aws", &work);
+        aws.save(&db, &alice).await.expect("failed to save lot");
+        aws.share(&db, &bob, Permission::Write)
+            .await
+            .expect("failed to share lot");
+
+        alice
+            .delete(&db, "password".try_into().unwrap())
+            .await
+            .expect("failed to delete user");
+
+        // `work` has no members left, but deleting it would cascade to
+        // the `aws` bob still has.
+        let lots = lot::orm::Entity::find()
+            .count(db.connection())
+            .await
+            .expect("failed to count lots");
+        assert_eq!(2, lots);
+        let loaded = Lot::load(&db, "aws", &bob)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        assert_eq!(aws.uuid(), loaded.uuid());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn register_with_default_lot() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (user, lot) = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        assert_eq!(lot::DEFAULT_LOT, lot.name());
+
+        let lots = user.lots(&db).await.expect("failed to load lots");
+        assert_eq!(1, lots.len());
+        assert_eq!(lot::DEFAULT_LOT, lots[0].name());
+        let loaded = Lot::load(&db, lot::DEFAULT_LOT, &user)
+            .await
+            .expect("failed to load lot")
+            .expect("missing default lot");
+        assert_eq!(lot.uuid(), loaded.uuid());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn records_with_tag() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (user, mut main) = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        let mut work = Lot::new("work");
+        work.save(&db, &user).await.expect("failed to save lot");
+        for (lot, name, tags) in [
+            ("main", "email", vec!["work", "daily"]),
+            ("main", "bank", vec![]),
+            ("work", "vpn", vec!["work"]),
+        ] {
+            let lot = if lot == "main" { &mut main } else { &mut work };
+            let data = Data::new("secret".try_into().unwrap()).with_tags(tags);
+            Record::new(lot, name.parse().unwrap(), data)
+                .save(&db, lot)
+                .await
+                .expect("failed to save record");
+        }
+
+        let mut names: Vec<String> = user
+            .records_with_tag(&db, "work")
+            .await
+            .expect("failed to filter")
+            .iter()
+            .map(|r| r.label().name().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(vec!["email", "vpn"], names);
+        assert!(
+            user.records_with_tag(&db, "home")
+                .await
+                .expect("failed to filter")
+                .is_empty()
+        );
+        let daily = main
+            .records_with_tag("daily")
+            .await
+            .expect("failed to filter");
+        assert_eq!(1, daily.len());
+        assert_eq!(vec!["work", "daily"], daily[0].data().tags());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn change_password() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let user = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut lot = Lot::new("main");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        let totp = user.enroll_totp(&db).await.expect("failed to enroll");
+
+        // Another long-lived session holding the same key.
+        let session =
+            User::load_with_totp(&db, "alice", "password".try_into().unwrap(), &totp.code())
+                .await
+                .expect("failed to load user");
+        assert!(!session.is_stale(&db).await.expect("failed to check"));
+
+        let changed = user
+            .change_password(&db, "new password".try_into().unwrap())
+            .await
+            .expect("failed to change password");
+        assert_eq!(1, changed.key_version());
+        assert!(!changed.is_stale(&db).await.expect("failed to check"));
+        assert!(session.is_stale(&db).await.expect("failed to check"));
+        assert!(matches!(
+            session
+                .change_password(&db, "other".try_into().unwrap())
+                .await,
+            Err(Error::StaleKey)
+        ));
+
+        assert!(matches!(
+            User::load_with_totp(&db, "alice", "password".try_into().unwrap(), &totp.code()).await,
+            Err(Error::Invalid)
+        ));
+        let loaded = User::load_with_totp(
+            &db,
+            "alice",
+            "new password".try_into().unwrap(),
+            &totp.code(),
+        )
+        .await
+        .expect("failed to load with new password");
+        let reloaded = Lot::load(&db, "main", &loaded)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        assert_eq!(reloaded, lot);
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rehash() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let weak = KdfParams {
+            memory_kib: 8 * 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let user = User::with_kdf("alice", "password".try_into().unwrap(), weak)
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        assert!(user.needs_rehash());
+        let mut lot = Lot::new("main");
+        lot.save(&db, &user).await.expect("failed to save lot");
+        Record::new(
+            &lot,
+            "a".parse::<Label>().unwrap(),
+            Data::new("1".try_into().unwrap()),
+        )
+        .save(&db, &mut lot)
+        .await
+        .expect("failed to save record");
+
+        let same = User::load(&db, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load user");
+        assert!(matches!(
+            same.rehash(&db, "hunter22".try_into().unwrap()).await,
+            Err(Error::Invalid)
+        ));
+        let rehashed = user
+            .rehash(&db, "password".try_into().unwrap())
+            .await
+            .expect("failed to rehash");
+        assert!(!rehashed.needs_rehash());
+        assert_eq!(KdfParams::default().memory_kib, rehashed.kdf().memory_kib);
+        assert_eq!(1, rehashed.key_version());
+
+        let loaded = User::load(&db, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load user");
+        assert_eq!(rehashed.kdf(), loaded.kdf());
+        let reloaded = Lot::load(&db, "main", &loaded)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        assert_eq!(reloaded, lot);
+        let (_, uuid) = reloaded.index().iter().next().expect("no record");
+        let record = Record::show(&db, &reloaded, uuid)
+            .await
+            .expect("failed to show record")
+            .expect("no record");
+        assert_eq!("1", record.password().expose());
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rewrap_for() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        let (alice, mut lot) = User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+        let record = Record::new(
+            &lot,
+            "a".parse::<Label>().unwrap(),
+            Data::new("1".try_into().unwrap()),
+        );
+        record
+            .save(&db, &mut lot)
+            .await
+            .expect("failed to save record");
+
+        // A second device's key, as if derived from a different salt.
+        let device_key = Key::<User>::generate();
+        lot.rewrap_for(&db, &alice, &device_key)
+            .await
+            .expect("failed to rewrap");
+        assert!(Lot::load(&db, lot::DEFAULT_LOT, &alice).await.is_err());
+
+        let mut device = User::load(&db, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to load user");
+        device.key = Key::from_bytes(&device_key.expose_bytes());
+        let loaded = Lot::load(&db, lot::DEFAULT_LOT, &device)
+            .await
+            .expect("failed to load lot")
+            .expect("no lot");
+        let shown = Record::show(&db, &loaded, record.uuid())
+            .await
+            .expect("failed to show record")
+            .expect("no record");
+        assert_eq!("1", shown.password().expose());
+    }
 }
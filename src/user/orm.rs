@@ -9,6 +9,17 @@ pub struct Model {
     pub salt: Vec<u8>,
     pub validation_data: Vec<u8>,
     pub validation_nonce: Vec<u8>,
+    /// TOTP secret encrypted under the user key, see
+    /// [`User::enroll_totp`](crate::user::User::enroll_totp).
+    pub totp_data: Option<Vec<u8>>,
+    pub totp_nonce: Option<Vec<u8>>,
+    /// Bumped by each [`User::change_password`](crate::user::User::change_password).
+    pub key_version: i64,
+    /// Argon2 cost parameters, see
+    /// [`KdfParams`](crate::encrypt::KdfParams).
+    pub kdf_memory: i64,
+    pub kdf_iterations: i64,
+    pub kdf_parallelism: i64,
 
     #[sea_orm(has_many, relation_enum = "UserLot", via_rel = "User")]
     pub user_lots: HasMany<crate::lot::orm::user_lots::Entity>,
@@ -41,3 +52,21 @@ impl Linked for UserToRecords {
         ]
     }
 }
+
+/// A user's key wrapped under their recovery phrase, see
+/// [`User::generate_recovery`](crate::user::User::generate_recovery).
+pub mod recovery {
+    use sea_orm::entity::prelude::*;
+
+    #[sea_orm::model]
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "user_recovery")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub username: String,
+        pub data: Vec<u8>,
+        pub nonce: Vec<u8>,
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
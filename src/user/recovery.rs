@@ -0,0 +1,173 @@
+use super::{Error, User};
+use crate::{
+    db::{Database, VaultEvent},
+    encrypt::{Encrypted, Key},
+    lot,
+    password::Password,
+    uuid::Uuid,
+};
+use bip39::Mnemonic;
+use rand_core::{OsRng, RngCore};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, sea_query::OnConflict};
+use std::{fmt, str::FromStr};
+use zeroize::Zeroizing;
+
+/// Bytes of entropy in a [`RecoveryPhrase`], 24 words' worth.
+const RECOVERY_ENTROPY: usize = 32;
+
+/// Tags the key a [`RecoveryBlob`] is sealed under.
+struct Recovery;
+
+/// A random recovery key, written down as 24 BIP-39 words, which opens
+/// the [`RecoveryBlob`] from [`User::generate_recovery`].
+///
+/// Like a [`Password`] it never prints: `{:?}` is redacted and the words
+/// are only handed out by [`RecoveryPhrase::words`]. Parse one back from
+/// the words with [`str::parse`].
+pub struct RecoveryPhrase(Zeroizing<[u8; RECOVERY_ENTROPY]>);
+
+impl RecoveryPhrase {
+    fn generate() -> Self {
+        let mut entropy = Zeroizing::new([0; RECOVERY_ENTROPY]);
+        OsRng.fill_bytes(&mut *entropy);
+        RecoveryPhrase(entropy)
+    }
+
+    /// The words, separated by single spaces, for the user to write down.
+    pub fn words(&self) -> Zeroizing<String> {
+        let mnemonic = Mnemonic::from_entropy(&*self.0).expect("entropy is a valid length");
+        Zeroizing::new(mnemonic.to_string())
+    }
+
+    fn key(&self) -> Key<Recovery> {
+        Key::from_bytes(&*self.0)
+    }
+}
+
+/// Words from the BIP-39 English list, in any case and with any
+/// whitespace between them. Anything else, including a typo the checksum
+/// catches, is [`Error::InvalidRecoveryPhrase`].
+impl FromStr for RecoveryPhrase {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = Zeroizing::new(s.split_whitespace().collect::<Vec<_>>().join(" "));
+        let mnemonic = Mnemonic::parse_normalized(&Zeroizing::new(words.to_lowercase()))
+            .map_err(|_| Error::InvalidRecoveryPhrase)?;
+        let entropy = Zeroizing::new(mnemonic.to_entropy());
+        let entropy = <[u8; RECOVERY_ENTROPY]>::try_from(entropy.as_slice())
+            .map_err(|_| Error::InvalidRecoveryPhrase)?;
+        Ok(RecoveryPhrase(Zeroizing::new(entropy)))
+    }
+}
+
+impl fmt::Debug for RecoveryPhrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RecoveryPhrase(***)")
+    }
+}
+
+/// A user's key sealed under a [`RecoveryPhrase`], as stored in the
+/// `user_recovery` table.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RecoveryBlob(Encrypted);
+
+impl RecoveryBlob {
+    pub fn encrypted(&self) -> &Encrypted {
+        &self.0
+    }
+}
+
+impl User {
+    /// Opt in to recovering this account without its password: seal the
+    /// user key under a fresh [`RecoveryPhrase`] and store the blob,
+    /// replacing any earlier one, so [`User::recover`] can open it.
+    ///
+    /// The phrase is the only copy, show it to the user once. Anyone with
+    /// it can take over the account, just as with the password. A phrase
+    /// opens the key it was made for, so [`User::change_password`] (which
+    /// `recover` calls) and [`User::rename`] drop the blob, and a new
+    /// phrase has to be generated after them.
+    ///
+    /// Logged as a [`VaultEvent::RecoveryGenerated`] for each of the
+    /// user's lots, since the phrase opens all of them.
+    pub async fn generate_recovery(
+        &self,
+        db: &Database,
+    ) -> Result<(RecoveryPhrase, RecoveryBlob), Error> {
+        db.check_writable()?;
+        let phrase = RecoveryPhrase::generate();
+        let blob = RecoveryBlob(
+            phrase
+                .key()
+                .encrypt_with_aad(self.key.as_bytes(), &User::recovery_aad(&self.username))?,
+        );
+        let active = super::orm::recovery::ActiveModel {
+            username: Set(self.username.clone()),
+            data: Set(blob.0.data.clone()),
+            nonce: Set(blob.0.nonce.clone()),
+        };
+        let events = db
+            .transaction(async |txn| -> Result<Vec<VaultEvent>, Error> {
+                super::orm::recovery::Entity::insert(active.clone())
+                    .on_conflict(
+                        OnConflict::column(super::orm::recovery::Column::Username)
+                            .update_columns([
+                                super::orm::recovery::Column::Data,
+                                super::orm::recovery::Column::Nonce,
+                            ])
+                            .to_owned(),
+                    )
+                    .exec(txn)
+                    .await?;
+                let events = lot::orm::user_lots::Entity::find()
+                    .filter(lot::orm::user_lots::Column::Username.eq(self.username()))
+                    .all(txn)
+                    .await?
+                    .into_iter()
+                    .map(|ul| {
+                        Ok(VaultEvent::RecoveryGenerated {
+                            lot: Uuid::parse(&ul.lot_uuid).map_err(lot::Error::from)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Database::log(txn, &events).await?;
+                Ok(events)
+            })
+            .await?;
+        db.notify(events);
+        Ok((phrase, blob))
+    }
+
+    /// Unlock `username` with the [`RecoveryPhrase`] from
+    /// [`User::generate_recovery`] and set `new_password`, as
+    /// [`User::change_password`] would with the old one.
+    ///
+    /// A wrong phrase, or a user without a current recovery blob, is
+    /// [`Error::Invalid`]. Holding the phrase is as good as the password
+    /// and TOTP code together, so no code is asked for. Once this
+    /// succeeds the phrase is spent.
+    pub async fn recover(
+        db: &Database,
+        username: &str,
+        phrase: &RecoveryPhrase,
+        new_password: Password,
+    ) -> Result<Self, Error> {
+        db.check_writable()?;
+        let model = super::orm::recovery::Entity::find_by_id(username.to_owned())
+            .one(db.connection())
+            .await?
+            .ok_or(Error::Invalid)?;
+        let blob = Encrypted::from_parts(model.data, model.nonce)?;
+        let key = phrase
+            .key()
+            .decrypt_with_aad(&blob, &User::recovery_aad(username))
+            .map_err(|_| Error::Invalid)?;
+        let user = User::resume(db, username, Key::from_bytes(&key)).await?;
+        user.change_password(db, new_password).await
+    }
+
+    fn recovery_aad(username: &str) -> Vec<u8> {
+        [b"r".as_slice(), username.as_bytes()].concat()
+    }
+}
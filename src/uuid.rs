@@ -42,6 +42,14 @@ impl<T> fmt::Display for Uuid<T> {
     }
 }
 
+/// As its hyphenated string, e.g. in [`AuditEntry`](crate::db::AuditEntry)
+/// JSON.
+impl<T> serde::Serialize for Uuid<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 impl<T> Clone for Uuid<T> {
     fn clone(&self) -> Self {
         Uuid(self.0, PhantomData)
@@ -0,0 +1,282 @@
+//! A single user's unlocked view of the database.
+//!
+//! [`Vault`] bundles a [`Database`] with one unlocked [`User`] and caches
+//! the lots it opens, so callers don't re-thread `db`, `user` and lot
+//! loading by hand. It is the library-level API for one user at a time;
+//! the [`EmbeddedHandler`] serves many users over the request protocol.
+//!
+//! [`EmbeddedHandler`]: crate::protocol::EmbeddedHandler
+
+use crate::{
+    db::Database,
+    lot::{self, Lot, LotSummary, LotTree, PATH_SEPARATOR},
+    password::Password,
+    record::{self, Data, Label, Path, Query, Record},
+    user::{self, User},
+    uuid::Uuid,
+};
+use std::collections::HashMap;
+use std::fmt;
+
+pub struct Vault {
+    db: Database,
+    /// `None` once [`Vault::lock`]ed.
+    user: Option<User>,
+    /// Lots opened so far, keyed by their full [`PATH_SEPARATOR`] path.
+    lots: HashMap<String, Lot>,
+}
+
+impl Vault {
+    /// Unlock `username` with `password`, see [`User::load`].
+    pub async fn unlock(db: Database, username: &str, password: Password) -> Result<Self, Error> {
+        let user = User::load(&db, username, password).await?;
+        Ok(Vault::from_user(db, user))
+    }
+
+    /// Wrap an already unlocked user, e.g. one just registered or
+    /// unlocked with [`User::load_with_totp`].
+    pub fn from_user(db: Database, user: User) -> Self {
+        Vault {
+            db,
+            user: Some(user),
+            lots: HashMap::new(),
+        }
+    }
+
+    /// Drop the user key and every cached lot key. Keys zeroize on drop,
+    /// so nothing secret outlives this call; every later operation
+    /// returns [`Error::Locked`].
+    pub fn lock(&mut self) {
+        self.lots.clear();
+        self.user = None;
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.user.is_none()
+    }
+
+    pub fn db(&self) -> &Database {
+        &self.db
+    }
+
+    pub fn user(&self) -> Result<&User, Error> {
+        self.user.as_ref().ok_or(Error::Locked)
+    }
+
+    /// The user's lots, see [`User::lots_summary`]. Opens none of them.
+    pub async fn lots(&self) -> Result<Vec<LotSummary>, Error> {
+        Ok(self.user()?.lots_summary(&self.db).await?)
+    }
+
+    /// Open the lot at `path` (e.g. `main` or `work::aws`), loading it on
+    /// first use.
+    pub async fn lot(&mut self, path: &str) -> Result<&mut Lot, Error> {
+        if !self.lots.contains_key(path) {
+            let user = self.user.as_ref().ok_or(Error::Locked)?;
+            let lot = Lot::load(&self.db, path, user)
+                .await?
+                .ok_or_else(|| lot::Error::NotFound(path.to_owned()))?;
+            self.lots.insert(path.to_owned(), lot);
+        }
+        Ok(self.lots.get_mut(path).expect("lot was just cached"))
+    }
+
+    /// Store `data` at `path`, replacing the record already there (and
+    /// extending its history) if the label name is taken.
+    pub async fn put(&mut self, path: &Path, data: Data) -> Result<Record, Error> {
+        self.user()?;
+        let db = self.db.clone();
+        let lot = self.lot(&path.lot).await?;
+        let record = match lot.index().find_by_name(path.label.name()).cloned() {
+            Some(uuid) => Record::with_uuid(uuid, lot, path.label.clone(), data),
+            None => Record::new(lot, path.label.clone(), data),
+        };
+        record.save(&db, lot).await?;
+        Ok(record)
+    }
+
+    /// The record whose label name matches `path`, if any.
+    pub async fn get(&mut self, path: &Path) -> Result<Option<Record>, Error> {
+        let db = self.db.clone();
+        let lot = self.lot(&path.lot).await?;
+        let Some(uuid) = lot.index().find_by_name(path.label.name()).cloned() else {
+            return Ok(None);
+        };
+        Ok(Record::show(&db, lot, &uuid).await?)
+    }
+
+    /// Every record, across all of the user's lots, matching `query`.
+    /// Only labels are searched, so no record is decrypted.
+    pub async fn search(&mut self, query: &Query) -> Result<Vec<(Path, Uuid<Record>)>, Error> {
+        let user = self.user.as_ref().ok_or(Error::Locked)?;
+        let mut paths = Vec::new();
+        flatten(user.lot_tree(&self.db).await?, None, &mut paths);
+        let mut results = Vec::new();
+        for (path, lot) in paths {
+            if !query.matches_lot(&path) {
+                continue;
+            }
+            let lot = self.lots.entry(path.clone()).or_insert(lot);
+            for (label, uuid) in lot.index().search(query) {
+                results.push((Path::new(path.clone(), label.clone()), uuid.clone()));
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// Pair each lot in `trees` with its full path.
+fn flatten(trees: Vec<LotTree>, parent: Option<&str>, out: &mut Vec<(String, Lot)>) {
+    for tree in trees {
+        let path = match parent {
+            Some(parent) => format!("{parent}{PATH_SEPARATOR}{}", tree.lot.name()),
+            None => tree.lot.name().to_owned(),
+        };
+        out.push((path.clone(), tree.lot));
+        flatten(tree.children, Some(&path), out);
+    }
+}
+
+impl fmt::Debug for Vault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Vault")
+            .field("user", &self.user)
+            .field("lots", &self.lots.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The vault has been [`Vault::lock`]ed.
+    Locked,
+    User(user::Error),
+    Lot(lot::Error),
+    Record(record::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Locked => write!(f, "vault is locked"),
+            Error::User(e) => write!(f, "user: {e}"),
+            Error::Lot(e) => write!(f, "lot: {e}"),
+            Error::Record(e) => write!(f, "record: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Locked => None,
+            Error::User(e) => Some(e),
+            Error::Lot(e) => Some(e),
+            Error::Record(e) => Some(e),
+        }
+    }
+}
+
+impl From<user::Error> for Error {
+    fn from(err: user::Error) -> Self {
+        Error::User(err)
+    }
+}
+
+impl From<lot::Error> for Error {
+    fn from(err: lot::Error) -> Self {
+        Error::Lot(err)
+    }
+}
+
+impl From<record::Error> for Error {
+    fn from(err: record::Error) -> Self {
+        Error::Record(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn unlock_put_get_lock() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register_with_default_lot(&db)
+            .await
+            .expect("failed to register user");
+
+        let mut vault = Vault::unlock(db.clone(), "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to unlock");
+        let path = Path::from_str("alice@example.com").expect("failed to parse path");
+        vault
+            .put(&path, Data::new("first".try_into().unwrap()))
+            .await
+            .expect("failed to put");
+        let put = vault
+            .put(&path, Data::new("second".try_into().unwrap()))
+            .await
+            .expect("failed to put");
+
+        let got = vault
+            .get(&path)
+            .await
+            .expect("failed to get")
+            .expect("no record");
+        assert_eq!(put.uuid(), got.uuid());
+        assert_eq!("second", got.password().expose());
+        assert!(
+            vault
+                .get(&Path::from_str("nobody").unwrap())
+                .await
+                .expect("failed to get")
+                .is_none()
+        );
+
+        let found = vault
+            .search(&Query::from_str("~.*::~example").unwrap())
+            .await
+            .expect("failed to search");
+        assert_eq!(vec![(path.clone(), put.uuid().clone())], found);
+        assert_eq!(
+            1,
+            vault.lots().await.expect("failed to list lots")[0].record_count
+        );
+
+        vault.lock();
+        assert!(vault.is_locked());
+        assert!(matches!(vault.get(&path).await, Err(Error::Locked)));
+        assert!(matches!(vault.lots().await, Err(Error::Locked)));
+        assert!(matches!(
+            vault
+                .put(&path, Data::new("third".try_into().unwrap()))
+                .await,
+            Err(Error::Locked)
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn missing_lot() {
+        let db = Database::new("sqlite://:memory:")
+            .await
+            .expect("failed to create database");
+        User::new("alice", "password".try_into().unwrap())
+            .expect("failed to make user")
+            .register(&db)
+            .await
+            .expect("failed to register user");
+        let mut vault = Vault::unlock(db, "alice", "password".try_into().unwrap())
+            .await
+            .expect("failed to unlock");
+        assert!(matches!(
+            vault.lot("nope").await,
+            Err(Error::Lot(lot::Error::NotFound(_)))
+        ));
+    }
+}
@@ -0,0 +1,158 @@
+//! Integration tests for [`valet::agent`], speaking HTTP/1.1 to the
+//! agent's Unix socket by hand.
+
+use serde_json::{Value, json};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use valet::agent::Agent;
+use valet::db::Database;
+use valet::record::Data;
+use valet::user::User;
+use valet::vault::Vault;
+
+/// `POST` `body` to `route`, returning the status and the (possibly
+/// empty) JSON reply.
+async fn post(socket: &Path, route: &str, body: Value) -> (u16, Value) {
+    let body = body.to_string();
+    let mut stream = UnixStream::connect(socket).await.expect("connect");
+    let request = format!(
+        "POST {route} HTTP/1.1\r\n\
+         Host: localhost\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.expect("write");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.expect("read");
+
+    let (head, body) = response.split_once("\r\n\r\n").expect("no header end");
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .expect("no status");
+    let body = if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_str(body).expect("reply isn't json")
+    };
+    (status, body)
+}
+
+/// A database holding alice, with one record in her default lot.
+async fn database() -> Database {
+    let db = Database::new("sqlite://:memory:")
+        .await
+        .expect("open in-memory db");
+    let (alice, _) = User::new("alice", "sesame".try_into().unwrap())
+        .expect("new user")
+        .register_with_default_lot(&db)
+        .await
+        .expect("register user");
+    Vault::from_user(db.clone(), alice)
+        .put(
+            &"alice@example.com".parse().unwrap(),
+            Data::new("hunter22".try_into().unwrap()),
+        )
+        .await
+        .expect("put record");
+    db
+}
+
+async fn spawn(agent: std::sync::Arc<Agent>, socket: &Path) -> tokio::task::JoinHandle<()> {
+    let path = socket.to_owned();
+    let task = tokio::spawn(async move {
+        let _ = agent.serve(&path).await;
+    });
+    for _ in 0..100 {
+        if UnixStream::connect(socket).await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    task
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unlock_search_get_lock() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let socket = dir.path().join("agent.sock");
+    let task = spawn(
+        Agent::new(database().await, valet::agent::DEFAULT_IDLE_TIMEOUT),
+        &socket,
+    )
+    .await;
+
+    let mode = std::fs::metadata(&socket)
+        .expect("no socket")
+        .permissions()
+        .mode();
+    assert_eq!(0o600, mode & 0o777);
+
+    let get = json!({ "path": "alice@example.com" });
+    assert_eq!(401, post(&socket, "/get", get.clone()).await.0);
+    let (status, reply) = post(
+        &socket,
+        "/unlock",
+        json!({ "username": "alice", "password": "wrong" }),
+    )
+    .await;
+    assert_eq!(401, status);
+    assert_eq!("unauthorized", reply["error"]);
+    let unlock = json!({ "username": "alice", "password": "sesame" });
+    assert_eq!(204, post(&socket, "/unlock", unlock).await.0);
+
+    let (status, hits) = post(&socket, "/search", json!({ "query": "~.*::~example" })).await;
+    assert_eq!(200, status);
+    assert_eq!(1, hits.as_array().expect("hits aren't a list").len());
+    assert_eq!("main::alice@example.com", hits[0]["path"]);
+
+    assert_eq!(
+        (200, json!({ "value": "hunter22" })),
+        post(&socket, "/get", get.clone()).await
+    );
+    assert_eq!(
+        (200, json!({ "value": "alice" })),
+        post(
+            &socket,
+            "/get",
+            json!({ "path": "alice@example.com", "field": "username" })
+        )
+        .await
+    );
+    assert_eq!(
+        404,
+        post(&socket, "/get", json!({ "path": "nobody" })).await.0
+    );
+
+    assert_eq!(204, post(&socket, "/lock", Value::Null).await.0);
+    assert_eq!(401, post(&socket, "/get", get).await.0);
+    task.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn idle_timeout_locks() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let socket = dir.path().join("agent.sock");
+    let task = spawn(
+        Agent::new(database().await, Duration::from_millis(100)),
+        &socket,
+    )
+    .await;
+
+    let unlock = json!({ "username": "alice", "password": "sesame" });
+    assert_eq!(204, post(&socket, "/unlock", unlock).await.0);
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert_eq!(
+        401,
+        post(&socket, "/get", json!({ "path": "alice@example.com" }))
+            .await
+            .0
+    );
+    task.abort();
+}
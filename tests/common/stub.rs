@@ -157,6 +157,7 @@ fn dispatch(st: &mut StubState, req: Request) -> Response {
         Request::CreateLot { .. } => Response::Error("stub: create_lot not supported".into()),
         Request::DeleteLot { .. } => Response::Error("stub: delete_lot not supported".into()),
         Request::History { .. } => Response::Error("stub: history not supported".into()),
+        Request::ListByLot { .. } => Response::Error("stub: list_by_lot not supported".into()),
     }
 }
 
@@ -8,8 +8,8 @@
 use crate::common::embedded_client_with_user;
 use valet::SendHandler;
 use valet::protocol::message::{
-    CreateRecord, Fetch, FindRecords, GenerateRecord, List, ListLots, ListUsers, Lock, LockAll,
-    Register, Status, Unlock,
+    CreateLot, CreateRecord, Fetch, FindRecords, GenerateRecord, List, ListByLot, ListLots,
+    ListUsers, Lock, LockAll, Register, Status, Unlock,
 };
 
 #[tokio::test(flavor = "multi_thread")]
@@ -113,7 +113,7 @@ async fn create_and_fetch_record() {
         .await
         .unwrap();
     assert_eq!(fetched.uuid().to_uuid(), created.uuid().to_uuid());
-    assert_eq!(fetched.password().to_string(), "hunter2");
+    assert_eq!(fetched.password().expose(), "hunter2");
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -141,6 +141,51 @@ async fn list_returns_created_records() {
     assert_eq!(entries.len(), 2);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn list_by_lot_groups_records() {
+    let client = embedded_client_with_user("alice", "sesame").await;
+    client
+        .call(CreateLot {
+            username: "alice".into(),
+            lot: "work".into(),
+        })
+        .await
+        .unwrap();
+    for (lot, host) in [(valet::lot::DEFAULT_LOT, "a.com"), ("work", "b.com")] {
+        client
+            .call(CreateRecord {
+                username: "alice".into(),
+                lot: lot.into(),
+                label: host.parse().unwrap(),
+                password: "pw".try_into().unwrap(),
+                extra: Default::default(),
+            })
+            .await
+            .unwrap();
+    }
+    let mut grouped: Vec<(String, Vec<String>)> = client
+        .call(ListByLot {
+            username: "alice".into(),
+            queries: vec![],
+        })
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(lot, entries)| {
+            let names = entries.iter().map(|(_, l)| l.name().to_string()).collect();
+            (lot, names)
+        })
+        .collect();
+    grouped.sort();
+    assert_eq!(
+        vec![
+            (valet::lot::DEFAULT_LOT.to_owned(), vec!["a.com".to_owned()]),
+            ("work".to_owned(), vec!["b.com".to_owned()]),
+        ],
+        grouped
+    );
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn find_records_domain_suffix() {
     let client = embedded_client_with_user("alice", "sesame").await;
@@ -71,7 +71,7 @@ async fn native_msg_relay_through_socket_server_to_embedded() {
     match envelope::payload_response(&reply) {
         Response::Record(r) => {
             assert_eq!(r.uuid().to_uuid(), created.uuid().to_uuid());
-            assert_eq!(r.password().to_string(), "hunter2");
+            assert_eq!(r.password().expose(), "hunter2");
         }
         other => panic!("unexpected {other:?}"),
     }
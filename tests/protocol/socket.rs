@@ -54,7 +54,7 @@ async fn roundtrip_covers_status_and_fetch() {
         .await
         .unwrap();
     assert_eq!(fetched.uuid().to_uuid(), uuid.to_uuid());
-    assert_eq!(fetched.password().to_string(), "correct horse battery");
+    assert_eq!(fetched.password().expose(), "correct horse battery");
 
     drop(socket_client);
     server_task.abort();
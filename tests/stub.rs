@@ -99,7 +99,7 @@ async fn fetch_returns_record() {
         .await
         .unwrap();
     assert_eq!(record.uuid().to_uuid(), uuid.to_uuid());
-    assert_eq!(record.password().to_string(), "hunter22");
+    assert_eq!(record.password().expose(), "hunter22");
 }
 
 #[tokio::test(flavor = "multi_thread")]